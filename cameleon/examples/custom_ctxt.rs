@@ -91,13 +91,11 @@ impl MyGenApiCtxt {
 /// `Camera::convert_into`.
 impl From<DefaultGenApiCtxt> for MyGenApiCtxt {
     fn from(from: DefaultGenApiCtxt) -> Self {
-        let value_ctxt = ValueCtxt::new(
-            from.value_ctxt.value_store,
-            MyCacheStore {
-                store: from.value_ctxt.cache_store,
-                use_cache: true,
-            },
-        );
+        // `ValueCtxt::map_cache_store` keeps event ports bound to the context.
+        let value_ctxt = from.value_ctxt.map_cache_store(|store| MyCacheStore {
+            store,
+            use_cache: true,
+        });
         Self {
             node_store: from.node_store,
             value_ctxt,
@@ -57,12 +57,18 @@
 //! camera.close().unwrap();
 //! ```
 
+use std::sync::{Arc, Mutex, MutexGuard};
+
 use auto_impl::auto_impl;
 use tracing::info;
 
 use super::{
+    event::{self, EventReceiver, EventSender},
     genapi::{DefaultGenApiCtxt, FromXml, GenApiCtxt, ParamsCtxt},
-    payload::{channel, PayloadReceiver, PayloadSender},
+    payload::{
+        channel_with_buffers, channel_with_config, BufferPool, PayloadReceiver, PayloadSender,
+        StreamConfig,
+    },
     CameleonError, CameleonResult, ControlResult, StreamError, StreamResult,
 };
 
@@ -125,6 +131,9 @@ pub struct Camera<Ctrl, Strm, Ctxt = DefaultGenApiCtxt> {
     pub strm: Strm,
     /// `GenApi context` of the camera.
     pub ctxt: Option<Ctxt>,
+    /// Event stream handle of the camera. `None` if the camera doesn't support event
+    /// notification.
+    pub evt: Option<SharedEventStream>,
     /// Information of the camera.
     info: CameraInfo,
 }
@@ -171,6 +180,9 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
         info!("try opening the device");
         self.ctrl.open()?;
         self.strm.open()?;
+        if let Some(evt) = &mut self.evt {
+            evt.open()?;
+        }
         info!("opened the device successfully");
         Ok(())
     }
@@ -205,8 +217,12 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
     {
         info!("try closing the device");
         self.stop_streaming()?;
+        self.stop_event()?;
         self.ctrl.close()?;
         self.strm.close()?;
+        if let Some(evt) = &mut self.evt {
+            evt.close()?;
+        }
         if let Some(ctxt) = &mut self.ctxt {
             ctxt.clear_cache()
         }
@@ -262,6 +278,7 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
     /// # Examples
     /// ```rust
     /// # use cameleon::u3v;
+    /// use cameleon::payload::{OverflowPolicy, StreamConfig};
     /// # let mut cameras = u3v::enumerate_cameras().unwrap();
     /// # if cameras.is_empty() {
     /// #     return;
@@ -275,46 +292,100 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
     /// // The streamed payload can be received like below:
     /// // payload_rx.recv().await.unwrap() or
     /// // payload.rx.try_recv().unwrap();
+    /// camera.stop_streaming().unwrap();
+    ///
+    /// // Start streaming which always keeps the latest frames in the channel.
+    /// let config = StreamConfig {
+    ///     overflow_policy: OverflowPolicy::DropOldest,
+    ///     ..StreamConfig::new(3)
+    /// };
+    /// let payload_rx = camera.start_streaming(config).unwrap();
     ///
     /// // Closes the camera.
     /// camera.close().unwrap();
     /// ```
     ///
     /// # Arguments
-    /// * `cap` - A capacity of the paylaod receiver, the sender will stop to send a payload when it
-    /// gets full.
+    /// * `config` - A configuration of payload delivery, see [`StreamConfig`]. `usize` is
+    /// regarded as a capacity of the paylaod receiver.
     ///
     ///
     /// # Panics
-    /// If `cap` is zero, this method will panic.
-    #[tracing::instrument(skip(self),
+    /// If channel capacity or buffer pool size is zero, this method will panic.
+    #[tracing::instrument(skip(self, config),
                           level = "info",
                           fields(camera = ?self.info()))]
-    pub fn start_streaming(&mut self, cap: usize) -> CameleonResult<PayloadReceiver>
+    pub fn start_streaming(
+        &mut self,
+        config: impl Into<StreamConfig>,
+    ) -> CameleonResult<PayloadReceiver>
     where
         Ctrl: DeviceControl,
         Strm: PayloadStream,
         Ctxt: GenApiCtxt,
     {
-        const DEFAULT_BUFFER_CAP: usize = 5;
-        info!("try starting streaming");
-
-        if self.strm.is_loop_running() {
-            return Err(StreamError::InStreaming.into());
-        }
-
-        // Enable streaimng.
-        self.ctrl.enable_streaming()?;
-        let mut ctxt = self.params_ctxt()?;
-        expect_node!(&ctxt, "TLParamsLocked", as_integer).set_value(&mut ctxt, 1)?;
-        expect_node!(&ctxt, "AcquisitionStart", as_command).execute(&mut ctxt)?;
-
-        // Start streaming loop.
-        let (sender, receiver) = channel(cap, DEFAULT_BUFFER_CAP);
-        self.strm.start_streaming_loop(sender, &mut self.ctrl)?;
+        let config = config.into();
+        info!(?config, "try starting streaming");
+        self.start_streaming_with_channel(channel_with_config(config))
+    }
 
-        info!("start streaming successfully");
-        Ok(receiver)
+    /// Starts streaming which fills only buffers announced in `pool`, and returns the receiver for
+    /// the `Payload`.
+    ///
+    /// All buffers in `pool` are queued when streaming starts, and a buffer is queued again by
+    /// sending back the payload with [`PayloadReceiver::send_back`]. Frames arriving while no
+    /// buffer is queued are dropped. See [`BufferPool`] and [`Self::start_streaming`] for more
+    /// details.
+    ///
+    /// Streaming doesn't start and [`StreamError::BufferTooSmall`] is returned if any buffer is
+    /// smaller than the maximum payload size of the device. The buffers can be taken back by
+    /// [`PayloadReceiver::revoke_buffers`] after [`Self::stop_streaming`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use cameleon::u3v;
+    /// use cameleon::payload::BufferPool;
+    /// # let mut cameras = u3v::enumerate_cameras().unwrap();
+    /// # if cameras.is_empty() {
+    /// #     return;
+    /// # }
+    /// # let mut camera = cameras.pop().unwrap();
+    /// camera.open().unwrap();
+    /// camera.load_context().unwrap();
+    ///
+    /// // Announce 4 buffers allocated by the application.
+    /// let mut pool = BufferPool::new();
+    /// for _ in 0..4 {
+    ///     pool.announce(vec![0u8; 640 * 480]);
+    /// }
+    /// let payload_rx = camera.start_streaming_with_buffers(3, pool).unwrap();
+    ///
+    /// // Take back the buffers after streaming is stopped.
+    /// camera.stop_streaming().unwrap();
+    /// let pool = payload_rx.revoke_buffers().unwrap();
+    ///
+    /// // Closes the camera.
+    /// camera.close().unwrap();
+    /// ```
+    ///
+    /// # Panics
+    /// If channel capacity is zero or `pool` is empty, this method will panic.
+    #[tracing::instrument(skip(self, config, pool),
+                          level = "info",
+                          fields(camera = ?self.info()))]
+    pub fn start_streaming_with_buffers(
+        &mut self,
+        config: impl Into<StreamConfig>,
+        pool: BufferPool,
+    ) -> CameleonResult<PayloadReceiver>
+    where
+        Ctrl: DeviceControl,
+        Strm: PayloadStream,
+        Ctxt: GenApiCtxt,
+    {
+        let config = config.into();
+        info!(?config, ?pool, "try starting streaming");
+        self.start_streaming_with_channel(channel_with_buffers(config, pool))
     }
 
     /// Stops the streaming.
@@ -369,6 +440,115 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
         Ok(())
     }
 
+    fn start_streaming_with_channel(
+        &mut self,
+        (sender, receiver): (PayloadSender, PayloadReceiver),
+    ) -> CameleonResult<PayloadReceiver>
+    where
+        Ctrl: DeviceControl,
+        Strm: PayloadStream,
+        Ctxt: GenApiCtxt,
+    {
+        if self.strm.is_loop_running() {
+            return Err(StreamError::InStreaming.into());
+        }
+
+        // Enable streaimng.
+        self.ctrl.enable_streaming()?;
+        let mut ctxt = self.params_ctxt()?;
+        expect_node!(&ctxt, "TLParamsLocked", as_integer).set_value(&mut ctxt, 1)?;
+
+        // Start streaming loop before the acquisition, so that the device doesn't start
+        // acquisition if the loop rejects the setting, e.g. announced buffers are too small.
+        if let Err(err) = self.strm.start_streaming_loop(sender, &mut self.ctrl) {
+            let mut ctxt = self.params_ctxt()?;
+            expect_node!(&ctxt, "TLParamsLocked", as_integer).set_value(&mut ctxt, 0)?;
+            self.ctrl.disable_streaming()?;
+            return Err(err.into());
+        }
+
+        let mut ctxt = self.params_ctxt()?;
+        expect_node!(&ctxt, "AcquisitionStart", as_command).execute(&mut ctxt)?;
+
+        info!("start streaming successfully");
+        Ok(receiver)
+    }
+
+    /// Starts event notification and returns the receiver for the [`Event`](event::Event).
+    ///
+    /// NOTE: Most devices send only events which are enabled by `EventSelector` and
+    /// `EventNotification` nodes defined in `GenICam SFNC`. Make sure to enable the events you
+    /// need through [`params_ctxt`](Self::params_ctxt).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use cameleon::u3v;
+    /// # let mut cameras = u3v::enumerate_cameras().unwrap();
+    /// # if cameras.is_empty() {
+    /// #     return;
+    /// # }
+    /// # let mut camera = cameras.pop().unwrap();
+    /// camera.open().unwrap();
+    ///
+    /// // Start event notification. Channel capacity is set to 16.
+    /// let event_rx = camera.start_event(16).unwrap();
+    /// // The event can be received like below:
+    /// // event_rx.recv().await.unwrap() or
+    /// // event_rx.try_recv().unwrap();
+    ///
+    /// // Closes the camera.
+    /// camera.close().unwrap();
+    /// ```
+    ///
+    /// # Arguments
+    /// * `cap` - A capacity of the event receiver, events are dropped while it's full.
+    ///
+    /// # Panics
+    /// If `cap` is zero, this method will panic.
+    #[tracing::instrument(skip(self),
+                          level = "info",
+                          fields(camera = ?self.info()))]
+    pub fn start_event(&mut self, cap: usize) -> CameleonResult<EventReceiver>
+    where
+        Ctrl: DeviceControl,
+    {
+        info!("try starting event notification");
+        let evt = self.evt.as_mut().ok_or(CameleonError::EventStreamMissing)?;
+        if evt.is_loop_running() {
+            return Err(StreamError::InStreaming.into());
+        }
+
+        let (sender, receiver) = event::channel(cap);
+        evt.start_event_loop(sender, &mut self.ctrl)?;
+
+        info!("start event notification successfully");
+        Ok(receiver)
+    }
+
+    /// Stops the event notification.
+    ///
+    /// The receiver returned from the previous [`Self::start_event`] call will be invalidated.
+    ///
+    /// This method is automatically called in [`close`](Self::close), so no need to call
+    /// explicitly when you close the camera.
+    #[tracing::instrument(skip(self),
+                          level = "info",
+                          fields(camera = ?self.info()))]
+    pub fn stop_event(&mut self) -> CameleonResult<()>
+    where
+        Ctrl: DeviceControl,
+    {
+        info!("try stopping event notification");
+        match &mut self.evt {
+            Some(evt) if evt.is_loop_running() => {
+                evt.stop_event_loop(&mut self.ctrl)?;
+                info!("stop event notification successfully");
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Returns the context of the camera params.
     ///
     /// Make sure to load `GenApi` context before calling this method.
@@ -441,15 +621,23 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
     }
 
     /// Constructs a camera.
+    ///
+    /// Use [`Self::set_event_stream`] to enable event notification of the camera.
     pub fn new(ctrl: Ctrl, strm: Strm, ctxt: Option<Ctxt>, info: CameraInfo) -> Self {
         Self {
             ctrl,
             strm,
             ctxt,
+            evt: None,
             info,
         }
     }
 
+    /// Set an event stream handle to the camera.
+    pub fn set_event_stream(&mut self, evt: impl EventStream + Send + 'static) {
+        self.evt = Some(SharedEventStream::new(evt));
+    }
+
     /// Converts internal types.
     ///
     /// This method works same as `std::convert::From`, just hack to avoid
@@ -460,12 +648,13 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
         Strm: From<Strm2>,
         Ctxt: From<Ctxt2>,
     {
-        Camera::new(
-            from.ctrl.into(),
-            from.strm.into(),
-            from.ctxt.map(|ctxt| ctxt.into()),
-            from.info,
-        )
+        Camera {
+            ctrl: from.ctrl.into(),
+            strm: from.strm.into(),
+            ctxt: from.ctxt.map(|ctxt| ctxt.into()),
+            evt: from.evt,
+            info: from.info,
+        }
     }
 
     /// Converts internal types. This method work same as `std::convert::Into`, just hack to avoid
@@ -492,12 +681,13 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
         Strm: Into<Strm2>,
         Ctxt: Into<Ctxt2>,
     {
-        Camera::new(
-            self.ctrl.into(),
-            self.strm.into(),
-            self.ctxt.map(|ctxt| ctxt.into()),
-            self.info,
-        )
+        Camera {
+            ctrl: self.ctrl.into(),
+            strm: self.strm.into(),
+            ctxt: self.ctxt.map(|ctxt| ctxt.into()),
+            evt: self.evt,
+            info: self.info,
+        }
     }
 
     /// Set a context to the camera. It's recommended to use [`Self::load_context`] instead if `Self::Ctxt`
@@ -507,6 +697,7 @@ impl<Ctrl, Strm, Ctxt> Camera<Ctrl, Strm, Ctxt> {
             ctrl: self.ctrl,
             strm: self.strm,
             ctxt: Some(ctxt),
+            evt: self.evt,
             info: self.info,
         }
     }
@@ -543,6 +734,31 @@ pub trait DeviceControl {
     /// Writes data to the device's memory.
     fn write(&mut self, address: u64, data: &[u8]) -> ControlResult<()>;
 
+    /// Reads data from multiple addresses of the device's memory at once.
+    ///
+    /// Each entry is a pair of an address and a buffer, and reads length is same as the buffer
+    /// length.
+    ///
+    /// The default implementation reads each entry in turn.
+    fn read_stacked(&mut self, entries: &mut [(u64, &mut [u8])]) -> ControlResult<()> {
+        for (address, buf) in entries {
+            self.read(*address, buf)?;
+        }
+        Ok(())
+    }
+
+    /// Writes data to multiple addresses of the device's memory at once.
+    ///
+    /// Each entry is a pair of an address and data.
+    ///
+    /// The default implementation writes each entry in turn.
+    fn write_stacked(&mut self, entries: &[(u64, &[u8])]) -> ControlResult<()> {
+        for (address, data) in entries {
+            self.write(*address, data)?;
+        }
+        Ok(())
+    }
+
     /// Returns `GenICam` xml string.
     fn genapi(&mut self) -> ControlResult<String>;
 
@@ -575,3 +791,77 @@ pub trait PayloadStream {
     /// Returns `true` if streaming loop is running.
     fn is_loop_running(&self) -> bool;
 }
+
+/// This trait provides event notification capability.
+#[auto_impl(&mut, Box)]
+pub trait EventStream {
+    /// Opens the handle.
+    fn open(&mut self) -> StreamResult<()>;
+
+    /// Closes the handle.
+    fn close(&mut self) -> StreamResult<()>;
+
+    /// Enables event notification of the device and starts event loop.
+    fn start_event_loop(
+        &mut self,
+        sender: EventSender,
+        ctrl: &mut dyn DeviceControl,
+    ) -> StreamResult<()>;
+
+    /// Stops event loop and disables event notification of the device.
+    fn stop_event_loop(&mut self, ctrl: &mut dyn DeviceControl) -> StreamResult<()>;
+
+    /// Returns `true` if event loop is running.
+    fn is_loop_running(&self) -> bool;
+}
+
+/// An [`EventStream`] shared among clones of [`Camera`].
+#[derive(Clone)]
+pub struct SharedEventStream(Arc<Mutex<dyn EventStream + Send>>);
+
+impl SharedEventStream {
+    /// Constructs a shared event stream from `evt`.
+    pub fn new(evt: impl EventStream + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(evt)))
+    }
+
+    fn lock(&self) -> StreamResult<MutexGuard<'_, dyn EventStream + Send + 'static>> {
+        self.0
+            .lock()
+            .map_err(|cause| StreamError::Poisoned(cause.to_string().into()))
+    }
+}
+
+impl std::fmt::Debug for SharedEventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedEventStream")
+            .field("is_loop_running", &self.is_loop_running())
+            .finish()
+    }
+}
+
+impl EventStream for SharedEventStream {
+    fn open(&mut self) -> StreamResult<()> {
+        self.lock()?.open()
+    }
+
+    fn close(&mut self) -> StreamResult<()> {
+        self.lock()?.close()
+    }
+
+    fn start_event_loop(
+        &mut self,
+        sender: EventSender,
+        ctrl: &mut dyn DeviceControl,
+    ) -> StreamResult<()> {
+        self.lock()?.start_event_loop(sender, ctrl)
+    }
+
+    fn stop_event_loop(&mut self, ctrl: &mut dyn DeviceControl) -> StreamResult<()> {
+        self.lock()?.stop_event_loop(ctrl)
+    }
+
+    fn is_loop_running(&self) -> bool {
+        self.lock().is_ok_and(|evt| evt.is_loop_running())
+    }
+}
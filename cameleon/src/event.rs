@@ -0,0 +1,101 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains types related to `Event` sent from the device.
+//!
+//! `Event` notifies the host of an occurrence on the device, e.g. the end of exposure or the
+//! reception of a frame trigger. See [`Event`] for more details.
+
+use std::time;
+
+use async_std::channel::{Receiver, Sender};
+
+use super::StreamResult;
+
+/// An event sent from the device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub(crate) id: u16,
+    pub(crate) timestamp: time::Duration,
+    pub(crate) data: Vec<u8>,
+}
+
+impl Event {
+    /// Constructs an event.
+    pub fn new(id: u16, timestamp: time::Duration, data: Vec<u8>) -> Self {
+        Self {
+            id,
+            timestamp,
+            data,
+        }
+    }
+
+    /// Returns event id of the event.
+    ///
+    /// The meaning of the id is defined by the device, and is described as `EventID` of the
+    /// `Port` node in `GenApi` xml.
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Timestamp of the device when the event is generated.
+    pub fn timestamp(&self) -> time::Duration {
+        self.timestamp
+    }
+
+    /// Returns event specific data which follows the event header.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the event data as `Vec<u8>`.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// An Receiver of the `Event` which is sent from a device.
+#[derive(Debug, Clone)]
+pub struct EventReceiver {
+    rx: Receiver<StreamResult<Event>>,
+}
+
+impl EventReceiver {
+    /// Receives [`Event`] sent from the device.
+    pub async fn recv(&self) -> StreamResult<Event> {
+        self.rx.recv().await?
+    }
+
+    /// Tries to receive [`Event`].
+    /// This method doesn't wait arrival of `event` and immediately returns `StreamError` if
+    /// the channel is empty.
+    pub fn try_recv(&self) -> StreamResult<Event> {
+        self.rx.try_recv()?
+    }
+}
+
+/// A sender of the [`Event`] which is sent to the host.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    tx: Sender<StreamResult<Event>>,
+}
+
+impl EventSender {
+    /// Sends [`Event`] to the host.
+    pub async fn send(&self, event: StreamResult<Event>) -> StreamResult<()> {
+        Ok(self.tx.send(event).await?)
+    }
+
+    /// Tries to send [`Event`] to the host.
+    /// Returns `StreamError` if the channel is full or closed.
+    pub fn try_send(&self, event: StreamResult<Event>) -> StreamResult<()> {
+        Ok(self.tx.try_send(event)?)
+    }
+}
+
+/// Creates [`EventReceiver`] and [`EventSender`].
+pub fn channel(event_cap: usize) -> (EventSender, EventReceiver) {
+    let (tx, rx) = async_std::channel::bounded(event_cap);
+    (EventSender { tx }, EventReceiver { rx })
+}
@@ -43,14 +43,14 @@ pub use node_kind::{
 };
 
 use std::{
-    convert::TryInto,
+    convert::{TryFrom, TryInto},
     sync::{Arc, Mutex},
 };
 
 use auto_impl::auto_impl;
 use cameleon_genapi::{builder::GenApiBuilder, store};
 
-use super::{ControlError, ControlResult, DeviceControl};
+use super::{event::Event, ControlError, ControlResult, DeviceControl};
 
 pub use cameleon_genapi::{
     elem_type::{AccessMode, NameSpace, Visibility},
@@ -115,6 +115,51 @@ where
     pub fn node_store(&self) -> &Ctxt::NS {
         self.ctxt.node_store()
     }
+
+    /// Attaches the event to `Port` nodes whose `EventID` matches the event id.
+    ///
+    /// Once the event is attached, event data features mapped onto the ports, e.g.
+    /// `EventExposureEndTimestamp`, can be read through the usual node API. The attached data
+    /// starts with the event header, i.e. event size, event id and timestamp, followed by the
+    /// event specific data.
+    ///
+    /// Returns `false` if no `Port` node is bound to the event id.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use cameleon::u3v;
+    /// # let mut cameras = u3v::enumerate_cameras().unwrap();
+    /// # if cameras.is_empty() {
+    /// #     return;
+    /// # }
+    /// # let mut camera = cameras.pop().unwrap();
+    /// camera.open().unwrap();
+    /// camera.load_context().unwrap();
+    ///
+    /// let event_rx = camera.start_event(16).unwrap();
+    /// if let Ok(event) = event_rx.try_recv() {
+    ///     let mut params_ctxt = camera.params_ctxt().unwrap();
+    ///     params_ctxt.attach_event(&event);
+    ///     if let Some(node) = params_ctxt.node("EventExposureEndTimestamp") {
+    ///         let timestamp = node.as_integer(&params_ctxt).unwrap().value(&mut params_ctxt);
+    ///     }
+    /// }
+    /// # camera.close().unwrap();
+    /// ```
+    pub fn attach_event(&mut self, event: &Event) -> bool {
+        const EVENT_HEADER_LENGTH: usize = 12;
+
+        let mut data = Vec::with_capacity(EVENT_HEADER_LENGTH + event.data().len());
+        let event_size = u16::try_from(EVENT_HEADER_LENGTH + event.data().len()).unwrap_or(0);
+        data.extend_from_slice(&event_size.to_le_bytes());
+        data.extend_from_slice(&event.id().to_le_bytes());
+        let timestamp = u64::try_from(event.timestamp().as_nanos()).unwrap_or(u64::MAX);
+        data.extend_from_slice(&timestamp.to_le_bytes());
+        data.extend_from_slice(event.data());
+
+        self.ctxt
+            .enter(|_, value_ctxt| value_ctxt.attach_event(event.id().into(), &data))
+    }
 }
 
 impl<Ctrl, Ctxt> ParamsCtxt<Ctrl, Ctxt>
@@ -346,9 +391,12 @@ impl FromXml for NoCacheGenApiCtxt {
 
 impl From<DefaultGenApiCtxt> for NoCacheGenApiCtxt {
     fn from(from: DefaultGenApiCtxt) -> Self {
+        let value_ctxt = from
+            .value_ctxt
+            .map_cache_store(|_| store::CacheSink::default());
         Self {
             node_store: from.node_store,
-            value_ctxt: ValueCtxt::new(from.value_ctxt.value_store, store::CacheSink::default()),
+            value_ctxt,
             reg_desc: from.reg_desc,
         }
     }
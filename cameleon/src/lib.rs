@@ -127,12 +127,15 @@
 )]
 
 pub mod camera;
+pub mod event;
 pub mod genapi;
 pub mod payload;
-#[cfg(feature = "libusb")]
+#[cfg(any(feature = "libusb", feature = "emulator"))]
 pub mod u3v;
 
-pub use camera::{Camera, CameraInfo, DeviceControl, PayloadStream};
+pub use camera::{
+    Camera, CameraInfo, DeviceControl, EventStream, PayloadStream, SharedEventStream,
+};
 
 use std::{borrow::Cow, num::TryFromIntError};
 
@@ -154,6 +157,10 @@ pub enum CameleonError {
     #[error("`GenApi` context is missing")]
     GenApiContextMissing,
 
+    /// The camera doesn't have an event stream handle.
+    #[error("event stream is missing")]
+    EventStreamMissing,
+
     /// `GenApi` xml doesn't meet `GenApi SFNC` specification.
     #[error("invalid `GenApi` xml: {0}")]
     InvalidGenApiXml(Cow<'static, str>),
@@ -169,7 +176,7 @@ pub type ControlResult<T> = std::result::Result<T, ControlError>;
 /// An error type for device control.
 #[derive(Debug, thiserror::Error)]
 pub enum ControlError {
-    /// The device is busy, may be opened by another application.
+    /// The device is busy, may be opened by another application or processing another command.
     #[error("device is busy")]
     Busy,
 
@@ -201,6 +208,31 @@ pub enum ControlError {
     /// e.g. try to write too large data that will overrun register.
     #[error("try to write invalid data to the device: {0}")]
     InvalidData(Box<dyn std::error::Error>),
+
+    /// The device denied the access to the address.
+    /// e.g. try to read unreadable address or write to unwritable address.
+    #[error("access to the address is denied by the device")]
+    AccessDenied,
+
+    /// Try to access an address that doesn't exist in the device.
+    #[error("attempt to access an address that doesn't exist in the device")]
+    InvalidAddress,
+
+    /// Try to write to a read only address.
+    #[error("attempt to write to a read only address")]
+    WriteProtect,
+
+    /// Try to access an address with bad alignment.
+    #[error("attempt to access an address with bad alignment")]
+    BadAlignment,
+
+    /// The command is not implemented in the device.
+    #[error("command is not implemented in the device")]
+    NotImplemented,
+
+    /// The device returned a device specific error status. The status code is contained.
+    #[error("device specific error: {0:#06X}")]
+    DeviceSpecific(u16),
 }
 
 /// A specialized `Result` type for streaming.
@@ -221,6 +253,18 @@ pub enum StreamError {
     #[error("invalid payload has been sent: {0}")]
     InvalidPayload(Cow<'static, str>),
 
+    /// Frames are lost before reaching the host, i.e. block ids of received payloads are not
+    /// contiguous.
+    ///
+    /// Block ids from `from` to `to` (inclusive) have never been received.
+    #[error("frames are lost: block ids from {from} to {to} have never been received")]
+    FramesLost {
+        /// The first block id of lost frames.
+        from: u64,
+        /// The last block id of lost frames.
+        to: u64,
+    },
+
     /// The device is disconnected from the host.
     #[error("device is disconnected")]
     Disconnected,
@@ -6,12 +6,32 @@
 //!
 //! `Payload` is an abstracted container that is mainly used to transfer an image, but also meta data of the image.
 //! See [`Payload`] and [`ImageInfo`] for more details.
+//!
+//! Statistics of streaming are available through [`PayloadReceiver::statistics`], see
+//! [`StreamStatistics`] for more details.
+//!
+//! How payloads are buffered when the receiver can't keep up with the device is configured by
+//! [`StreamConfig`].
+//!
+//! By default, buffers of payloads are allocated by the streaming loop on demand. Alternatively,
+//! buffers allocated by the application can be announced in a [`BufferPool`], then the streaming
+//! loop fills only the announced buffers.
 
 pub use cameleon_device::PixelFormat;
 
-use std::time;
+use std::{
+    collections::VecDeque,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex, Weak},
+    time::{self, Instant},
+};
 
-use async_std::channel::{Receiver, Sender};
+use async_std::{
+    channel::{Receiver, Sender, TrySendError},
+    task,
+};
+use futures::future::{self, Either, Future};
 
 use super::{StreamError, StreamResult};
 
@@ -51,7 +71,7 @@ pub struct Payload {
     pub(crate) id: u64,
     pub(crate) payload_type: PayloadType,
     pub(crate) image_info: Option<ImageInfo>,
-    pub(crate) payload: Vec<u8>,
+    pub(crate) payload: PayloadBuf,
     pub(crate) valid_payload_size: usize,
     pub(crate) timestamp: time::Duration,
 }
@@ -92,64 +112,556 @@ impl Payload {
         self.timestamp
     }
 
+    fn empty(payload: PayloadBuf) -> Self {
+        Self {
+            id: 0,
+            payload_type: PayloadType::Image,
+            image_info: None,
+            payload,
+            valid_payload_size: 0,
+            timestamp: time::Duration::ZERO,
+        }
+    }
+
+    /// Returns id of the buffer if the payload is written into a buffer announced in
+    /// [`BufferPool`].
+    pub fn buffer_id(&self) -> Option<BufferId> {
+        match &self.payload {
+            PayloadBuf::Owned(_) => None,
+            PayloadBuf::Announced(buf) => Some(buf.id),
+        }
+    }
+
     /// Returns the payload as `Vec<u8>`.
-    pub fn into_vec(mut self) -> Vec<u8> {
-        self.payload.resize(self.valid_payload_size, 0);
-        self.payload
+    ///
+    /// NOTE: If the payload is written into an announced buffer, the data is copied and the
+    /// buffer is dropped. Use [`PayloadReceiver::send_back`] to queue the buffer again.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.payload {
+            PayloadBuf::Owned(mut buf) => {
+                buf.resize(self.valid_payload_size, 0);
+                buf
+            }
+            PayloadBuf::Announced(buf) => buf.as_ref()[..self.valid_payload_size].to_vec(),
+        }
+    }
+}
+
+/// A buffer allocated by the application, which the streaming loop writes payload data into.
+///
+/// The trait is implemented for all types that can be viewed as a byte slice, e.g. `Vec<u8>`,
+/// `Box<[u8]>` or memory mapped files.
+pub trait PayloadBuffer: AsRef<[u8]> + AsMut<[u8]> + Send + 'static {}
+
+impl<T> PayloadBuffer for T where T: AsRef<[u8]> + AsMut<[u8]> + Send + 'static {}
+
+/// Id of a buffer announced in [`BufferPool`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BufferId(usize);
+
+/// Buffers allocated by the application, which are announced to the streaming loop.
+///
+/// All buffers in the pool are queued when streaming starts. The streaming loop fills only the
+/// queued buffers and never allocates buffers by itself, so a frame is dropped if no buffer is
+/// queued. A buffer is queued again when the payload is sent back by
+/// [`PayloadReceiver::send_back`].
+///
+/// Each buffer must be equal or larger than the maximum payload size of the device, otherwise
+/// streaming fails to start with [`StreamError::BufferTooSmall`].
+///
+/// The buffers are given back by [`PayloadReceiver::revoke_buffers`] after streaming is stopped.
+///
+/// # Examples
+///
+/// ```rust
+/// use cameleon::payload::BufferPool;
+///
+/// let mut pool = BufferPool::new();
+/// let ids: Vec<_> = (0..4)
+///     .map(|_| pool.announce(vec![0u8; 640 * 480].into_boxed_slice()))
+///     .collect();
+/// assert_eq!(pool.len(), 4);
+/// assert_ne!(ids[0], ids[1]);
+/// ```
+#[derive(Default)]
+pub struct BufferPool {
+    buffers: Vec<AnnouncedBuffer>,
+    /// Id of the next announced buffer.
+    next_id: usize,
+}
+
+impl BufferPool {
+    /// Construct an empty `BufferPool`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Announce a buffer and returns its id.
+    pub fn announce(&mut self, buf: impl PayloadBuffer) -> BufferId {
+        let id = BufferId(self.next_id);
+        self.next_id += 1;
+        self.buffers.push(AnnouncedBuffer {
+            id,
+            buf: Box::new(buf),
+        });
+        id
+    }
+
+    fn minimum_buffer_len(&self) -> Option<usize> {
+        self.buffers.iter().map(|buf| buf.as_ref().len()).min()
+    }
+
+    fn push(&mut self, buf: AnnouncedBuffer) {
+        self.next_id = self.next_id.max(buf.id.0 + 1);
+        self.buffers.push(buf);
+    }
+
+    /// Returns the number of announced buffers.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Returns `true` if no buffer is announced.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Storage of payload data.
+pub(crate) enum PayloadBuf {
+    /// A buffer allocated by the streaming loop.
+    Owned(Vec<u8>),
+    /// A buffer announced by the application.
+    Announced(AnnouncedBuffer),
+}
+
+pub(crate) struct AnnouncedBuffer {
+    id: BufferId,
+    buf: Box<dyn PayloadBuffer>,
+}
+
+impl AsRef<[u8]> for AnnouncedBuffer {
+    fn as_ref(&self) -> &[u8] {
+        (*self.buf).as_ref()
+    }
+}
+
+impl Default for PayloadBuf {
+    fn default() -> Self {
+        Self::Owned(Vec::new())
     }
 }
 
+impl Deref for PayloadBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(buf) => buf,
+            Self::Announced(buf) => (*buf.buf).as_ref(),
+        }
+    }
+}
+
+impl DerefMut for PayloadBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            Self::Owned(buf) => buf,
+            Self::Announced(buf) => (*buf.buf).as_mut(),
+        }
+    }
+}
+
+/// An announced buffer can't be cloned, so the clone owns a copy of the data.
+impl Clone for PayloadBuf {
+    fn clone(&self) -> Self {
+        Self::Owned(self.to_vec())
+    }
+}
+
+impl PartialEq for PayloadBuf {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for PayloadBuf {}
+
+impl fmt::Debug for PayloadBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Owned(buf) => f.debug_tuple("Owned").field(&buf.len()).finish(),
+            Self::Announced(buf) => f
+                .debug_tuple("Announced")
+                .field(&buf.id)
+                .field(&self.len())
+                .finish(),
+        }
+    }
+}
+
+/// Default number of payload buffers kept for reuse.
+const DEFAULT_BUFFER_POOL_SIZE: usize = 5;
+
+/// Behavior of the sender when the channel is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the newest payload. The payloads in the channel are kept.
+    #[default]
+    DropNewest,
+
+    /// Drop the oldest payload in the channel to make room for the newest one, i.e. the receiver
+    /// always gets the latest payloads. Suitable for live preview.
+    ///
+    /// NOTE: Errors in the channel may be dropped as well, but they are still counted in
+    /// [`StreamStatistics`].
+    DropOldest,
+
+    /// Wait until the receiver makes room in the channel. The streaming loop stops reading from
+    /// the device meanwhile, so frames may be dropped on the device side instead.
+    /// Suitable for recording.
+    Block,
+}
+
+/// Configuration of payload delivery from the streaming loop to the receiver.
+///
+/// `usize` is converted into `StreamConfig` with the value as the channel capacity.
+///
+/// # Examples
+///
+/// ```rust
+/// use cameleon::payload::{OverflowPolicy, StreamConfig};
+///
+/// // Always keep the latest 2 frames for live preview.
+/// let config = StreamConfig {
+///     overflow_policy: OverflowPolicy::DropOldest,
+///     ..StreamConfig::new(2)
+/// };
+/// assert_eq!(config.buffer_pool_size, 5);
+/// assert_eq!(StreamConfig::from(2), StreamConfig::new(2));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamConfig {
+    /// Capacity of the payload channel.
+    pub channel_capacity: usize,
+
+    /// Maximum number of payload buffers sent back by [`PayloadReceiver::send_back`] and kept for
+    /// reuse.
+    pub buffer_pool_size: usize,
+
+    /// Behavior of the sender when the channel is full.
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl StreamConfig {
+    /// Construct `StreamConfig` with default buffer pool size and [`OverflowPolicy::DropNewest`].
+    #[must_use]
+    pub fn new(channel_capacity: usize) -> Self {
+        Self {
+            channel_capacity,
+            buffer_pool_size: DEFAULT_BUFFER_POOL_SIZE,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl From<usize> for StreamConfig {
+    fn from(channel_capacity: usize) -> Self {
+        Self::new(channel_capacity)
+    }
+}
+
+/// A snapshot of statistics of streaming.
+///
+/// Frame rates and throughput are calculated from frames that are received from the device
+/// intact, including the ones dropped because the channel was full.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamStatistics {
+    /// Number of payloads delivered to the channel.
+    pub delivered_frames: u64,
+    /// Number of payloads dropped because the channel was full, see [`OverflowPolicy`].
+    pub dropped_frames: u64,
+    /// Number of frames that have never reached the host, see [`StreamError::FramesLost`].
+    pub lost_frames: u64,
+    /// Number of frames that are received from the device, but broken or truncated. These
+    /// frames are reported as [`StreamError::InvalidPayload`] or [`StreamError::Timeout`].
+    pub incomplete_frames: u64,
+    /// Frame rate of the latest frames in frames per second.
+    pub current_frame_rate: f64,
+    /// Average frame rate since streaming is started in frames per second.
+    pub average_frame_rate: f64,
+    /// Average throughput of valid payload data since streaming is started in bytes per second.
+    pub throughput: f64,
+    /// Average time from a payload is sent to the channel until it's received.
+    pub average_latency: time::Duration,
+    /// Maximum time from a payload is sent to the channel until it's received.
+    pub maximum_latency: time::Duration,
+}
+
+/// A payload in the channel with the time when it's sent.
+type SentPayload = (StreamResult<Payload>, Instant);
+
+/// Interval to check whether the channel has room while [`PayloadSender::send_until`] blocks.
+const BLOCK_POLL_INTERVAL: time::Duration = time::Duration::from_millis(1);
+
 /// An Receiver of the `Payload` which is sent from a device.
 #[derive(Debug, Clone)]
 pub struct PayloadReceiver {
     /// Sends back `payload` to the device for reusing it.
     tx: Sender<Payload>,
 
-    /// Receives `payload` from the device.
-    rx: Receiver<StreamResult<Payload>>,
+    /// Receives `payload` from the device. The receiver is shared among clones so that the
+    /// sender can evict payloads without keeping the channel open.
+    rx: Arc<Receiver<SentPayload>>,
+
+    /// Receives buffers sent back to the streaming loop, which is used to revoke announced
+    /// buffers.
+    buffer_rx: Receiver<Payload>,
+
+    /// Statistics shared with the sender.
+    stats: Arc<Mutex<StatisticsRecorder>>,
 }
 
 impl PayloadReceiver {
     /// Receives [`Payload`] sent from the device.
     pub async fn recv(&self) -> StreamResult<Payload> {
-        self.rx.recv().await?
+        let (payload, sent_at) = self.rx.recv().await?;
+        self.record_received(&payload, sent_at);
+        payload
     }
 
     /// Tries to receive [`Payload`].
     /// This method doesn't wait arrival of `payload` and immediately returns `StreamError` if
     /// the channel is empty.
     pub fn try_recv(&self) -> StreamResult<Payload> {
-        self.rx.try_recv()?
+        let (payload, sent_at) = self.rx.try_recv()?;
+        self.record_received(&payload, sent_at);
+        payload
+    }
+
+    /// Returns a snapshot of statistics of streaming.
+    pub fn statistics(&self) -> StreamStatistics {
+        self.stats.lock().unwrap().snapshot(Instant::now())
     }
 
     /// Sends back [`Payload`] to the device to reuse already allocated `payload`.
     ///
     /// Sending back `payload` may improve performance of streaming, but not required to call this
     /// method.
+    ///
+    /// If the payload is written into a buffer announced in [`BufferPool`], the buffer is queued
+    /// again. In that case, the streaming loop can't reuse the buffer unless it's sent back.
     pub fn send_back(&self, payload: Payload) {
         self.tx.try_send(payload).ok();
     }
+
+    /// Takes back buffers announced in [`BufferPool`] after streaming is stopped.
+    ///
+    /// The returned pool contains the buffers held by the streaming loop, the buffers of payloads
+    /// remaining in the channel and the buffers sent back by [`Self::send_back`]. Payloads held
+    /// by the application must be sent back before calling this method to revoke their buffers.
+    ///
+    /// Returns [`StreamError::InStreaming`] if the streaming loop is still running.
+    pub fn revoke_buffers(&self) -> StreamResult<BufferPool> {
+        if !self.rx.is_closed() {
+            return Err(StreamError::InStreaming);
+        }
+
+        let mut pool = BufferPool::new();
+        let sent = std::iter::from_fn(|| self.rx.try_recv().ok()).filter_map(|(res, _)| res.ok());
+        let sent_back = std::iter::from_fn(|| self.buffer_rx.try_recv().ok());
+        for payload in sent.chain(sent_back) {
+            if let PayloadBuf::Announced(buf) = payload.payload {
+                pool.push(buf);
+            }
+        }
+        Ok(pool)
+    }
+
+    fn record_received(&self, payload: &StreamResult<Payload>, sent_at: Instant) {
+        if payload.is_ok() {
+            self.stats.lock().unwrap().record_latency(sent_at.elapsed());
+        }
+    }
 }
 
 /// A sender of the [`Payload`] which is sent to the host.
+///
+/// The sender records [`StreamStatistics`] according to what is sent.
 #[derive(Debug, Clone)]
 pub struct PayloadSender {
     /// Receives from the device.
-    tx: Sender<StreamResult<Payload>>,
+    tx: Sender<SentPayload>,
     /// Sends back payload to reuse it.
     rx: Receiver<Payload>,
+    /// Queues a payload again when the payload is dropped.
+    recycle_tx: Sender<Payload>,
+    /// Statistics shared with the receiver.
+    stats: Arc<Mutex<StatisticsRecorder>>,
+    overflow_policy: OverflowPolicy,
+    /// `false` if the streaming loop must fill only announced buffers.
+    allocates_buffers: bool,
+    /// Evicts the oldest payload from the channel, which exists only if the policy is
+    /// [`OverflowPolicy::DropOldest`]. The reference is weak so that the channel is closed once
+    /// all receivers are dropped.
+    evict_rx: Option<Weak<Receiver<SentPayload>>>,
+    /// Length of the smallest announced buffer.
+    minimum_buffer_len: Option<usize>,
 }
 
 impl PayloadSender {
     /// Sends [`Payload`] to the host.
     pub async fn send(&self, payload: StreamResult<Payload>) -> StreamResult<()> {
-        Ok(self.tx.send(payload).await?)
+        let kind = SentKind::of(&payload);
+        let res = self.tx.send((payload, Instant::now())).await;
+        self.stats
+            .lock()
+            .unwrap()
+            .record_sent(kind, res.is_ok(), Instant::now());
+        res.map_err(|err| {
+            let msg = err.to_string();
+            self.recycle(err.into_inner().0);
+            StreamError::ReceiveError(msg.into())
+        })
     }
 
     /// Tries to send [`Payload`] to the host.
     /// Returns `StreamError` if the channel is full or empty.
     pub fn try_send(&self, payload: StreamResult<Payload>) -> StreamResult<()> {
-        Ok(self.tx.try_send(payload)?)
+        let kind = SentKind::of(&payload);
+        let res = self.tx.try_send((payload, Instant::now()));
+        self.finish_try_send(kind, res)
+    }
+
+    /// Sends [`Payload`] to the host according to the [`OverflowPolicy`] of the channel.
+    ///
+    /// The returned future waits for room in the channel only if the policy is
+    /// [`OverflowPolicy::Block`]. Returns `StreamError` if the payload is dropped.
+    pub async fn send_with_policy(&self, payload: StreamResult<Payload>) -> StreamResult<()> {
+        match self.overflow_policy {
+            OverflowPolicy::DropNewest => self.try_send(payload),
+            OverflowPolicy::DropOldest => {
+                let evict_rx = self.evict_rx.as_ref().unwrap();
+                let kind = SentKind::of(&payload);
+                let mut item = (payload, Instant::now());
+                let res = loop {
+                    match self.tx.try_send(item) {
+                        Err(TrySendError::Full(rejected)) => {
+                            item = rejected;
+                            let evicted = evict_rx.upgrade().and_then(|rx| rx.try_recv().ok());
+                            if let Some((evicted, _)) = evicted {
+                                self.stats.lock().unwrap().record_evicted(&evicted);
+                                self.recycle(evicted);
+                            }
+                        }
+                        res => break res,
+                    }
+                };
+                self.finish_try_send(kind, res)
+            }
+            OverflowPolicy::Block => self.send(payload).await,
+        }
+    }
+
+    /// Sends [`Payload`] like [`PayloadSender::send_with_policy`], but gives up once `cancel`
+    /// resolves.
+    ///
+    /// Dropping the future of [`PayloadSender::send_with_policy`] drops the payload with it,
+    /// whereas this method queues the buffer of the payload again on cancellation.
+    /// Returns `None` if the sending is cancelled.
+    pub(crate) async fn send_until(
+        &self,
+        payload: StreamResult<Payload>,
+        mut cancel: impl Future + Unpin,
+    ) -> Option<StreamResult<()>> {
+        if self.overflow_policy != OverflowPolicy::Block {
+            return Some(self.send_with_policy(payload).await);
+        }
+
+        let kind = SentKind::of(&payload);
+        let mut item = (payload, Instant::now());
+        loop {
+            match self.tx.try_send(item) {
+                Err(TrySendError::Full(rejected)) => {
+                    item = rejected;
+                    let wait = Box::pin(task::sleep(BLOCK_POLL_INTERVAL));
+                    if let Either::Right(_) = future::select(wait, &mut cancel).await {
+                        self.recycle(item.0);
+                        return None;
+                    }
+                }
+                res => return Some(self.finish_try_send(kind, res)),
+            }
+        }
+    }
+
+    /// Returns the [`OverflowPolicy`] of the channel.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// Returns `false` if the streaming loop must fill only buffers received by
+    /// [`Self::try_recv`], i.e. buffers are announced in [`BufferPool`].
+    pub fn allocates_buffers(&self) -> bool {
+        self.allocates_buffers
+    }
+
+    /// Records a payload which is dropped by the streaming loop without being sent, e.g. no
+    /// announced buffer is queued.
+    pub fn record_dropped(&self, payload: &Payload) {
+        self.stats.lock().unwrap().record_sent(
+            SentKind::Frame(payload.valid_payload_size),
+            false,
+            Instant::now(),
+        );
+    }
+
+    fn finish_try_send(
+        &self,
+        kind: SentKind,
+        res: Result<(), TrySendError<SentPayload>>,
+    ) -> StreamResult<()> {
+        self.stats
+            .lock()
+            .unwrap()
+            .record_sent(kind, res.is_ok(), Instant::now());
+        res.map_err(|err| {
+            let msg = err.to_string();
+            self.recycle(err.into_inner().0);
+            StreamError::ReceiveError(msg.into())
+        })
+    }
+
+    /// Queues an announced buffer again, which is used to give back buffers held by the
+    /// streaming loop when the loop stops.
+    pub(crate) fn give_back(&self, buf: PayloadBuf) {
+        if let PayloadBuf::Announced(_) = buf {
+            self.recycle(Ok(Payload::empty(buf)));
+        }
+    }
+
+    /// Returns the length of the smallest announced buffer, or `None` if the streaming loop
+    /// allocates buffers by itself.
+    pub(crate) fn minimum_buffer_len(&self) -> Option<usize> {
+        self.minimum_buffer_len
+    }
+
+    /// Queues the buffer of a dropped payload again so that announced buffers are never lost.
+    fn recycle(&self, payload: StreamResult<Payload>) {
+        if let Ok(payload) = payload {
+            self.recycle_tx.try_send(payload).ok();
+        }
     }
 
     /// Tries to receive [`Payload`].
@@ -160,22 +672,188 @@ impl PayloadSender {
     }
 }
 
-/// Creates [`PayloadReceiver`] and [`PayloadSender`].
+/// Creates [`PayloadReceiver`] and [`PayloadSender`] with [`OverflowPolicy::DropNewest`].
 pub fn channel(payload_cap: usize, buffer_cap: usize) -> (PayloadSender, PayloadReceiver) {
-    let (device_tx, host_rx) = async_std::channel::bounded(payload_cap);
+    channel_with_config(StreamConfig {
+        channel_capacity: payload_cap,
+        buffer_pool_size: buffer_cap,
+        overflow_policy: OverflowPolicy::DropNewest,
+    })
+}
+
+/// Creates [`PayloadReceiver`] and [`PayloadSender`] configured by [`StreamConfig`].
+///
+/// # Panics
+/// If `channel_capacity` or `buffer_pool_size` is zero.
+pub fn channel_with_config(config: StreamConfig) -> (PayloadSender, PayloadReceiver) {
+    build_channel(config, config.buffer_pool_size, true)
+}
+
+/// Creates [`PayloadReceiver`] and [`PayloadSender`] configured by [`StreamConfig`], whose
+/// streaming loop fills only buffers announced in `pool`.
+///
+/// `buffer_pool_size` of `config` is ignored, all buffers in the pool are queued instead.
+///
+/// # Panics
+/// If `channel_capacity` is zero or `pool` is empty.
+pub fn channel_with_buffers(
+    config: StreamConfig,
+    pool: BufferPool,
+) -> (PayloadSender, PayloadReceiver) {
+    let (mut sender, receiver) = build_channel(config, pool.len(), false);
+    sender.minimum_buffer_len = pool.minimum_buffer_len();
+    for buf in pool.buffers {
+        sender.recycle(Ok(Payload::empty(PayloadBuf::Announced(buf))));
+    }
+    (sender, receiver)
+}
+
+fn build_channel(
+    config: StreamConfig,
+    buffer_cap: usize,
+    allocates_buffers: bool,
+) -> (PayloadSender, PayloadReceiver) {
+    let (device_tx, host_rx) = async_std::channel::bounded(config.channel_capacity);
     let (host_tx, device_rx) = async_std::channel::bounded(buffer_cap);
+    let stats = Arc::new(Mutex::new(StatisticsRecorder::new(Instant::now())));
+    let host_rx = Arc::new(host_rx);
+    let evict_rx = if config.overflow_policy == OverflowPolicy::DropOldest {
+        Some(Arc::downgrade(&host_rx))
+    } else {
+        None
+    };
     (
         PayloadSender {
             tx: device_tx,
-            rx: device_rx,
+            rx: device_rx.clone(),
+            recycle_tx: host_tx.clone(),
+            stats: stats.clone(),
+            overflow_policy: config.overflow_policy,
+            allocates_buffers,
+            evict_rx,
+            minimum_buffer_len: None,
         },
         PayloadReceiver {
             tx: host_tx,
             rx: host_rx,
+            buffer_rx: device_rx,
+            stats,
         },
     )
 }
 
+/// Number of the latest frames used to calculate the current frame rate.
+const FRAME_RATE_WINDOW: usize = 16;
+
+/// Classification of what is sent to the channel.
+#[derive(Debug, Clone, Copy)]
+enum SentKind {
+    /// A payload with its valid size.
+    Frame(usize),
+    /// Lost frames.
+    Lost(u64),
+    /// A broken or truncated frame.
+    Incomplete,
+    /// Other errors, which aren't related to a specific frame.
+    Other,
+}
+
+impl SentKind {
+    fn of(payload: &StreamResult<Payload>) -> Self {
+        match payload {
+            Ok(payload) => Self::Frame(payload.valid_payload_size),
+            Err(StreamError::FramesLost { from, to }) => Self::Lost(to - from + 1),
+            Err(StreamError::InvalidPayload(..) | StreamError::Timeout) => Self::Incomplete,
+            Err(_) => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct StatisticsRecorder {
+    stats: StreamStatistics,
+    started_at: Instant,
+    received_frames: u64,
+    received_bytes: u64,
+    /// Arrival time of the latest frames.
+    recent_frames: VecDeque<Instant>,
+    /// Number of received payloads and the sum of their latencies.
+    latency_count: u64,
+    total_latency: time::Duration,
+}
+
+impl StatisticsRecorder {
+    fn new(now: Instant) -> Self {
+        Self {
+            stats: StreamStatistics::default(),
+            started_at: now,
+            received_frames: 0,
+            received_bytes: 0,
+            recent_frames: VecDeque::with_capacity(FRAME_RATE_WINDOW),
+            latency_count: 0,
+            total_latency: time::Duration::ZERO,
+        }
+    }
+
+    fn record_sent(&mut self, kind: SentKind, is_delivered: bool, now: Instant) {
+        match kind {
+            SentKind::Frame(size) => {
+                if is_delivered {
+                    self.stats.delivered_frames += 1;
+                } else {
+                    self.stats.dropped_frames += 1;
+                }
+                self.received_frames += 1;
+                self.received_bytes += size as u64;
+                if self.recent_frames.len() == FRAME_RATE_WINDOW {
+                    self.recent_frames.pop_front();
+                }
+                self.recent_frames.push_back(now);
+            }
+            SentKind::Lost(count) => self.stats.lost_frames += count,
+            SentKind::Incomplete => self.stats.incomplete_frames += 1,
+            SentKind::Other => {}
+        }
+    }
+
+    /// Record a payload which is evicted from the channel after it's delivered.
+    fn record_evicted(&mut self, evicted: &StreamResult<Payload>) {
+        if evicted.is_ok() {
+            self.stats.delivered_frames -= 1;
+            self.stats.dropped_frames += 1;
+        }
+    }
+
+    fn record_latency(&mut self, latency: time::Duration) {
+        self.latency_count += 1;
+        self.total_latency += latency;
+        self.stats.maximum_latency = self.stats.maximum_latency.max(latency);
+    }
+
+    fn snapshot(&self, now: Instant) -> StreamStatistics {
+        let elapsed = (now - self.started_at).as_secs_f64();
+        let mut stats = self.stats.clone();
+
+        if let (Some(first), Some(last)) = (self.recent_frames.front(), self.recent_frames.back()) {
+            let window = (*last - *first).as_secs_f64();
+            if window > 0.0 {
+                stats.current_frame_rate = (self.recent_frames.len() - 1) as f64 / window;
+            }
+        }
+        if elapsed > 0.0 {
+            stats.average_frame_rate = self.received_frames as f64 / elapsed;
+            stats.throughput = self.received_bytes as f64 / elapsed;
+        }
+        if self.latency_count > 0 {
+            stats.average_latency = time::Duration::from_secs_f64(
+                self.total_latency.as_secs_f64() / self.latency_count as f64,
+            );
+        }
+
+        stats
+    }
+}
+
 impl From<async_std::channel::RecvError> for StreamError {
     fn from(err: async_std::channel::RecvError) -> Self {
         StreamError::ReceiveError(err.to_string().into())
@@ -199,3 +877,72 @@ impl<T> From<async_std::channel::TrySendError<T>> for StreamError {
         StreamError::ReceiveError(err.to_string().into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> time::Duration {
+        time::Duration::from_millis(millis)
+    }
+
+    fn assert_approx_eq(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn test_frame_rate_and_throughput() {
+        let started_at = Instant::now();
+        let mut recorder = StatisticsRecorder::new(started_at);
+        // 10 frames at 10fps, then 16 frames at 20fps.
+        for i in 1..=10 {
+            recorder.record_sent(SentKind::Frame(100), true, started_at + ms(100 * i));
+        }
+        for i in 1..=16 {
+            recorder.record_sent(SentKind::Frame(100), true, started_at + ms(1000 + 50 * i));
+        }
+
+        let stats = recorder.snapshot(started_at + ms(2000));
+        assert_eq!(stats.delivered_frames, 26);
+        // Only the latest `FRAME_RATE_WINDOW` frames are used for the current frame rate.
+        assert_approx_eq(stats.current_frame_rate, 20.0);
+        assert_approx_eq(stats.average_frame_rate, 13.0);
+        assert_approx_eq(stats.throughput, 1300.0);
+    }
+
+    #[test]
+    fn test_latency() {
+        let mut recorder = StatisticsRecorder::new(Instant::now());
+        let stats = recorder.snapshot(Instant::now());
+        assert_eq!(stats.average_latency, time::Duration::ZERO);
+        assert_eq!(stats.maximum_latency, time::Duration::ZERO);
+
+        recorder.record_latency(ms(1));
+        recorder.record_latency(ms(5));
+        recorder.record_latency(ms(3));
+        let stats = recorder.snapshot(Instant::now());
+        assert_eq!(stats.average_latency, ms(3));
+        assert_eq!(stats.maximum_latency, ms(5));
+    }
+
+    #[test]
+    fn test_record_evicted() {
+        let started_at = Instant::now();
+        let mut recorder = StatisticsRecorder::new(started_at);
+        recorder.record_sent(SentKind::Frame(100), true, started_at + ms(100));
+        recorder.record_sent(SentKind::Frame(100), true, started_at + ms(200));
+        recorder.record_sent(SentKind::Frame(100), false, started_at + ms(300));
+
+        // An evicted payload is counted as dropped instead of delivered.
+        recorder.record_evicted(&Ok(Payload::empty(PayloadBuf::Owned(vec![]))));
+        let stats = recorder.snapshot(started_at + ms(300));
+        assert_eq!(stats.delivered_frames, 1);
+        assert_eq!(stats.dropped_frames, 2);
+
+        // An evicted error isn't a frame.
+        recorder.record_evicted(&Err(StreamError::Timeout));
+        let stats = recorder.snapshot(started_at + ms(300));
+        assert_eq!(stats.delivered_frames, 1);
+        assert_eq!(stats.dropped_frames, 2);
+    }
+}
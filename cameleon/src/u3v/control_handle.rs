@@ -17,7 +17,7 @@ use cameleon_device::{
 };
 use tracing::error;
 
-use super::register_map::{self, Abrm, ManifestTable, Sbrm, Sirm};
+use super::register_map::{self, Abrm, Eirm, ManifestTable, Sbrm, Sirm};
 
 use crate::{camera::DeviceControl, genapi::CompressionType, ControlError, ControlResult};
 
@@ -33,7 +33,21 @@ const INITIAL_MAXIMUM_CMD_LENGTH: u32 = 128;
 /// This value is temporarily used until the device's bootstrap register value is read.
 const INITIAL_MAXIMUM_ACK_LENGTH: u32 = 128;
 
-const PAYLOAD_TRANSFER_SIZE: u32 = 1024 * 64;
+/// Default size of each payload transfer of streaming.
+const DEFAULT_PAYLOAD_TRANSFER_SIZE: u32 = 1024 * 64;
+
+/// Timeout duration to wait for stale acks in recovering the control channel.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Acks whose request id is behind the current one within this window are regarded as stale
+/// acks of former transactions.
+const STALE_REQUEST_ID_WINDOW: u16 = 0x100;
+
+/// Length of prefix magic and CCD of a command packet.
+const CMD_HEADER_LENGTH: usize = 4 + 8;
+
+/// Length of prefix magic and CCD of an acknowledge packet.
+const ACK_HEADER_LENGTH: usize = 4 + 8;
 
 /// This handle provides low level API to read and write data from the device.  
 /// See [`ControlHandle::abrm`] and [`register_map`](super::register_map) which provide more
@@ -80,6 +94,8 @@ pub struct ControlHandle {
     sbrm: Option<Sbrm>,
     /// Cache for `Sirm`.
     sirm: Option<Sirm>,
+    /// Cache for `Eirm`.
+    eirm: Option<Eirm>,
     /// Cache for `ManifestTable`.
     manifest_table: Option<ManifestTable>,
 }
@@ -132,6 +148,43 @@ impl ControlHandle {
         self.config.retry_count = count;
     }
 
+    /// The value determines how many times to retry a read transaction after a failure, e.g.
+    /// timeout or request id mismatch.
+    ///
+    /// Each failure is followed by recovery of the control channel, which drains stale
+    /// acknowledges, clears the halt of the endpoint and resynchronizes request ids. Write
+    /// transactions are never retried because they may have side effects on the device.
+    #[must_use]
+    pub fn read_retry_count(&self) -> u16 {
+        self.config.read_retry_count
+    }
+
+    /// Set the value determines how many times to retry a read transaction after a failure.
+    ///
+    /// See [`ControlHandle::read_retry_count`] for details.
+    pub fn set_read_retry_count(&mut self, count: u16) {
+        self.config.read_retry_count = count;
+    }
+
+    /// Size of each bulk transfer which carries payload data of streaming.
+    #[must_use]
+    pub fn payload_transfer_size(&self) -> u32 {
+        self.config.payload_transfer_size
+    }
+
+    /// Set size of each bulk transfer which carries payload data of streaming.
+    ///
+    /// The size is rounded up to the payload size alignment of the device, and takes effect when
+    /// streaming is enabled next time. Larger transfers reduce the overhead per transfer, but
+    /// consume more memory for each transfer in flight.
+    ///
+    /// # Panics
+    /// If `size` is zero.
+    pub fn set_payload_transfer_size(&mut self, size: u32) {
+        assert!(size > 0, "payload transfer size must not be zero");
+        self.config.payload_transfer_size = size;
+    }
+
     /// Returns the device info of the handle.
     pub fn device_info(&self) -> &u3v::DeviceInfo {
         &self.info
@@ -174,6 +227,21 @@ impl ControlHandle {
         Ok(sirm)
     }
 
+    /// Returns [`Eirm`].
+    pub fn eirm(&mut self) -> ControlResult<Eirm> {
+        if let Some(eirm) = self.eirm {
+            return Ok(eirm);
+        }
+
+        let addr = self.sbrm()?.eirm_address(self)?.ok_or_else(|| {
+            ControlError::InvalidDevice("the u3v device doesn't have `EIRM ADDRESS`".into())
+        })?;
+        let eirm = Eirm::new(addr);
+        self.eirm = Some(eirm);
+
+        Ok(eirm)
+    }
+
     /// Returns [`ManifestTable`].
     pub fn manifest_table(&mut self) -> ControlResult<ManifestTable> {
         if let Some(manifest_table) = self.manifest_table {
@@ -198,6 +266,7 @@ impl ControlHandle {
             abrm: None,
             sbrm: None,
             sirm: None,
+            eirm: None,
             manifest_table: None,
         })
     }
@@ -227,8 +296,46 @@ impl ControlHandle {
 
     fn send_cmd<'a, T, U>(&'a mut self, cmd: T) -> ControlResult<U>
     where
-        T: cmd::CommandScd,
+        T: cmd::CommandScd + Clone,
         U: ack::ParseScd<'a>,
+    {
+        // Only read transactions are retried because they don't have side effects on the device.
+        let is_idempotent = matches!(
+            cmd.scd_kind(),
+            cmd::ScdKind::ReadMem | cmd::ScdKind::ReadMemStacked
+        );
+        let mut retry_count = if is_idempotent {
+            self.config.read_retry_count
+        } else {
+            0
+        };
+
+        let recv_len = loop {
+            match self.transact(cmd.clone()) {
+                Ok(recv_len) => break recv_len,
+                Err(err @ ControlError::Timeout) | Err(err @ ControlError::Io(..)) => {
+                    error!(?err, "control transaction failed, recovering the channel");
+                    self.recover(matches!(err, ControlError::Timeout))?;
+                    if retry_count == 0 {
+                        return Err(err);
+                    }
+                    retry_count -= 1;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        // This codes seems weird due to a lifetime problem.
+        // `ack::AckPacket::parse` is a fast operation, so it's ok to call it repeatedly.
+        Ok(ack::AckPacket::parse(&self.buffer[0..recv_len])
+            .unwrap()
+            .scd_as()?)
+    }
+
+    /// Send a command and receive the corresponding ack. Returns the length of the ack.
+    fn transact<T>(&mut self, cmd: T) -> ControlResult<usize>
+    where
+        T: cmd::CommandScd,
     {
         let cmd = cmd.finalize(self.next_req_id);
         let cmd_len = cmd.cmd_len();
@@ -244,14 +351,23 @@ impl ControlHandle {
 
         // Receive ack and interpret the packet.
         let mut retry_count = self.config.retry_count;
-        let mut ok = None;
         while retry_count > 0 {
             let recv_len = self
                 .inner
                 .recv(&mut self.buffer, self.config.timeout_duration)?;
 
             let ack = ack::AckPacket::parse(&self.buffer[0..recv_len])?;
-            self.verify_ack(&ack)?;
+            if self.is_stale(&ack) {
+                // Ack of a former transaction which arrived too late, just ignore it.
+                continue;
+            }
+            if let Err(err) = self.verify_ack(&ack) {
+                // The transaction is completed even if the device returns an error status.
+                if ack.request_id() == self.next_req_id {
+                    self.next_req_id = self.next_req_id.wrapping_add(1);
+                }
+                return Err(err);
+            }
 
             // Retry up to retry count.
             if ack.scd_kind() == ack::ScdKind::Pending {
@@ -262,37 +378,77 @@ impl ControlHandle {
             }
 
             self.next_req_id = self.next_req_id.wrapping_add(1);
-            ok = Some(recv_len);
-            break;
+            return Ok(recv_len);
         }
 
-        // This codes seems weird due to a lifetime problem.
-        // `ack::AckPacket::parse` is a fast operation, so it's ok to call it repeatedly.
-        if let Some(recv_len) = ok {
-            Ok(ack::AckPacket::parse(&self.buffer[0..recv_len])
-                .unwrap()
-                .scd_as()?)
+        Err(ControlError::Io(anyhow::Error::msg(
+            "the number of times pending was returned exceeds the retry_count.",
+        )))
+    }
+
+    /// Recover the control channel from a failed transaction so that following transactions
+    /// succeed.
+    ///
+    /// If `timed_out` is `true`, wait for the late ack of the failed transaction, because the
+    /// device may be still processing the command.
+    fn recover(&mut self, timed_out: bool) -> ControlResult<()> {
+        // Drain stale acks which are still in flight.
+        let maximum_ack_length = self.config.maximum_ack_length as usize;
+        if self.buffer.len() < maximum_ack_length {
+            self.buffer.resize(maximum_ack_length, 0);
+        }
+        let mut timeout = if timed_out {
+            self.config.timeout_duration
         } else {
-            Err(ControlError::Io(anyhow::Error::msg(
-                "the number of times pending was returned exceeds the retry_count.",
-            )))
+            DRAIN_TIMEOUT
+        };
+        while self.inner.recv(&mut self.buffer, timeout).is_ok() {
+            timeout = DRAIN_TIMEOUT;
         }
+
+        // The endpoint may be halted by the device.
+        self.inner.clear_halt()?;
+
+        // Skip the request id of the failed transaction, so that its ack is regarded as stale
+        // even if it arrives later.
+        self.next_req_id = self.next_req_id.wrapping_add(1);
+
+        Ok(())
+    }
+
+    /// Returns `true` if the ack is for a former transaction.
+    fn is_stale(&self, ack: &ack::AckPacket) -> bool {
+        let distance = self.next_req_id.wrapping_sub(ack.request_id());
+        distance != 0 && distance <= STALE_REQUEST_ID_WINDOW
     }
 
     fn verify_ack(&self, ack: &ack::AckPacket) -> ControlResult<()> {
-        let status = ack.status().kind();
-        if status != ack::StatusKind::GenCp(ack::GenCpStatus::Success) {
-            return Err(ControlError::Io(anyhow::Error::msg(format!(
-                "invalid status: {:?}",
-                ack.status().kind()
-            ))));
-        }
+        use ack::{GenCpStatus, StatusKind};
 
         if ack.request_id() != self.next_req_id {
             return Err(ControlError::Io(anyhow::Error::msg("request id mismatch")));
         }
 
-        Ok(())
+        let status = ack.status();
+        let err = match status.kind() {
+            StatusKind::GenCp(GenCpStatus::Success) => return Ok(()),
+            StatusKind::GenCp(GenCpStatus::AccessDenied) => ControlError::AccessDenied,
+            StatusKind::GenCp(GenCpStatus::InvalidAddress) => ControlError::InvalidAddress,
+            StatusKind::GenCp(GenCpStatus::WriteProtect) => ControlError::WriteProtect,
+            StatusKind::GenCp(GenCpStatus::BadAlignment) => ControlError::BadAlignment,
+            StatusKind::GenCp(GenCpStatus::Busy) => ControlError::Busy,
+            StatusKind::GenCp(GenCpStatus::NotImplemented) => ControlError::NotImplemented,
+            StatusKind::DeviceSpecific => ControlError::DeviceSpecific(status.code()),
+            kind => ControlError::Io(anyhow::Error::msg(format!("invalid status: {:?}", kind))),
+        };
+        Err(err)
+    }
+
+    fn is_stacked_commands_supported(&mut self) -> ControlResult<bool> {
+        Ok(self
+            .abrm()?
+            .device_capability()?
+            .is_stacked_commands_supported())
     }
 
     fn verify_xml(&mut self, xml: &[u8], ent: register_map::ManifestEntry) -> ControlResult<()> {
@@ -390,6 +546,140 @@ impl DeviceControl for ControlHandle {
         Ok(())
     }
 
+    /// Reads data from multiple addresses with `ReadMemStacked` commands if the device supports
+    /// stacked commands, otherwise reads each entry in turn.
+    ///
+    /// Entries are packed into as few commands as the maximum command and acknowledge length
+    /// allow.
+    fn read_stacked(&mut self, entries: &mut [(u64, &mut [u8])]) -> ControlResult<()> {
+        unwrap_or_log!(self.assert_open());
+
+        if !unwrap_or_log!(self.is_stacked_commands_supported()) {
+            for (address, buf) in entries {
+                unwrap_or_log!(self.read(*address, buf));
+            }
+            return Ok(());
+        }
+
+        let maximum_read_length =
+            cmd::ReadMem::maximum_read_length(self.config.maximum_ack_length as usize) as usize;
+        // Each entry of `ReadMemStacked` consists of address(8bytes), reserved(2bytes) and read
+        // length(2bytes).
+        let maximum_entry_count =
+            (self.config.maximum_cmd_length as usize).saturating_sub(CMD_HEADER_LENGTH) / 12;
+
+        // Split entries so that each of them fits into an acknowledge.
+        let mut chunks = vec![];
+        for (address, buf) in entries.iter_mut() {
+            let mut address = *address;
+            for chunk in buf.chunks_mut(maximum_read_length) {
+                let len = chunk.len() as u64;
+                chunks.push((address, chunk));
+                address += len;
+            }
+        }
+
+        let mut rest = chunks.as_mut_slice();
+        while !rest.is_empty() {
+            let mut batch_len = 0;
+            let mut read_length = 0;
+            for (_, chunk) in rest.iter() {
+                if batch_len > 0
+                    && (batch_len == maximum_entry_count
+                        || read_length + chunk.len() > maximum_read_length)
+                {
+                    break;
+                }
+                batch_len += 1;
+                read_length += chunk.len();
+            }
+            let (batch, tail) = std::mem::take(&mut rest).split_at_mut(batch_len);
+            rest = tail;
+
+            let cmd_entries = batch
+                .iter()
+                .map(|(address, chunk)| cmd::ReadMem::new(*address, chunk.len() as u16))
+                .collect();
+            let cmd = unwrap_or_log!(cmd::ReadMemStacked::new(cmd_entries));
+            let ack: ack::ReadMemStacked = unwrap_or_log!(self.send_cmd(cmd));
+
+            if ack.data.len() != read_length {
+                let err_msg = "read mem stacked failed: read length mismatch";
+                return Err(ControlError::Io(anyhow::Error::msg(err_msg)));
+            }
+            let mut data = ack.data;
+            for (_, chunk) in batch.iter_mut() {
+                let (head, tail) = data.split_at(chunk.len());
+                chunk.copy_from_slice(head);
+                data = tail;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes data to multiple addresses with `WriteMemStacked` commands if the device supports
+    /// stacked commands, otherwise writes each entry in turn.
+    ///
+    /// Entries are packed into as few commands as the maximum command and acknowledge length
+    /// allow.
+    fn write_stacked(&mut self, entries: &[(u64, &[u8])]) -> ControlResult<()> {
+        unwrap_or_log!(self.assert_open());
+
+        if !unwrap_or_log!(self.is_stacked_commands_supported()) {
+            for (address, data) in entries {
+                unwrap_or_log!(self.write(*address, data));
+            }
+            return Ok(());
+        }
+
+        let maximum_cmd_length = self.config.maximum_cmd_length as usize;
+        // Each entry of the acknowledge consists of reserved(2bytes) and written length(2bytes).
+        let maximum_entry_count =
+            (self.config.maximum_ack_length as usize).saturating_sub(ACK_HEADER_LENGTH) / 4;
+
+        // Split entries so that each of them fits into a command.
+        let mut chunks = vec![];
+        for (address, data) in entries {
+            let cmd = unwrap_or_log!(cmd::WriteMem::new(*address, data));
+            // An entry of `WriteMemStacked` has 4 bytes longer header than `WriteMem` command
+            // because it also contains reserved field and data length.
+            chunks.extend(unwrap_or_log!(
+                cmd.chunks(maximum_cmd_length.saturating_sub(4))
+            ));
+        }
+
+        let mut chunks = chunks.into_iter().peekable();
+        while chunks.peek().is_some() {
+            let mut cmd_length = CMD_HEADER_LENGTH;
+            let mut batch = vec![];
+            while let Some(chunk) = chunks.peek() {
+                // Each entry of `WriteMemStacked` consists of address(8bytes), reserved(2bytes),
+                // data length(2bytes) and data.
+                let entry_length = 12 + chunk.data_len();
+                if !batch.is_empty()
+                    && (batch.len() == maximum_entry_count
+                        || cmd_length + entry_length > maximum_cmd_length)
+                {
+                    break;
+                }
+                cmd_length += entry_length;
+                batch.push(chunks.next().unwrap());
+            }
+
+            let data_lens: Vec<_> = batch.iter().map(|chunk| chunk.data_len()).collect();
+            let cmd = unwrap_or_log!(cmd::WriteMemStacked::new(batch));
+            let ack: ack::WriteMemStacked = unwrap_or_log!(self.send_cmd(cmd));
+
+            if !ack.lengths.iter().map(|len| *len as usize).eq(data_lens) {
+                let err_msg = "write mem stacked failed: written length mismatch";
+                return Err(ControlError::Io(anyhow::Error::msg(err_msg)));
+            }
+        }
+
+        Ok(())
+    }
+
     fn genapi(&mut self) -> ControlResult<String> {
         fn zip_err(err: impl std::fmt::Debug) -> ControlError {
             ControlError::InvalidDevice(format!("zipped xml file is broken: {:?}", err).into())
@@ -460,7 +750,7 @@ impl DeviceControl for ControlHandle {
         let required_payload_size = unwrap_or_log!(sirm.required_payload_size(self));
         let required_trailer_size = unwrap_or_log!(sirm.required_leader_size(self));
 
-        let payload_transfer_size = align!(PAYLOAD_TRANSFER_SIZE, u32);
+        let payload_transfer_size = align!(self.config.payload_transfer_size, u32);
         let payload_transfer_count = (required_payload_size / payload_transfer_size as u64) as u32;
         let payload_final_transfer1_size =
             align!(required_payload_size % payload_transfer_size as u64, u64) as u32;
@@ -553,7 +843,17 @@ impl SharedControlHandle {
         #[must_use]
         pub fn retry_count(&self) -> u16,
         /// Thread safe version of [`ControlHandle::set_retry_count`].
-        pub fn set_retry_count(&self, count: u16) -> ()
+        pub fn set_retry_count(&self, count: u16) -> (),
+        /// Thread safe version of [`ControlHandle::read_retry_count`].
+        #[must_use]
+        pub fn read_retry_count(&self) -> u16,
+        /// Thread safe version of [`ControlHandle::set_read_retry_count`].
+        pub fn set_read_retry_count(&self, count: u16) -> (),
+        /// Thread safe version of [`ControlHandle::payload_transfer_size`].
+        #[must_use]
+        pub fn payload_transfer_size(&self) -> u32,
+        /// Thread safe version of [`ControlHandle::set_payload_transfer_size`].
+        pub fn set_payload_transfer_size(&self, size: u32) -> ()
     );
 
     /// Returns the device info of the handle.
@@ -572,6 +872,8 @@ impl DeviceControl for SharedControlHandle {
         fn close(&mut self) -> ControlResult<()>,
         fn read(&mut self, address: u64, buf: &mut [u8]) -> ControlResult<()>,
         fn write(&mut self, address: u64, data: &[u8]) -> ControlResult<()>,
+        fn read_stacked(&mut self, entries: &mut [(u64, &mut [u8])]) -> ControlResult<()>,
+        fn write_stacked(&mut self, entries: &[(u64, &[u8])]) -> ControlResult<()>,
         fn genapi(&mut self) -> ControlResult<String>,
         fn enable_streaming(&mut self) -> ControlResult<()>,
         fn disable_streaming(&mut self) -> ControlResult<()>
@@ -586,11 +888,17 @@ struct ConnectionConfig {
     /// device.
     retry_count: u16,
 
+    /// The value determines how many times to retry a read transaction after a failure.
+    read_retry_count: u16,
+
     /// Maximum length of a command sent to device from host. Unit is byte.
     maximum_cmd_length: u32,
 
     /// Maximum length of a acknowledge sent to host from device. Unit is byte.
     maximum_ack_length: u32,
+
+    /// Size of each payload transfer of streaming. Unit is byte.
+    payload_transfer_size: u32,
 }
 
 impl Default for ConnectionConfig {
@@ -598,8 +906,10 @@ impl Default for ConnectionConfig {
         Self {
             timeout_duration: INITIAL_TIMEOUT_DURATION,
             retry_count: 3,
+            read_retry_count: 2,
             maximum_cmd_length: INITIAL_MAXIMUM_CMD_LENGTH,
             maximum_ack_length: INITIAL_MAXIMUM_ACK_LENGTH,
+            payload_transfer_size: DEFAULT_PAYLOAD_TRANSFER_SIZE,
         }
     }
 }
@@ -0,0 +1,300 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains low level event notification implementation for `U3V` device.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_std::task;
+use cameleon_device::u3v::{self, protocol::event as u3v_event};
+use futures::channel::oneshot;
+use tracing::{error, info, warn};
+
+use crate::{
+    camera::EventStream,
+    event::{Event, EventSender},
+    ControlError, ControlResult, DeviceControl, StreamError, StreamResult,
+};
+
+use super::register_map::{Abrm, Eirm};
+
+/// This type is used to receive event packets from the device.
+pub struct EventHandle {
+    /// Inner channel to receive event packets.
+    pub inner: Arc<Mutex<u3v::ReceiveChannel>>,
+    /// Parameters for event notification.
+    params: EventParams,
+    cancellation_tx: Option<oneshot::Sender<()>>,
+    completion_rx: Option<oneshot::Receiver<()>>,
+}
+
+macro_rules! unwrap_or_poisoned {
+    ($res:expr) => {{
+        $res.map_err(|cause| {
+            let err = StreamError::Poisoned(cause.to_string().into());
+            error!(?err);
+            err
+        })
+    }};
+}
+
+impl EventHandle {
+    /// Read an event packet and returns events contained in the packet.
+    ///
+    /// Buffer size must be equal or larger than [`EventParams::maximum_transfer_length`].
+    pub fn read_events(&self, buf: &mut [u8]) -> StreamResult<Vec<Event>> {
+        if self.is_loop_running() {
+            Err(StreamError::InStreaming)
+        } else {
+            let len = unwrap_or_poisoned!(self.inner.lock())?.recv(buf, self.params.timeout)?;
+            parse_events(&buf[..len])
+        }
+    }
+
+    /// Return params.
+    #[must_use]
+    pub fn params(&self) -> &EventParams {
+        &self.params
+    }
+
+    ///  Return mutable params.
+    pub fn params_mut(&mut self) -> &mut EventParams {
+        &mut self.params
+    }
+
+    pub(super) fn new(device: &u3v::Device) -> ControlResult<Option<Self>> {
+        let inner = device.event_channel()?;
+        Ok(inner.map(|inner| Self {
+            inner: Arc::new(Mutex::new(inner)),
+            params: EventParams::default(),
+            cancellation_tx: None,
+            completion_rx: None,
+        }))
+    }
+
+    fn stop_loop(&mut self) -> StreamResult<()> {
+        if self.is_loop_running() {
+            let (cancellation_tx, completion_rx) = (
+                self.cancellation_tx.take().unwrap(),
+                self.completion_rx.take().unwrap(),
+            );
+            cancellation_tx.send(()).map_err(|_| {
+                StreamError::Poisoned("failed to send cancellation signal to event loop".into())
+            })?;
+            task::block_on(completion_rx)
+                .map_err(|e| StreamError::Poisoned(e.to_string().into()))?;
+        }
+        Ok(())
+    }
+}
+
+impl EventStream for EventHandle {
+    fn open(&mut self) -> StreamResult<()> {
+        unwrap_or_poisoned!(self.inner.lock())?.open().map_err(|e| {
+            error!(?e);
+            e.into()
+        })
+    }
+
+    fn close(&mut self) -> StreamResult<()> {
+        self.stop_loop()?;
+        unwrap_or_poisoned!(self.inner.lock())?
+            .close()
+            .map_err(|e| {
+                error!(?e);
+                e.into()
+            })
+    }
+
+    fn start_event_loop(
+        &mut self,
+        sender: EventSender,
+        ctrl: &mut dyn DeviceControl,
+    ) -> StreamResult<()> {
+        if self.is_loop_running() {
+            return Err(StreamError::InStreaming);
+        }
+
+        self.params = EventParams::from_control(ctrl).map_err(|e| {
+            StreamError::Io(anyhow::Error::msg(format!(
+                "failed to setup event parameters: {}",
+                e
+            )))
+        })?;
+        eirm(ctrl)
+            .and_then(|eirm| eirm.enable_event(ctrl))
+            .map_err(|e| {
+                StreamError::Io(anyhow::Error::msg(format!(
+                    "failed to enable event interface: {}",
+                    e
+                )))
+            })?;
+
+        let (cancellation_tx, cancellation_rx) = oneshot::channel();
+        let (completion_tx, completion_rx) = oneshot::channel();
+        self.cancellation_tx = Some(cancellation_tx);
+        self.completion_rx = Some(completion_rx);
+
+        let event_loop = EventLoop {
+            inner: self.inner.clone(),
+            params: self.params.clone(),
+            sender,
+            completion_tx,
+            cancellation_rx,
+        };
+        std::thread::spawn(|| {
+            event_loop.run();
+        });
+
+        info!("start event loop successfully");
+        Ok(())
+    }
+
+    fn stop_event_loop(&mut self, ctrl: &mut dyn DeviceControl) -> StreamResult<()> {
+        self.stop_loop()?;
+        eirm(ctrl)
+            .and_then(|eirm| eirm.disable_event(ctrl))
+            .map_err(|e| {
+                StreamError::Io(anyhow::Error::msg(format!(
+                    "failed to disable event interface: {}",
+                    e
+                )))
+            })?;
+
+        info!("stop event loop successfully");
+        Ok(())
+    }
+
+    fn is_loop_running(&self) -> bool {
+        debug_assert_eq!(self.completion_rx.is_some(), self.cancellation_tx.is_some());
+        self.completion_rx.is_some()
+    }
+}
+
+impl Drop for EventHandle {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            error!(?e)
+        }
+    }
+}
+
+impl From<EventHandle> for Box<dyn EventStream + Send> {
+    fn from(evt: EventHandle) -> Self {
+        Box::new(evt)
+    }
+}
+
+struct EventLoop {
+    inner: Arc<Mutex<u3v::ReceiveChannel>>,
+    params: EventParams,
+    sender: EventSender,
+    completion_tx: oneshot::Sender<()>,
+    cancellation_rx: oneshot::Receiver<()>,
+}
+
+impl EventLoop {
+    fn run(mut self) {
+        let mut buf = vec![0; self.params.maximum_transfer_length];
+        let inner = self.inner.lock().unwrap();
+
+        loop {
+            // Stop the loop when
+            // 1. `cancellation_tx` sends signal.
+            // 2. `cancellation_tx` is dropped.
+            if self.cancellation_rx.try_recv().transpose().is_some() {
+                break;
+            }
+
+            let len = match inner.recv(&mut buf, self.params.timeout) {
+                Ok(len) => len,
+                Err(err) => {
+                    // Timeout just means that no event has occurred on the device.
+                    let err = StreamError::from(err);
+                    if matches!(err, StreamError::Io(..) | StreamError::Disconnected) {
+                        error!(?err);
+                        self.sender.try_send(Err(err)).ok();
+                    }
+                    continue;
+                }
+            };
+
+            match parse_events(&buf[..len]) {
+                Ok(events) => {
+                    for event in events {
+                        if let Err(err) = self.sender.try_send(Ok(event)) {
+                            warn!(?err);
+                        }
+                    }
+                }
+                Err(err) => {
+                    warn!(?err);
+                    self.sender.try_send(Err(err)).ok();
+                }
+            }
+        }
+
+        if let Err(e) = self.completion_tx.send(()) {
+            error!(?e);
+        }
+    }
+}
+
+/// Parameters to receive event packets.
+#[derive(Debug, Clone, Default)]
+pub struct EventParams {
+    /// Maximum length of an event packet.
+    pub maximum_transfer_length: usize,
+
+    /// Timeout duration of each transaction between device.
+    pub timeout: Duration,
+}
+
+impl EventParams {
+    /// Construct `EventParams`.
+    #[must_use]
+    pub fn new(maximum_transfer_length: usize, timeout: Duration) -> Self {
+        Self {
+            maximum_transfer_length,
+            timeout,
+        }
+    }
+
+    /// Build `EventParams` from [`DeviceControl`].
+    pub fn from_control<Ctrl: DeviceControl + ?Sized>(ctrl: &mut Ctrl) -> ControlResult<Self> {
+        let abrm = Abrm::new(ctrl)?;
+        let eirm = eirm(ctrl)?;
+        let maximum_transfer_length = eirm.maximum_event_transfer_length(ctrl)? as usize;
+        let timeout = abrm.maximum_device_response_time(ctrl)?;
+
+        Ok(Self::new(maximum_transfer_length, timeout))
+    }
+}
+
+fn eirm<Ctrl: DeviceControl + ?Sized>(ctrl: &mut Ctrl) -> ControlResult<Eirm> {
+    Abrm::new(ctrl)?.sbrm(ctrl)?.eirm(ctrl)?.ok_or_else(|| {
+        let msg = "the U3V device doesn't have `EIRM`";
+        error!(msg);
+        ControlError::InvalidDevice(msg.into())
+    })
+}
+
+fn parse_events(buf: &[u8]) -> StreamResult<Vec<Event>> {
+    let packet = u3v_event::EventPacket::parse(buf)
+        .map_err(|e| StreamError::InvalidPayload(format!("invalid event packet: {}", e).into()))?;
+    Ok(packet
+        .scd
+        .iter()
+        .map(|scd| {
+            Event::new(
+                scd.event_id,
+                Duration::from_nanos(scd.timestamp),
+                scd.data.to_vec(),
+            )
+        })
+        .collect())
+}
@@ -46,14 +46,23 @@
 #![allow(clippy::missing_panics_doc)]
 
 pub mod control_handle;
+pub mod event_handle;
 pub mod register_map;
 pub mod stream_handle;
 
 pub use control_handle::{ControlHandle, SharedControlHandle};
+pub use event_handle::{EventHandle, EventParams};
 pub use stream_handle::{StreamHandle, StreamParams};
 
 pub use cameleon_device::u3v::DeviceInfo;
 
+#[cfg(feature = "emulator")]
+pub use cameleon_device::u3v::{
+    bandwidth, clock, disconnect_device, event, fault, frame_source, reconnect_device,
+    snapshot_device, trigger_events, BuilderError, BuilderResult, BusSpeed, EmulatorBuilder,
+    MemorySnapshot, RegisterImage,
+};
+
 use cameleon_device::u3v;
 
 use super::{
@@ -84,6 +93,7 @@ pub fn enumerate_cameras() -> CameleonResult<Vec<Camera<ControlHandle, StreamHan
         } else {
             continue;
         };
+        let evt = EventHandle::new(&dev)?;
         let ctxt = None;
 
         let dev_info = dev.device_info;
@@ -93,8 +103,11 @@ pub fn enumerate_cameras() -> CameleonResult<Vec<Camera<ControlHandle, StreamHan
             serial_number: dev_info.serial_number,
         };
 
-        let camera: Camera<ControlHandle, StreamHandle, DefaultGenApiCtxt> =
+        let mut camera: Camera<ControlHandle, StreamHandle, DefaultGenApiCtxt> =
             Camera::new(ctrl, strm, ctxt, camera_info);
+        if let Some(evt) = evt {
+            camera.set_event_stream(evt);
+        }
         cameras.push(camera)
     }
 
@@ -5,6 +5,7 @@
 //! This module contains low level streaming implementation for `U3V` device.
 
 use std::{
+    collections::VecDeque,
     convert::TryInto,
     sync::{Arc, Mutex, MutexGuard},
     time::Duration,
@@ -17,18 +18,23 @@ use tracing::{error, info, warn};
 
 use crate::{
     camera::PayloadStream,
-    payload::{ImageInfo, Payload, PayloadSender, PayloadType},
+    payload::{ImageInfo, Payload, PayloadBuf, PayloadSender, PayloadType},
     ControlError, ControlResult, DeviceControl, StreamError, StreamResult,
 };
 
 use super::register_map::Abrm;
 
+/// Default number of bulk transfers queued in the streaming loop.
+const DEFAULT_QUEUED_TRANSFERS: usize = 16;
+
 /// This type is used to receive stream packets from the device.
 pub struct StreamHandle {
     /// Inner channel to receive payload data.
     pub inner: Arc<Mutex<u3v::ReceiveChannel>>,
     /// Parameters for streaming.
     params: StreamParams,
+    /// Number of bulk transfers queued in the streaming loop.
+    queued_transfers: usize,
     cancellation_tx: Option<oneshot::Sender<()>>,
     completion_rx: Option<oneshot::Receiver<()>>,
 }
@@ -98,11 +104,34 @@ impl StreamHandle {
         &mut self.params
     }
 
+    /// Number of bulk transfers queued in the streaming loop.
+    ///
+    /// The streaming loop keeps this many transfers in flight, so that the host is always ready
+    /// to receive the data the device sends, even across the boundary of frames.
+    #[must_use]
+    pub fn queued_transfers(&self) -> usize {
+        self.queued_transfers
+    }
+
+    /// Set number of bulk transfers queued in the streaming loop.
+    ///
+    /// The value takes effect when the streaming loop is started next time. Increase the value
+    /// if frames are dropped at high frame rates. Size of each transfer is configured by
+    /// [`ControlHandle::set_payload_transfer_size`](super::ControlHandle::set_payload_transfer_size).
+    ///
+    /// # Panics
+    /// If `count` is zero.
+    pub fn set_queued_transfers(&mut self, count: usize) {
+        assert!(count > 0, "number of queued transfers must not be zero");
+        self.queued_transfers = count;
+    }
+
     pub(super) fn new(device: &u3v::Device) -> ControlResult<Option<Self>> {
         let inner = device.stream_channel()?;
         Ok(inner.map(|inner| Self {
             inner: Arc::new(Mutex::new(inner)),
             params: StreamParams::default(),
+            queued_transfers: DEFAULT_QUEUED_TRANSFERS,
             cancellation_tx: None,
             completion_rx: None,
         }))
@@ -145,6 +174,17 @@ impl PayloadStream for StreamHandle {
             return Err(StreamError::InStreaming);
         }
 
+        if let Some(len) = sender.minimum_buffer_len() {
+            let maximum_payload_size = self.params.maximum_payload_size();
+            if len < maximum_payload_size {
+                error!(
+                    len,
+                    maximum_payload_size, "announced buffer is smaller than the payload"
+                );
+                return Err(StreamError::BufferTooSmall);
+            }
+        }
+
         let (cancellation_tx, cancellation_rx) = oneshot::channel();
         let (completion_tx, completion_rx) = oneshot::channel();
         self.cancellation_tx = Some(cancellation_tx);
@@ -153,6 +193,7 @@ impl PayloadStream for StreamHandle {
         let strm_loop = StreamingLoop {
             inner: self.inner.clone(),
             params: self.params.clone(),
+            queued_transfers: self.queued_transfers,
             sender,
             completion_tx,
             cancellation_rx,
@@ -205,6 +246,7 @@ impl From<StreamHandle> for Box<dyn PayloadStream> {
 struct StreamingLoop {
     inner: Arc<Mutex<u3v::ReceiveChannel>>,
     params: StreamParams,
+    queued_transfers: usize,
     sender: PayloadSender,
     completion_tx: oneshot::Sender<()>,
     cancellation_rx: oneshot::Receiver<()>,
@@ -212,27 +254,20 @@ struct StreamingLoop {
 
 impl StreamingLoop {
     fn run(mut self) {
-        let mut trailer_buf = vec![0; self.params.trailer_size];
-        let mut payload_buf_opt = None;
-        let mut leader_buf = vec![0; self.params.leader_size];
-        let mut inner = self.inner.lock().unwrap();
+        let layout = transfer_layout(&self.params);
+        // Frames whose transfers are submitted, the front is the oldest one.
+        // NOTE: Buffers of the frames must not be reallocated nor dropped while their transfers
+        // are pending.
+        let mut frames: VecDeque<Frame> = VecDeque::new();
+        // Frames whose buffers can be reused.
+        let mut spare_frames: Vec<Frame> = Vec::new();
+        // Block id of the last received leader, which is used to detect lost frames.
+        let mut last_block_id = None;
+        let channel = self.inner.clone();
+        let inner = channel.lock().unwrap();
+        let mut async_pool = AsyncPool::new(&inner);
 
         loop {
-            macro_rules! unwrap_or_continue {
-                ($result:expr, $payload_buf:expr) => {
-                    match $result {
-                        Ok(v) => v,
-                        Err(e) => {
-                            warn!(?e);
-                            // Reuse `payload_buf`.
-                            payload_buf_opt = $payload_buf;
-                            self.sender.try_send(Err(e)).ok();
-                            continue;
-                        }
-                    }
-                };
-            }
-
             // Stop the loop when
             // 1. `cancellation_tx` sends signal.
             // 2. `cancellation_tx` is dropped.
@@ -240,67 +275,314 @@ impl StreamingLoop {
                 break;
             }
 
-            let maximum_payload_size = self.params.maximum_payload_size();
-            let mut payload_buf = match payload_buf_opt.take() {
-                Some(payload_buf) => payload_buf,
-                None => match self.sender.try_recv() {
-                    Ok(mut payload) => {
-                        if payload.payload.len() != maximum_payload_size {
-                            payload.payload.resize(maximum_payload_size, 0);
-                        }
-                        payload.payload
-                    }
-                    Err(_) => {
-                        vec![0; maximum_payload_size]
+            // Keep the queue of transfers full so that the device never waits for the host.
+            if let Err(err) =
+                self.fill_queue(&mut async_pool, &layout, &mut frames, &mut spare_frames)
+            {
+                error!(?err);
+                resync(&mut async_pool, &mut frames, &mut spare_frames);
+                if self.send(Err(err)) {
+                    continue;
+                }
+                break;
+            }
+
+            let frame = frames.front_mut().unwrap();
+            let transfer = layout[frame.completed];
+            let res = async_pool
+                .poll(self.params.timeout)
+                .map_err(StreamError::from)
+                .and_then(|len| frame.complete(transfer, len));
+            if let Err(err) = res {
+                // Timeout before the leader just means that the device doesn't send a frame yet.
+                // Invalid leader means the loop is out of sync with the device, which is
+                // recovered by discarding the transfers in flight.
+                let is_waiting_leader = transfer == Transfer::Leader
+                    && !matches!(err, StreamError::Io(..) | StreamError::Disconnected);
+                if is_waiting_leader && matches!(err, StreamError::Timeout) {
+                    continue;
+                }
+                resync(&mut async_pool, &mut frames, &mut spare_frames);
+                if !is_waiting_leader {
+                    warn!(?err);
+                    if !self.send(Err(err)) {
+                        break;
                     }
-                },
-            };
-
-            let leader = match read_leader(&mut inner, &self.params, &mut trailer_buf) {
-                Ok(leader) => leader,
-                Err(err) => {
-                    // Report and send error if the error is fatal.
-                    if matches!(err, StreamError::Io(..) | StreamError::Disconnected) {
-                        error!(?err);
-                        self.sender.try_send(Err(err)).ok();
+                }
+                continue;
+            }
+
+            if transfer == Transfer::Leader {
+                if let Some(err) = detect_frames_lost(&mut last_block_id, frame.block_id) {
+                    warn!(?err);
+                    if !self.send(Err(err)) {
+                        break;
                     }
-                    payload_buf_opt = Some(payload_buf);
-                    continue;
                 }
-            };
-            let read_payload_size = unwrap_or_continue!(
-                read_payload(&mut inner, &self.params, &mut payload_buf),
-                Some(payload_buf)
-            );
-            let trailer = unwrap_or_continue!(
-                read_trailer(&mut inner, &self.params, &mut leader_buf),
-                Some(payload_buf)
-            );
+            }
 
-            let payload = unwrap_or_continue!(
-                PayloadBuilder {
-                    leader,
-                    payload_buf,
-                    read_payload_size,
-                    trailer
+            if frame.completed == layout.len() {
+                let mut frame = frames.pop_front().unwrap();
+                let res = frame.build();
+                if let Err(err) = &res {
+                    warn!(?err);
+                }
+                match res {
+                    // No buffer is queued for the frame, so the frame is dropped.
+                    Ok(payload) if frame.is_scratch => {
+                        warn!(
+                            block_id = frame.block_id,
+                            "no buffer is queued, drop the frame"
+                        );
+                        self.sender.record_dropped(&payload);
+                        frame.payload_buf = payload.payload;
+                        spare_frames.push(frame);
+                    }
+                    res => {
+                        spare_frames.push(frame);
+                        if !self.send(res) {
+                            break;
+                        }
+                    }
                 }
-                .build(),
-                None
-            );
-            if let Err(err) = self.sender.try_send(Ok(payload)) {
-                warn!(?err);
             }
         }
 
-        if let Err(e) = self.completion_tx.send(()) {
+        resync(&mut async_pool, &mut frames, &mut spare_frames);
+        drop(async_pool);
+        drop(inner);
+
+        // Give back announced buffers, then drop the sender so that the receiver can revoke them.
+        let Self {
+            sender,
+            completion_tx,
+            ..
+        } = self;
+        for frame in spare_frames {
+            sender.give_back(frame.payload_buf);
+        }
+        drop(sender);
+
+        if let Err(e) = completion_tx.send(()) {
             error!(?e);
         }
     }
+
+    fn fill_queue(
+        &self,
+        async_pool: &mut AsyncPool,
+        layout: &[Transfer],
+        frames: &mut VecDeque<Frame>,
+        spare_frames: &mut Vec<Frame>,
+    ) -> StreamResult<()> {
+        while async_pool.pending() < self.queued_transfers {
+            if !matches!(frames.back(), Some(frame) if frame.submitted < layout.len()) {
+                let mut frame = spare_frames
+                    .pop()
+                    .unwrap_or_else(|| Frame::new(&self.params));
+                self.prepare_frame(&mut frame);
+                frames.push_back(frame);
+            }
+
+            let frame = frames.back_mut().unwrap();
+            let buf = frame.buffer(layout[frame.submitted]);
+            async_pool.submit(buf)?;
+            frame.submitted += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Send a payload or an error to the receiver according to the overflow policy of the
+    /// channel.
+    ///
+    /// Returns `false` if the loop is cancelled while waiting for room in the channel.
+    fn send(&mut self, payload: StreamResult<Payload>) -> bool {
+        // The payload is given back to the sender on cancellation so that announced buffers
+        // aren't lost.
+        let send = self.sender.send_until(payload, &mut self.cancellation_rx);
+        match task::block_on(send) {
+            Some(res) => {
+                if let Err(err) = res {
+                    warn!(?err);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn prepare_frame(&self, frame: &mut Frame) {
+        frame.submitted = 0;
+        frame.completed = 0;
+        frame.read_payload_size = 0;
+
+        let maximum_payload_size = self.params.maximum_payload_size();
+        let is_reusable = match &frame.payload_buf {
+            PayloadBuf::Owned(buf) => {
+                self.sender.allocates_buffers() && buf.len() == maximum_payload_size
+            }
+            PayloadBuf::Announced(_) => true,
+        };
+        if is_reusable {
+            return;
+        }
+
+        if let Some(buf) = self.next_buffer(maximum_payload_size) {
+            frame.payload_buf = buf;
+            frame.is_scratch = false;
+        } else {
+            // Receive the frame into a scratch buffer to keep up with the device.
+            if frame.payload_buf.len() != maximum_payload_size {
+                frame.payload_buf = PayloadBuf::Owned(vec![0; maximum_payload_size]);
+            }
+            frame.is_scratch = true;
+        }
+    }
+
+    /// Returns a buffer sent back from the receiver if available. Returns `None` if no buffer is
+    /// queued and the streaming loop isn't allowed to allocate a buffer.
+    fn next_buffer(&self, maximum_payload_size: usize) -> Option<PayloadBuf> {
+        match self.sender.try_recv() {
+            Ok(payload) => match payload.payload {
+                PayloadBuf::Owned(mut buf) => {
+                    if buf.len() != maximum_payload_size {
+                        buf.resize(maximum_payload_size, 0);
+                    }
+                    Some(PayloadBuf::Owned(buf))
+                }
+                // Announced buffers are validated when the streaming loop starts.
+                buf @ PayloadBuf::Announced(_) => Some(buf),
+            },
+            Err(_) if self.sender.allocates_buffers() => {
+                Some(PayloadBuf::Owned(vec![0; maximum_payload_size]))
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Returns `StreamError::FramesLost` if block ids aren't contiguous.
+fn detect_frames_lost(last_block_id: &mut Option<u64>, block_id: u64) -> Option<StreamError> {
+    let last = last_block_id.replace(block_id)?;
+    match last.checked_add(1) {
+        Some(next) if block_id == next => None,
+        Some(next) if block_id > next => Some(StreamError::FramesLost {
+            from: next,
+            to: block_id - 1,
+        }),
+        // Wrapping around at `u64::MAX` is also regarded as going backwards.
+        _ => {
+            warn!(last, block_id, "block id has gone backwards");
+            None
+        }
+    }
+}
+
+/// Cancel all transfers in flight and discard the frames that they belong to.
+fn resync(async_pool: &mut AsyncPool, frames: &mut VecDeque<Frame>, spare_frames: &mut Vec<Frame>) {
+    async_pool.cancel_all();
+    while !async_pool.is_empty() {
+        async_pool.poll(Duration::from_secs(1)).ok();
+    }
+    spare_frames.extend(frames.drain(..));
+}
+
+/// A bulk transfer which the device sends for a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    Leader,
+    Payload { offset: usize, len: usize },
+    Trailer,
+}
+
+/// Returns transfers of a frame in the order the device sends them.
+fn transfer_layout(params: &StreamParams) -> Vec<Transfer> {
+    let mut layout = vec![Transfer::Leader];
+    let mut offset = 0;
+    let mut push_payload = |len| {
+        if len != 0 {
+            layout.push(Transfer::Payload { offset, len });
+            offset += len;
+        }
+    };
+    for _ in 0..params.payload_count {
+        push_payload(params.payload_size);
+    }
+    push_payload(params.payload_final1_size);
+    push_payload(params.payload_final2_size);
+    layout.push(Transfer::Trailer);
+    layout
+}
+
+/// Buffers and progress of a frame in flight.
+struct Frame {
+    leader_buf: Vec<u8>,
+    payload_buf: PayloadBuf,
+    /// `true` if `payload_buf` is a scratch buffer, which is never sent to the receiver.
+    is_scratch: bool,
+    trailer_buf: Vec<u8>,
+    /// Number of submitted transfers.
+    submitted: usize,
+    /// Number of completed transfers.
+    completed: usize,
+    block_id: u64,
+    read_payload_size: usize,
+}
+
+impl Frame {
+    fn new(params: &StreamParams) -> Self {
+        Self {
+            leader_buf: vec![0; params.leader_size],
+            payload_buf: PayloadBuf::default(),
+            is_scratch: false,
+            trailer_buf: vec![0; params.trailer_size],
+            submitted: 0,
+            completed: 0,
+            block_id: 0,
+            read_payload_size: 0,
+        }
+    }
+
+    fn buffer(&mut self, transfer: Transfer) -> &mut [u8] {
+        match transfer {
+            Transfer::Leader => &mut self.leader_buf,
+            Transfer::Payload { offset, len } => &mut self.payload_buf[offset..offset + len],
+            Transfer::Trailer => &mut self.trailer_buf,
+        }
+    }
+
+    fn complete(&mut self, transfer: Transfer, len: usize) -> StreamResult<()> {
+        match transfer {
+            // Validate the leader early to detect the loss of synchronization with the device.
+            Transfer::Leader => {
+                self.block_id = parse_leader(&self.leader_buf)?.block_id();
+            }
+            Transfer::Payload { .. } => self.read_payload_size += len,
+            Transfer::Trailer => {}
+        }
+        self.completed += 1;
+        Ok(())
+    }
+
+    fn build(&mut self) -> StreamResult<Payload> {
+        let leader = parse_leader(&self.leader_buf)?;
+        let trailer = parse_trailer(&self.trailer_buf)?;
+        PayloadBuilder {
+            leader,
+            payload_buf: &mut self.payload_buf,
+            read_payload_size: self.read_payload_size,
+            trailer,
+        }
+        .build()
+    }
 }
 
 struct PayloadBuilder<'a> {
     leader: u3v_stream::Leader<'a>,
-    payload_buf: Vec<u8>,
+    /// The buffer is taken only if the payload is built successfully, so that the buffer is
+    /// reused otherwise.
+    payload_buf: &'a mut PayloadBuf,
     read_payload_size: usize,
     trailer: u3v_stream::Trailer<'a>,
 }
@@ -348,7 +630,7 @@ impl<'a> PayloadBuilder<'a> {
             id,
             payload_type: PayloadType::Image,
             image_info,
-            payload: self.payload_buf,
+            payload: std::mem::take(self.payload_buf),
             valid_payload_size,
             timestamp: leader.timestamp(),
         })
@@ -401,7 +683,7 @@ impl<'a> PayloadBuilder<'a> {
             id,
             payload_type: PayloadType::ImageExtendedChunk,
             image_info,
-            payload: self.payload_buf,
+            payload: std::mem::take(self.payload_buf),
             valid_payload_size,
             timestamp: leader.timestamp(),
         })
@@ -418,7 +700,7 @@ impl<'a> PayloadBuilder<'a> {
             id,
             payload_type: PayloadType::Chunk,
             image_info: None,
-            payload: self.payload_buf,
+            payload: std::mem::take(self.payload_buf),
             valid_payload_size,
             timestamp: leader.timestamp(),
         })
@@ -533,7 +815,7 @@ fn read_leader<'a>(
     let leader_size = params.leader_size;
     recv(inner, params, buf, leader_size)?;
 
-    u3v_stream::Leader::parse(buf).map_err(|e| StreamError::InvalidPayload(format!("{}", e).into()))
+    parse_leader(buf)
 }
 
 fn read_payload(
@@ -573,6 +855,14 @@ fn read_trailer<'a>(
     let trailer_size = params.trailer_size as usize;
     recv(inner, params, buf, trailer_size)?;
 
+    parse_trailer(buf)
+}
+
+fn parse_leader(buf: &[u8]) -> StreamResult<u3v_stream::Leader<'_>> {
+    u3v_stream::Leader::parse(buf).map_err(|e| StreamError::InvalidPayload(format!("{}", e).into()))
+}
+
+fn parse_trailer(buf: &[u8]) -> StreamResult<u3v_stream::Trailer<'_>> {
     u3v_stream::Trailer::parse(buf)
         .map_err(|e| StreamError::InvalidPayload(format!("invalid trailer: {}", e).into()))
 }
@@ -0,0 +1,1490 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#![cfg(feature = "emulator")]
+
+use std::{convert::TryInto, time::Duration};
+
+use async_std::{future, task};
+use cameleon::{
+    genapi::GenApiError,
+    payload::{self, BufferPool, OverflowPolicy, PayloadType, PixelFormat, StreamConfig},
+    u3v::{
+        self,
+        bandwidth::{Bandwidth, Overflow},
+        clock::VirtualClock,
+        fault::{ControlFault, ControlFaultKind, StreamFault, StreamFaultKind},
+        frame_source::{Checkerboard, FrameCounter, RawFiles},
+        EmulatorBuilder,
+    },
+    CameleonError, Camera, ControlError, DeviceControl, EventStream, StreamError,
+};
+use cameleon_device::u3v::{
+    prelude::*,
+    protocol::{ack, cmd, event},
+    register_map::{abrm, eirm},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Builds an emulated device with `serial`, then opens the camera of the device.
+fn open_emulated_camera(
+    builder: EmulatorBuilder,
+    serial: &str,
+) -> Camera<u3v::ControlHandle, u3v::StreamHandle> {
+    builder.serial_number(serial).unwrap().build();
+
+    let mut camera = u3v::enumerate_cameras()
+        .unwrap()
+        .into_iter()
+        .find(|cam| cam.info().serial_number == serial)
+        .unwrap();
+    camera.open().unwrap();
+    camera
+}
+
+#[test]
+fn test_emulated_camera() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0001");
+    camera.load_context().unwrap();
+
+    let abrm = camera.ctrl.abrm().unwrap();
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0001");
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_streaming() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0002");
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let mut last = None;
+    for _ in 0..5 {
+        let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+            .unwrap()
+            .unwrap();
+        let image_info = payload.image_info().unwrap();
+        assert_eq!(image_info.pixel_format, PixelFormat::Mono8);
+        assert_eq!(image_info.image_size, image_info.width * image_info.height);
+        assert_eq!(payload.image().unwrap().len(), image_info.image_size);
+        if let Some((last_id, last_timestamp)) = last {
+            assert!(payload.id() > last_id);
+            assert!(payload.timestamp() > last_timestamp);
+        }
+        last = Some((payload.id(), payload.timestamp()));
+        payload_rx.send_back(payload);
+    }
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_image_format() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0003");
+    camera.load_context().unwrap();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let width = ctxt.node("Width").unwrap().as_integer(&ctxt).unwrap();
+    let height = ctxt.node("Height").unwrap().as_integer(&ctxt).unwrap();
+    let pixel_format = ctxt
+        .node("PixelFormat")
+        .unwrap()
+        .as_enumeration(&ctxt)
+        .unwrap();
+    let payload_size = ctxt.node("PayloadSize").unwrap().as_integer(&ctxt).unwrap();
+    let gain = ctxt.node("Gain").unwrap().as_float(&ctxt).unwrap();
+
+    width.set_value(&mut ctxt, 320).unwrap();
+    height.set_value(&mut ctxt, 240).unwrap();
+    pixel_format
+        .set_entry_by_symbolic(&mut ctxt, "Mono16")
+        .unwrap();
+    assert_eq!(payload_size.value(&mut ctxt).unwrap(), 320 * 240 * 2);
+
+    gain.set_value(&mut ctxt, 1.5).unwrap();
+    assert!((gain.value(&mut ctxt).unwrap() - 1.5).abs() < f64::EPSILON);
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let mut ctxt = camera.params_ctxt().unwrap();
+    assert!(!width.is_writable(&mut ctxt).unwrap());
+
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    let image_info = payload.image_info().unwrap();
+    assert_eq!(image_info.pixel_format, PixelFormat::Mono16);
+    assert_eq!(image_info.width, 320);
+    assert_eq!(image_info.height, 240);
+    assert_eq!(payload.image().unwrap().len(), 320 * 240 * 2);
+    payload_rx.send_back(payload);
+
+    camera.stop_streaming().unwrap();
+    let mut ctxt = camera.params_ctxt().unwrap();
+    assert!(width.is_writable(&mut ctxt).unwrap());
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_software_trigger() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0004");
+    camera.load_context().unwrap();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let trigger_mode = ctxt
+        .node("TriggerMode")
+        .unwrap()
+        .as_enumeration(&ctxt)
+        .unwrap();
+    let trigger_software = ctxt
+        .node("TriggerSoftware")
+        .unwrap()
+        .as_command(&ctxt)
+        .unwrap();
+    trigger_mode.set_entry_by_symbolic(&mut ctxt, "On").unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    task::block_on(future::timeout(
+        Duration::from_millis(200),
+        payload_rx.recv(),
+    ))
+    .unwrap_err();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    trigger_software.execute(&mut ctxt).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.id(), 0);
+    payload_rx.send_back(payload);
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_frame_source() {
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().frame_source(FrameCounter::new(Checkerboard::new(4))),
+        "EMU0005",
+    );
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    for _ in 0..3 {
+        let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+            .unwrap()
+            .unwrap();
+        let image_info = payload.image_info().unwrap();
+        let image = payload.image().unwrap();
+
+        // The first 8 bytes are stamped with the block id.
+        assert_eq!(image[..8], payload.id().to_le_bytes());
+        // The rest of the first row is the checkerboard pattern.
+        assert_eq!(image[8..12], [0xff; 4]);
+        assert_eq!(image[12..16], [0x00; 4]);
+        // The fifth row starts with the inverted pattern.
+        let row = 4 * image_info.width;
+        assert_eq!(image[row..row + 4], [0x00; 4]);
+        payload_rx.send_back(payload);
+    }
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_raw_files() {
+    let dir = std::env::temp_dir().join(format!("cameleon-emulator-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("0.raw"), vec![1; 640 * 480]).unwrap();
+    std::fs::write(dir.join("1.raw"), vec![2; 640 * 480]).unwrap();
+
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().frame_source(RawFiles::from_dir(&dir).unwrap()),
+        "EMU0006",
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    for expected in &[1, 2, 1] {
+        let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+            .unwrap()
+            .unwrap();
+        assert!(payload.image().unwrap().iter().all(|v| v == expected));
+        payload_rx.send_back(payload);
+    }
+
+    camera.close().unwrap();
+}
+
+/// Address of `SerialNumber` register in `ABRM`.
+const SERIAL_NUMBER_ADDRESS: u64 = 0x0144;
+
+#[test]
+fn test_emulated_camera_control_fault() {
+    let serial_number = SERIAL_NUMBER_ADDRESS..SERIAL_NUMBER_ADDRESS + 1;
+    let pending = ControlFaultKind::Pending {
+        timeout: Duration::from_millis(100),
+        delay: Duration::from_millis(50),
+    };
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new()
+            .control_fault(
+                ControlFault::new(pending)
+                    .address_range(serial_number.clone())
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(ControlFaultKind::Status(0x8006))
+                    .address_range(serial_number.clone())
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(ControlFaultKind::RequestIdMismatch)
+                    .address_range(serial_number.clone())
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(ControlFaultKind::Drop)
+                    .address_range(serial_number)
+                    .times(1),
+            ),
+        "EMU0007",
+    );
+    // Return failures as they are instead of retrying.
+    camera.ctrl.set_read_retry_count(0);
+    let abrm = camera.ctrl.abrm().unwrap();
+
+    // Pending ack is followed by the actual ack.
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0007");
+    // Error status.
+    assert!(matches!(
+        abrm.serial_number(&mut camera.ctrl),
+        Err(ControlError::AccessDenied)
+    ));
+    // Request id mismatch.
+    assert!(abrm.serial_number(&mut camera.ctrl).is_err());
+    // Dropped ack.
+    assert!(abrm.serial_number(&mut camera.ctrl).is_err());
+    // All faults are consumed.
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0007");
+
+    // Accesses near the end of the address space are matched against faults without overflow.
+    let mut buf = [0; 4];
+    assert!(camera.ctrl.read(u64::MAX - 1, &mut buf).is_err());
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0007");
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_stream_fault() {
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new()
+            .stream_fault(StreamFault::new(StreamFaultKind::Truncate(1024)).block_ids(1..2))
+            .stream_fault(StreamFault::new(StreamFaultKind::Halt).block_ids(3..4)),
+        "EMU0008",
+    );
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let recv = || task::block_on(future::timeout(TIMEOUT, payload_rx.recv())).unwrap();
+
+    assert_eq!(recv().unwrap().id(), 0);
+    // Truncated payload.
+    assert!(recv().is_err());
+    assert_eq!(recv().unwrap().id(), 2);
+    // Halted stream endpoint.
+    assert!(recv().is_err());
+
+    // The device rejects commands until the halt of the stream endpoint is cleared.
+    assert!(camera.close().is_err());
+}
+
+#[test]
+fn test_emulated_camera_reconnect() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0009");
+    let find_camera = || {
+        u3v::enumerate_cameras()
+            .unwrap()
+            .into_iter()
+            .find(|cam| cam.info().serial_number == "EMU0009")
+    };
+    camera.load_context().unwrap();
+    let abrm = camera.ctrl.abrm().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    payload_rx.send_back(payload);
+
+    u3v::disconnect_device("EMU0009").unwrap();
+    assert!(find_camera().is_none());
+    assert!(matches!(
+        abrm.serial_number(&mut camera.ctrl),
+        Err(ControlError::Disconnected)
+    ));
+    // Payloads which have been already received may remain in the channel.
+    loop {
+        match task::block_on(future::timeout(TIMEOUT, payload_rx.recv())).unwrap() {
+            Ok(payload) => payload_rx.send_back(payload),
+            Err(err) => {
+                assert!(matches!(err, StreamError::Disconnected));
+                break;
+            }
+        }
+    }
+    assert!(camera.close().is_err());
+    assert!(u3v::disconnect_device("EMU0009").is_err());
+
+    u3v::reconnect_device("EMU0009").unwrap();
+    assert!(u3v::reconnect_device("EMU0009").is_err());
+    let mut camera = find_camera().unwrap();
+    camera.open().unwrap();
+    let abrm = camera.ctrl.abrm().unwrap();
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0009");
+    camera.close().unwrap();
+}
+
+/// Send a command via the raw control channel and returns the serialized ack.
+fn send_command<T: CommandScd>(
+    channel: &cameleon_device::u3v::ControlChannel,
+    command: &cmd::CommandPacket<T>,
+) -> Vec<u8> {
+    let mut buf = vec![];
+    command.serialize(&mut buf).unwrap();
+    channel.send(&buf, TIMEOUT).unwrap();
+
+    let mut ack = vec![0; command.maximum_ack_len()];
+    let len = channel.recv(&mut ack, TIMEOUT).unwrap();
+    ack.truncate(len);
+    ack
+}
+
+#[test]
+fn test_emulated_camera_stacked_commands() {
+    EmulatorBuilder::new()
+        .serial_number("EMU0010")
+        .unwrap()
+        .build();
+
+    let device = cameleon_device::u3v::enumerate_devices()
+        .unwrap()
+        .into_iter()
+        .find(|dev| dev.device_info.serial_number == "EMU0010")
+        .unwrap();
+    let mut channel = device.control_channel().unwrap();
+    channel.open().unwrap();
+
+    // Write multiple registers at once.
+    let name = b"stacked";
+    let entries = vec![
+        cmd::WriteMem::new(abrm::USER_DEFINED_NAME.0, name).unwrap(),
+        cmd::WriteMem::new(abrm::USER_DEFINED_NAME.0 + 32, name).unwrap(),
+    ];
+    let command = cmd::WriteMemStacked::new(entries).unwrap().finalize(0);
+    let buf = send_command(&channel, &command);
+    let ack = ack::AckPacket::parse(&buf).unwrap();
+    assert!(ack.status().is_success());
+    assert_eq!(
+        ack.scd_as::<ack::WriteMemStacked>().unwrap().lengths,
+        vec![7, 7]
+    );
+
+    // Read multiple registers at once.
+    let entries = vec![
+        cmd::ReadMem::new(abrm::SERIAL_NUMBER.0, 7),
+        cmd::ReadMem::new(abrm::USER_DEFINED_NAME.0 + 32, 7),
+    ];
+    let command = cmd::ReadMemStacked::new(entries).unwrap().finalize(1);
+    let buf = send_command(&channel, &command);
+    let ack = ack::AckPacket::parse(&buf).unwrap();
+    assert!(ack.status().is_success());
+    assert_eq!(
+        ack.scd_as::<ack::ReadMemStacked>().unwrap().data,
+        b"EMU0010stacked"
+    );
+
+    // Each entry is checked for access rights.
+    let entries = vec![
+        cmd::WriteMem::new(abrm::USER_DEFINED_NAME.0, name).unwrap(),
+        cmd::WriteMem::new(abrm::SERIAL_NUMBER.0, name).unwrap(),
+    ];
+    let command = cmd::WriteMemStacked::new(entries).unwrap().finalize(2);
+    let buf = send_command(&channel, &command);
+    let ack = ack::AckPacket::parse(&buf).unwrap();
+    assert_eq!(
+        ack.status().kind(),
+        ack::StatusKind::GenCp(ack::GenCpStatus::WriteProtect)
+    );
+
+    let entries = vec![
+        cmd::ReadMem::new(abrm::SERIAL_NUMBER.0, 7),
+        cmd::ReadMem::new(u64::from(u32::MAX), 4),
+    ];
+    let command = cmd::ReadMemStacked::new(entries).unwrap().finalize(3);
+    let buf = send_command(&channel, &command);
+    let ack = ack::AckPacket::parse(&buf).unwrap();
+    assert_eq!(
+        ack.status().kind(),
+        ack::StatusKind::GenCp(ack::GenCpStatus::InvalidAddress)
+    );
+
+    channel.close().unwrap();
+}
+
+const VENDOR_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<RegisterDescription
+  ModelName="VendorModel"
+  VendorName="VendorName"
+  StandardNameSpace="None"
+  SchemaMajorVersion="1"
+  SchemaMinorVersion="1"
+  SchemaSubMinorVersion="0"
+  MajorVersion="2"
+  MinorVersion="3"
+  SubMinorVersion="4"
+  ProductGuid="01234567-0123-0123-0123-0123456789ab"
+  VersionGuid="76543210-3210-3210-3210-ba9876543210"
+  xmlns="http://www.genicam.org/GenApi/Version_1_1"
+  xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+  xsi:schemaLocation="http://www.genicam.org/GenApi/Version_1_1 GenApiSchema_Version_1_1.xsd">
+
+    <Category Name="Root" NameSpace="Standard">
+        <pFeature>VendorValue</pFeature>
+    </Category>
+
+    <IntReg Name="VendorValue">
+        <Address>0x100000</Address>
+        <Length>4</Length>
+        <AccessMode>RW</AccessMode>
+        <pPort>Device</pPort>
+        <Sign>Unsigned</Sign>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <Port Name="Device" NameSpace="Standard">
+    </Port>
+</RegisterDescription>
+"#;
+
+#[test]
+fn test_emulated_camera_vendor_xml() {
+    for (serial_number, zipped) in [("EMU0011", false), ("EMU0012", true)] {
+        let image = u3v::RegisterImage::new().segment(0x0010_0000, 42_u32.to_le_bytes());
+        let mut builder = EmulatorBuilder::from_genapi_xml(VENDOR_XML, image).unwrap();
+        if zipped {
+            builder = builder.zip_genapi_xml();
+        }
+
+        let mut camera = open_emulated_camera(builder, serial_number);
+        assert_eq!(camera.info().model_name, "VendorModel");
+        // The hash of the XML file is verified in loading.
+        let xml = camera.load_context().unwrap();
+        assert_eq!(xml, VENDOR_XML);
+
+        let mut ctxt = camera.params_ctxt().unwrap();
+        let value = ctxt.node("VendorValue").unwrap().as_integer(&ctxt).unwrap();
+        assert_eq!(value.value(&mut ctxt).unwrap(), 42);
+        value.set_value(&mut ctxt, 7).unwrap();
+
+        let mut buf = [0; 4];
+        camera.ctrl.read(0x0010_0000, &mut buf).unwrap();
+        assert_eq!(u32::from_le_bytes(buf), 7);
+
+        camera.close().unwrap();
+    }
+}
+
+#[test]
+fn test_emulated_camera_error_status() {
+    let vendor_value = 0x0010_0000..0x0010_0004;
+    let status_fault = |code| {
+        ControlFault::new(ControlFaultKind::Status(code))
+            .address_range(vendor_value.clone())
+            .times(1)
+    };
+    let image = u3v::RegisterImage::new().segment(0x0010_0000, 42_u32.to_le_bytes());
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::from_genapi_xml(VENDOR_XML, image)
+            .unwrap()
+            .control_fault(status_fault(0x8006))
+            .control_fault(status_fault(0x8003))
+            .control_fault(status_fault(0xC001)),
+        "EMU0013",
+    );
+    camera.load_context().unwrap();
+
+    // The status is kept in the error of `GenApi` node operations.
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let value = ctxt.node("VendorValue").unwrap().as_integer(&ctxt).unwrap();
+    match value.value(&mut ctxt) {
+        Err(GenApiError::Device(err)) => assert!(matches!(
+            err.downcast_ref::<ControlError>(),
+            Some(ControlError::AccessDenied)
+        )),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let mut buf = [0; 4];
+    assert!(matches!(
+        camera.ctrl.read(0x0010_0000, &mut buf),
+        Err(ControlError::InvalidAddress)
+    ));
+    assert!(matches!(
+        camera.ctrl.read(0x0010_0000, &mut buf),
+        Err(ControlError::DeviceSpecific(0xC001))
+    ));
+
+    // Error statuses don't break the following transactions.
+    camera.ctrl.read(0x0010_0000, &mut buf).unwrap();
+    assert_eq!(u32::from_le_bytes(buf), 42);
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_eirm() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0014");
+
+    let sbrm = camera.ctrl.sbrm().unwrap();
+    assert_eq!(
+        sbrm.u3v_version(&mut camera.ctrl).unwrap(),
+        semver::Version::new(1, 0, 0)
+    );
+    assert!(matches!(
+        sbrm.current_speed(&mut camera.ctrl).unwrap(),
+        cameleon_device::u3v::BusSpeed::SuperSpeed
+    ));
+    let sirm_address = sbrm.sirm_address(&mut camera.ctrl).unwrap().unwrap();
+    let sirm_length = sbrm.sirm_length(&mut camera.ctrl).unwrap().unwrap();
+    let eirm_address = sbrm.eirm_address(&mut camera.ctrl).unwrap().unwrap();
+    let eirm_length = sbrm.eirm_length(&mut camera.ctrl).unwrap().unwrap();
+    assert!(sirm_address + u64::from(sirm_length) <= eirm_address);
+    assert_eq!(eirm_length, 12);
+
+    let mut buf = [0; 4];
+    camera
+        .ctrl
+        .read(
+            eirm_address + eirm::MAXIMUM_EVENT_TRANSFER_LENGTH.0,
+            &mut buf,
+        )
+        .unwrap();
+    assert!(u32::from_le_bytes(buf) > 0);
+    let eirm = camera.ctrl.eirm().unwrap();
+    assert_eq!(
+        eirm.maximum_event_transfer_length(&mut camera.ctrl)
+            .unwrap(),
+        u32::from_le_bytes(buf)
+    );
+    // Too short length to hold an event packet is rejected.
+    assert!(eirm
+        .set_maximum_event_transfer_length(&mut camera.ctrl, 8)
+        .is_err());
+    eirm.set_maximum_event_transfer_length(&mut camera.ctrl, 512)
+        .unwrap();
+    assert_eq!(
+        eirm.maximum_event_transfer_length(&mut camera.ctrl)
+            .unwrap(),
+        512
+    );
+
+    // Writing 1 to `EventTestControl` sends a test event when the event interface is enabled.
+    // Release the event interface claimed by the camera to read raw event packets.
+    camera.evt.as_mut().unwrap().close().unwrap();
+    let device = cameleon_device::u3v::enumerate_devices()
+        .unwrap()
+        .into_iter()
+        .find(|dev| dev.device_info.serial_number == "EMU0014")
+        .unwrap();
+    let mut event_channel = device.event_channel().unwrap().unwrap();
+    event_channel.open().unwrap();
+
+    assert!(!eirm.is_event_enable(&mut camera.ctrl).unwrap());
+    eirm.enable_event(&mut camera.ctrl).unwrap();
+    assert!(eirm.is_event_enable(&mut camera.ctrl).unwrap());
+    // `MaximumEventTransferLength` can't be changed while the event interface is enabled.
+    assert!(matches!(
+        eirm.set_maximum_event_transfer_length(&mut camera.ctrl, 256),
+        Err(ControlError::WriteProtect)
+    ));
+    assert_eq!(
+        eirm.maximum_event_transfer_length(&mut camera.ctrl)
+            .unwrap(),
+        512
+    );
+    eirm.send_test_event(&mut camera.ctrl).unwrap();
+
+    let mut buf = vec![0; 1024];
+    let len = event_channel.recv(&mut buf, TIMEOUT).unwrap();
+    let packet = event::EventPacket::parse(&buf[..len]).unwrap();
+    assert_eq!(packet.scd[0].event_id, 0x4FFF);
+
+    // `EventTestControl` is self-clearing.
+    let mut buf = [0; 4];
+    camera
+        .ctrl
+        .read(eirm_address + eirm::EVENT_TEST_CONTROL.0, &mut buf)
+        .unwrap();
+    assert_eq!(u32::from_le_bytes(buf), 0);
+
+    eirm.disable_event(&mut camera.ctrl).unwrap();
+    let mut buf = [0; 4];
+    camera
+        .ctrl
+        .read(eirm_address + eirm::EI_CONTROL.0, &mut buf)
+        .unwrap();
+    assert_eq!(u32::from_le_bytes(buf), 0);
+    eirm.set_maximum_event_transfer_length(&mut camera.ctrl, 1024)
+        .unwrap();
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_virtual_clock() {
+    let clock = VirtualClock::new();
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().virtual_clock(clock.clone()),
+        "EMU0015",
+    );
+    camera.load_context().unwrap();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let frame_rate = ctxt
+        .node("AcquisitionFrameRate")
+        .unwrap()
+        .as_float(&ctxt)
+        .unwrap();
+    frame_rate.set_value(&mut ctxt, 10.0).unwrap();
+
+    // The first frame is captured as soon as the acquisition starts.
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.timestamp(), Duration::ZERO);
+    payload_rx.send_back(payload);
+
+    // No frame is captured until the clock advances by a frame interval.
+    clock.advance(Duration::from_millis(50));
+    let wait = Duration::from_millis(200);
+    assert!(task::block_on(future::timeout(wait, payload_rx.recv())).is_err());
+
+    for i in 1..=3 {
+        clock.advance(Duration::from_millis(50));
+        let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(payload.id(), i);
+        assert_eq!(payload.timestamp(), Duration::from_millis(100 * i));
+        payload_rx.send_back(payload);
+        clock.advance(Duration::from_millis(50));
+    }
+
+    camera.stop_streaming().unwrap();
+
+    // `Timestamp` register is latched with the device time.
+    let abrm = camera.ctrl.abrm().unwrap();
+    abrm.set_timestamp_latch_bit(&mut camera.ctrl).unwrap();
+    assert_eq!(abrm.timestamp(&mut camera.ctrl).unwrap(), 350_000_000);
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_chunk_data() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0016");
+    camera.load_context().unwrap();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let chunk_mode_active = ctxt
+        .node("ChunkModeActive")
+        .unwrap()
+        .as_boolean(&ctxt)
+        .unwrap();
+    let chunk_selector = ctxt
+        .node("ChunkSelector")
+        .unwrap()
+        .as_enumeration(&ctxt)
+        .unwrap();
+    let chunk_enable = ctxt.node("ChunkEnable").unwrap().as_boolean(&ctxt).unwrap();
+    let payload_size = ctxt.node("PayloadSize").unwrap().as_integer(&ctxt).unwrap();
+    let exposure_time = ctxt.node("ExposureTime").unwrap().as_float(&ctxt).unwrap();
+
+    chunk_mode_active.set_value(&mut ctxt, true).unwrap();
+    for chunk in &["Timestamp", "FrameID", "ExposureTime", "Gain"] {
+        chunk_selector
+            .set_entry_by_symbolic(&mut ctxt, chunk)
+            .unwrap();
+        chunk_enable.set_value(&mut ctxt, true).unwrap();
+    }
+    chunk_selector
+        .set_entry_by_symbolic(&mut ctxt, "Image")
+        .unwrap();
+    assert!(chunk_enable.value(&mut ctxt).unwrap());
+    exposure_time.set_value(&mut ctxt, 2000.0).unwrap();
+    // Image chunk + 4 chunks with 8 bytes data.
+    let image_size = 640 * 480;
+    assert_eq!(
+        payload_size.value(&mut ctxt).unwrap(),
+        image_size + 8 + 4 * 16
+    );
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.payload_type(), PayloadType::ImageExtendedChunk);
+    assert_eq!(payload.image().unwrap().len(), image_size as usize);
+
+    // Parse chunks from the end of the payload.
+    let mut rest = payload.payload();
+    let mut chunks = vec![];
+    while !rest.is_empty() {
+        let (data, trailer) = rest.split_at(rest.len() - 8);
+        let chunk_id = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let chunk_len = u32::from_be_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        let (rest_data, chunk_data) = data.split_at(data.len() - chunk_len as usize);
+        chunks.push((chunk_id, chunk_data));
+        rest = rest_data;
+    }
+    let chunk_ids: Vec<_> = chunks.iter().map(|(id, _)| *id).collect();
+    assert_eq!(
+        chunk_ids,
+        &[
+            0x4000_0005,
+            0x4000_0004,
+            0x4000_0003,
+            0x4000_0002,
+            0x4000_0001
+        ]
+    );
+    let exposure_time = f64::from_le_bytes(chunks[1].1.try_into().unwrap());
+    assert!((exposure_time - 2000.0).abs() < f64::EPSILON);
+    let frame_id = u64::from_le_bytes(chunks[2].1.try_into().unwrap());
+    assert_eq!(frame_id, payload.id());
+    let timestamp = u64::from_le_bytes(chunks[3].1.try_into().unwrap());
+    assert_eq!(timestamp as u128, payload.timestamp().as_nanos());
+    payload_rx.send_back(payload);
+
+    camera.stop_streaming().unwrap();
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_bandwidth_stall() {
+    // A 640x480 Mono8 frame takes about 100ms to send, which overflows 30fps.
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().bandwidth(Bandwidth::new(3_000_000)),
+        "EMU0017",
+    );
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let mut ids = vec![];
+    let mut lost = vec![];
+    while ids.len() < 3 {
+        match task::block_on(future::timeout(TIMEOUT, payload_rx.recv())).unwrap() {
+            Ok(payload) => {
+                ids.push(payload.id());
+                payload_rx.send_back(payload);
+            }
+            Err(StreamError::FramesLost { from, to }) => lost.push((from, to)),
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+    camera.stop_streaming().unwrap();
+
+    // Frames captured while the previous frame is being sent are dropped.
+    assert_eq!(ids[0], 0);
+    assert!(ids.windows(2).all(|ids| ids[1] > ids[0] + 1));
+    // Every gap of block ids is reported.
+    let gaps: Vec<_> = ids.windows(2).map(|ids| (ids[0] + 1, ids[1] - 1)).collect();
+    assert_eq!(lost, gaps);
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_bandwidth_discard() {
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().bandwidth(Bandwidth::new(3_000_000).overflow(Overflow::Discard)),
+        "EMU0018",
+    );
+    camera.load_context().unwrap();
+
+    // The rest of the frame is discarded when the next frame is captured.
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let recv = || task::block_on(future::timeout(TIMEOUT, payload_rx.recv())).unwrap();
+    let mut discarded = 0;
+    while discarded < 2 {
+        match recv() {
+            Err(StreamError::InvalidPayload(msg)) => {
+                assert!(msg.contains("DataDiscarded"));
+                discarded += 1;
+            }
+            // A frame is dropped entirely if even its leader isn't sent.
+            Err(StreamError::FramesLost { .. }) => {}
+            res => panic!("unexpected result: {:?}", res.map(|payload| payload.id())),
+        }
+    }
+    camera.stop_streaming().unwrap();
+
+    // Frames are sent intact once the frame rate fits in the bandwidth.
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let frame_rate = ctxt
+        .node("AcquisitionFrameRate")
+        .unwrap()
+        .as_float(&ctxt)
+        .unwrap();
+    frame_rate.set_value(&mut ctxt, 5.0).unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.image().unwrap().len(), 640 * 480);
+    payload_rx.send_back(payload);
+    camera.stop_streaming().unwrap();
+
+    // No frame is due without a trigger, so the frame in flight isn't discarded.
+    let mut ctxt = camera.params_ctxt().unwrap();
+    frame_rate.set_value(&mut ctxt, 30.0).unwrap();
+    let trigger_mode = ctxt
+        .node("TriggerMode")
+        .unwrap()
+        .as_enumeration(&ctxt)
+        .unwrap();
+    trigger_mode.set_entry_by_symbolic(&mut ctxt, "On").unwrap();
+    let trigger_software = ctxt
+        .node("TriggerSoftware")
+        .unwrap()
+        .as_command(&ctxt)
+        .unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let mut ctxt = camera.params_ctxt().unwrap();
+    trigger_software.execute(&mut ctxt).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload.id(), 0);
+    assert_eq!(payload.image().unwrap().len(), 640 * 480);
+    camera.stop_streaming().unwrap();
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_memory_snapshot() {
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0019");
+    camera.load_context().unwrap();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    for (name, value) in &[("Width", 320), ("Height", 240)] {
+        let node = ctxt.node(name).unwrap().as_integer(&ctxt).unwrap();
+        node.set_value(&mut ctxt, *value).unwrap();
+    }
+    camera.close().unwrap();
+
+    let path = std::env::temp_dir().join("cameleon_test_memory_snapshot.bin");
+    u3v::snapshot_device("EMU0019")
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let snapshot = u3v::MemorySnapshot::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(u3v::snapshot_device("EMU9999").is_err());
+
+    // The restored device starts with the configured ROI.
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().memory_snapshot(snapshot).unwrap(),
+        "EMU0020",
+    );
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+        .unwrap()
+        .unwrap();
+    let image_info = payload.image_info().unwrap();
+    assert_eq!((image_info.width, image_info.height), (320, 240));
+    payload_rx.send_back(payload);
+    camera.stop_streaming().unwrap();
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_stacked_access() {
+    let user_defined_name = abrm::USER_DEFINED_NAME.0..abrm::USER_DEFINED_NAME.0 + 64;
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().control_fault(
+            ControlFault::new(ControlFaultKind::Delay(Duration::from_millis(100)))
+                .address_range(user_defined_name),
+        ),
+        "EMU0021",
+    );
+
+    let name = abrm::USER_DEFINED_NAME.0;
+    camera
+        .ctrl
+        .write_stacked(&[(name, b"stacked"), (name + 32, b"write")])
+        .unwrap();
+
+    // Entries are read by a single command, so the delay is applied only once.
+    let mut serial_number = [0; 7];
+    let (mut first, mut second) = ([0; 7], [0; 5]);
+    let now = std::time::Instant::now();
+    camera
+        .ctrl
+        .read_stacked(&mut [
+            (abrm::SERIAL_NUMBER.0, &mut serial_number),
+            (name, &mut first),
+            (name + 32, &mut second),
+        ])
+        .unwrap();
+    assert!(now.elapsed() < Duration::from_millis(200));
+    assert_eq!(&serial_number, b"EMU0021");
+    assert_eq!(&first, b"stacked");
+    assert_eq!(&second, b"write");
+
+    // Entries which don't fit into a single acknowledge are split.
+    let table = camera.ctrl.manifest_table().unwrap();
+    let xml_address = table
+        .entries(&mut camera.ctrl)
+        .unwrap()
+        .next()
+        .unwrap()
+        .file_address(&mut camera.ctrl)
+        .unwrap();
+    let mut stacked = vec![0; 4096];
+    let mut serial_number = [0; 7];
+    camera
+        .ctrl
+        .read_stacked(&mut [
+            (xml_address, &mut stacked),
+            (abrm::SERIAL_NUMBER.0, &mut serial_number),
+        ])
+        .unwrap();
+    let mut sequential = vec![0; 4096];
+    camera.ctrl.read(xml_address, &mut sequential).unwrap();
+    assert_eq!(stacked, sequential);
+    assert_eq!(&serial_number, b"EMU0021");
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_control_recovery() {
+    let serial_number = abrm::SERIAL_NUMBER.0..abrm::SERIAL_NUMBER.0 + 1;
+    let user_defined_name = abrm::USER_DEFINED_NAME.0..abrm::USER_DEFINED_NAME.0 + 1;
+    // Longer than the maximum device response time of the emulator.
+    let late = ControlFaultKind::Delay(Duration::from_millis(700));
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new()
+            .control_fault(
+                ControlFault::new(late)
+                    .address_range(serial_number.clone())
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(ControlFaultKind::RequestIdMismatch)
+                    .address_range(serial_number.clone())
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(ControlFaultKind::Corrupt)
+                    .address_range(serial_number.clone())
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(ControlFaultKind::Drop)
+                    .address_range(serial_number)
+                    .times(1),
+            )
+            .control_fault(
+                ControlFault::new(late)
+                    .address_range(user_defined_name)
+                    .times(1),
+            ),
+        "EMU0022",
+    );
+    let abrm = camera.ctrl.abrm().unwrap();
+
+    // Reads are retried after recovering the channel.
+    camera.ctrl.set_read_retry_count(4);
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0022");
+
+    // Writes are not retried, but the late ack doesn't break the following transactions.
+    assert!(matches!(
+        abrm.set_user_defined_name(&mut camera.ctrl, "recovered"),
+        Err(ControlError::Timeout)
+    ));
+    assert_eq!(
+        abrm.user_defined_name(&mut camera.ctrl).unwrap().unwrap(),
+        "recovered"
+    );
+    assert_eq!(abrm.serial_number(&mut camera.ctrl).unwrap(), "EMU0022");
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_event() {
+    use u3v::event::Event;
+
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0023");
+    let event_rx = camera.start_event(8).unwrap();
+    assert!(matches!(
+        camera.start_event(8),
+        Err(CameleonError::StreamError(StreamError::InStreaming))
+    ));
+
+    u3v::trigger_events(
+        "EMU0023",
+        vec![
+            Event::new(Event::EXPOSURE_END).data(vec![0x01, 0x02]),
+            Event::new(Event::FRAME_START),
+        ],
+    )
+    .unwrap();
+
+    let recv = || {
+        task::block_on(future::timeout(TIMEOUT, event_rx.recv()))
+            .unwrap()
+            .unwrap()
+    };
+    let exposure_end = recv();
+    assert_eq!(exposure_end.id(), Event::EXPOSURE_END);
+    assert_eq!(exposure_end.data(), &[0x01, 0x02]);
+    let frame_start = recv();
+    assert_eq!(frame_start.id(), Event::FRAME_START);
+    assert!(frame_start.data().is_empty());
+    assert!(exposure_end.timestamp() <= frame_start.timestamp());
+
+    // Events are no longer sent once the event notification is stopped.
+    camera.stop_event().unwrap();
+    u3v::trigger_events("EMU0023", vec![Event::new(Event::EXPOSURE_END)]).unwrap();
+    assert!(event_rx.try_recv().is_err());
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_multi_event() {
+    use u3v::event::Event;
+
+    let mut camera = open_emulated_camera(EmulatorBuilder::new(), "EMU0024");
+    let abrm = camera.ctrl.abrm().unwrap();
+    assert!(abrm.device_capability().unwrap().is_multi_event_supported());
+    let mut config = abrm.device_configuration(&mut camera.ctrl).unwrap();
+    config.set_multi_event_enable_bit();
+    abrm.write_device_configuration(&mut camera.ctrl, config)
+        .unwrap();
+    let event_rx = camera.start_event(16).unwrap();
+
+    // Multiple events are packed into a packet.
+    let ids = [
+        Event::FRAME_START,
+        Event::EXPOSURE_START,
+        Event::EXPOSURE_END,
+        Event::FRAME_END,
+    ];
+    let events: Vec<_> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| Event::new(id).data(vec![i as u8; i]))
+        .collect();
+    u3v::trigger_events("EMU0024", events).unwrap();
+
+    for (i, &id) in ids.iter().enumerate() {
+        let event = task::block_on(future::timeout(TIMEOUT, event_rx.recv()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.id(), id);
+        assert_eq!(event.data(), vec![i as u8; i].as_slice());
+    }
+    assert!(event_rx.try_recv().is_err());
+
+    camera.stop_event().unwrap();
+    camera.close().unwrap();
+}
+
+const EVENT_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<RegisterDescription
+  ModelName="EventModel"
+  VendorName="VendorName"
+  StandardNameSpace="None"
+  SchemaMajorVersion="1"
+  SchemaMinorVersion="1"
+  SchemaSubMinorVersion="0"
+  MajorVersion="1"
+  MinorVersion="0"
+  SubMinorVersion="0"
+  ProductGuid="01234567-0123-0123-0123-0123456789ab"
+  VersionGuid="76543210-3210-3210-3210-ba9876543210"
+  xmlns="http://www.genicam.org/GenApi/Version_1_1"
+  xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+  xsi:schemaLocation="http://www.genicam.org/GenApi/Version_1_1 GenApiSchema_Version_1_1.xsd">
+
+    <Category Name="Root" NameSpace="Standard">
+        <pFeature>EventExposureEndTimestamp</pFeature>
+        <pFeature>EventExposureEndFrameID</pFeature>
+    </Category>
+
+    <IntReg Name="EventExposureEndTimestamp">
+        <Address>0x4</Address>
+        <Length>8</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>EventExposureEndPort</pPort>
+        <Sign>Unsigned</Sign>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <IntReg Name="EventExposureEndFrameID">
+        <Address>0xC</Address>
+        <Length>8</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>EventExposureEndPort</pPort>
+        <Sign>Unsigned</Sign>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <Port Name="EventExposureEndPort">
+        <EventID>9003</EventID>
+    </Port>
+
+    <Port Name="Device" NameSpace="Standard">
+    </Port>
+</RegisterDescription>
+"#;
+
+#[test]
+fn test_emulated_camera_event_port() {
+    use u3v::event::Event;
+
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::from_genapi_xml(EVENT_XML, u3v::RegisterImage::new()).unwrap(),
+        "EMU0025",
+    );
+    camera.load_context().unwrap();
+    let event_rx = camera.start_event(8).unwrap();
+
+    let mut ctxt = camera.params_ctxt().unwrap();
+    let timestamp = ctxt
+        .node("EventExposureEndTimestamp")
+        .unwrap()
+        .as_integer(&ctxt)
+        .unwrap();
+    let frame_id = ctxt
+        .node("EventExposureEndFrameID")
+        .unwrap()
+        .as_integer(&ctxt)
+        .unwrap();
+    assert!(matches!(
+        frame_id.value(&mut ctxt),
+        Err(GenApiError::EventDataMissing)
+    ));
+
+    for id in [3_u64, 4] {
+        u3v::trigger_events(
+            "EMU0025",
+            vec![
+                Event::new(Event::FRAME_START),
+                Event::new(Event::EXPOSURE_END).data(id.to_le_bytes()),
+            ],
+        )
+        .unwrap();
+
+        let recv = || {
+            task::block_on(future::timeout(TIMEOUT, event_rx.recv()))
+                .unwrap()
+                .unwrap()
+        };
+        // No port is bound to `FrameStart`.
+        assert!(!ctxt.attach_event(&recv()));
+        let exposure_end = recv();
+        assert!(ctxt.attach_event(&exposure_end));
+
+        // Cached values are invalidated by the new event.
+        assert_eq!(frame_id.value(&mut ctxt).unwrap(), id as i64);
+        assert_eq!(
+            timestamp.value(&mut ctxt).unwrap(),
+            exposure_end.timestamp().as_nanos() as i64
+        );
+    }
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_queued_transfers() {
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().frame_source(FrameCounter::new(Checkerboard::new(4))),
+        "EMU0026",
+    );
+    camera.load_context().unwrap();
+
+    // A 640x480 Mono8 frame is sent in 76 transfers of 4000 bytes followed by a final transfer.
+    camera.ctrl.set_payload_transfer_size(4000);
+    camera.strm.set_queued_transfers(4);
+    assert_eq!(camera.strm.queued_transfers(), 4);
+
+    let payload_rx = camera.start_streaming(3).unwrap();
+    let sirm = camera.ctrl.sirm().unwrap();
+    assert_eq!(sirm.payload_transfer_size(&mut camera.ctrl).unwrap(), 4000);
+    assert_eq!(sirm.payload_transfer_count(&mut camera.ctrl).unwrap(), 76);
+    assert_eq!(
+        sirm.payload_final_transfer1_size(&mut camera.ctrl).unwrap(),
+        3200
+    );
+
+    let mut last_id = None;
+    for _ in 0..5 {
+        let payload = task::block_on(future::timeout(TIMEOUT, payload_rx.recv()))
+            .unwrap()
+            .unwrap();
+        let image = payload.image().unwrap();
+        assert_eq!(image.len(), 640 * 480);
+        assert_eq!(image[..8], payload.id().to_le_bytes());
+        if let Some(last_id) = last_id {
+            assert_eq!(payload.id(), last_id + 1);
+        }
+        last_id = Some(payload.id());
+        payload_rx.send_back(payload);
+    }
+    camera.stop_streaming().unwrap();
+
+    camera.close().unwrap();
+}
+
+/// Step of the virtual clock in [`advance_until`].
+const CLOCK_STEP: Duration = Duration::from_millis(10);
+
+/// Advances `clock` step by step until `f` returns `Some`.
+///
+/// The device handles the steps asynchronously, so several steps may be handled at once.
+fn advance_until<T>(clock: &VirtualClock, mut f: impl FnMut() -> Option<T>) -> T {
+    let start = std::time::Instant::now();
+    loop {
+        if let Some(res) = f() {
+            return res;
+        }
+        assert!(start.elapsed() < TIMEOUT, "timed out advancing the clock");
+        clock.advance(CLOCK_STEP);
+        std::thread::yield_now();
+    }
+}
+
+#[test]
+fn test_emulated_camera_stream_statistics() {
+    // A 640x480 Mono8 frame takes about 100ms to send, which overflows 30fps.
+    let clock = VirtualClock::new();
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new()
+            .bandwidth(Bandwidth::new(3_000_000))
+            .virtual_clock(clock.clone()),
+        "EMU0027",
+    );
+    camera.load_context().unwrap();
+
+    let payload_rx = camera.start_streaming(1).unwrap();
+    assert_eq!(payload_rx.statistics().delivered_frames, 0);
+
+    // Frames are dropped while the channel is full.
+    advance_until(&clock, || {
+        let stats = payload_rx.statistics();
+        (stats.delivered_frames + stats.dropped_frames >= 3).then_some(())
+    });
+    let payload = payload_rx.try_recv().unwrap();
+    assert_eq!(payload.payload().len(), 640 * 480);
+    camera.stop_streaming().unwrap();
+
+    let stats = payload_rx.statistics();
+    assert!(stats.delivered_frames >= 1);
+    assert!(stats.dropped_frames > 0);
+    // Frames captured while the previous frame is being sent never reach the host.
+    assert!(stats.lost_frames > 0);
+    assert_eq!(stats.incomplete_frames, 0);
+    // Rates are measured on the host side.
+    assert!(stats.throughput > 0.0);
+    assert!(stats.current_frame_rate > 0.0);
+    assert!(stats.average_frame_rate > 0.0);
+    assert!(stats.average_latency <= stats.maximum_latency);
+    assert!(stats.maximum_latency > Duration::ZERO);
+
+    camera.close().unwrap();
+}
+
+#[test]
+fn test_emulated_camera_overflow_policy() {
+    let clock = VirtualClock::new();
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new().virtual_clock(clock.clone()),
+        "EMU0028",
+    );
+    camera.load_context().unwrap();
+
+    // The channel always keeps the latest frames.
+    let config = StreamConfig {
+        overflow_policy: OverflowPolicy::DropOldest,
+        ..StreamConfig::new(2)
+    };
+    let payload_rx = camera.start_streaming(config).unwrap();
+    advance_until(&clock, || {
+        let stats = payload_rx.statistics();
+        (stats.delivered_frames + stats.dropped_frames >= 5).then_some(())
+    });
+    // Payloads remain in the channel after streaming is stopped.
+    camera.stop_streaming().unwrap();
+    let stats = payload_rx.statistics();
+    let first = payload_rx.try_recv().unwrap();
+    let second = payload_rx.try_recv().unwrap();
+    assert!(payload_rx.try_recv().is_err());
+    assert_eq!(second.id(), first.id() + 1);
+    assert_eq!(
+        second.id(),
+        stats.delivered_frames + stats.dropped_frames - 1
+    );
+    assert_eq!(stats.delivered_frames, 2);
+
+    // The streaming loop waits for the receiver, so no frame is dropped on the host.
+    let config = StreamConfig {
+        overflow_policy: OverflowPolicy::Block,
+        ..StreamConfig::new(1)
+    };
+    let payload_rx = camera.start_streaming(config).unwrap();
+    // At least two frames are captured while the receiver doesn't receive the first one.
+    for _ in 0..3 {
+        clock.advance(Duration::from_millis(100));
+    }
+    let recv = || task::block_on(future::timeout(TIMEOUT, payload_rx.recv())).unwrap();
+    for id in 0..3 {
+        assert_eq!(recv().unwrap().id(), id);
+    }
+    assert_eq!(payload_rx.statistics().dropped_frames, 0);
+
+    // Stopping streaming doesn't wait for the receiver.
+    for _ in 0..3 {
+        clock.advance(Duration::from_millis(100));
+    }
+    camera.stop_streaming().unwrap();
+
+    camera.close().unwrap();
+
+    // The channel is closed once the receiver is dropped even if the policy evicts payloads.
+    let (sender, receiver) = payload::channel_with_config(StreamConfig {
+        overflow_policy: OverflowPolicy::DropOldest,
+        ..StreamConfig::new(1)
+    });
+    let lost = || Err(StreamError::FramesLost { from: 0, to: 0 });
+    task::block_on(sender.send_with_policy(lost())).unwrap();
+    task::block_on(sender.send_with_policy(lost())).unwrap();
+    drop(receiver);
+    assert!(task::block_on(sender.send_with_policy(lost())).is_err());
+}
+
+#[test]
+fn test_emulated_camera_buffer_pool() {
+    let clock = VirtualClock::new();
+    let mut camera = open_emulated_camera(
+        EmulatorBuilder::new()
+            .frame_source(FrameCounter::new(Checkerboard::new(4)))
+            .virtual_clock(clock.clone()),
+        "EMU0029",
+    );
+    camera.load_context().unwrap();
+
+    let mut pool = BufferPool::new();
+    let buffer_ids: Vec<_> = (0..2)
+        .map(|_| pool.announce(vec![0u8; 640 * 480].into_boxed_slice()))
+        .collect();
+    let payload_rx = camera.start_streaming_with_buffers(3, pool).unwrap();
+    let recv = || advance_until(&clock, || payload_rx.try_recv().ok());
+
+    // The streaming loop fills only announced buffers.
+    let payloads = vec![recv(), recv()];
+    for payload in &payloads {
+        assert!(buffer_ids.contains(&payload.buffer_id().unwrap()));
+        assert_eq!(payload.image().unwrap()[..8], payload.id().to_le_bytes());
+    }
+    assert_ne!(payloads[0].buffer_id(), payloads[1].buffer_id());
+
+    // Frames are dropped while no buffer is queued.
+    advance_until(&clock, || {
+        (payload_rx.statistics().dropped_frames > 0).then_some(())
+    });
+    assert!(payload_rx.try_recv().is_err());
+
+    // Buffers are queued again by sending back payloads.
+    for payload in payloads {
+        payload_rx.send_back(payload);
+    }
+    let payload = recv();
+    assert!(buffer_ids.contains(&payload.buffer_id().unwrap()));
+    assert_eq!(payload.image().unwrap()[..8], payload.id().to_le_bytes());
+
+    // Buffers can't be revoked while streaming.
+    assert!(matches!(
+        payload_rx.revoke_buffers(),
+        Err(StreamError::InStreaming)
+    ));
+
+    // All buffers are given back after streaming is stopped.
+    camera.stop_streaming().unwrap();
+    payload_rx.send_back(payload);
+    let pool = payload_rx.revoke_buffers().unwrap();
+    assert_eq!(pool.len(), buffer_ids.len());
+
+    // The revoked buffers can be announced again.
+    let payload_rx = camera.start_streaming_with_buffers(3, pool).unwrap();
+    let payload = advance_until(&clock, || payload_rx.try_recv().ok());
+    assert!(buffer_ids.contains(&payload.buffer_id().unwrap()));
+    camera.stop_streaming().unwrap();
+
+    // The buffer of the frame waiting for room in the channel is given back on stopping.
+    let mut pool = BufferPool::new();
+    let buffer_ids: Vec<_> = (0..2)
+        .map(|_| pool.announce(vec![0u8; 640 * 480]))
+        .collect();
+    let config = StreamConfig {
+        overflow_policy: OverflowPolicy::Block,
+        ..StreamConfig::new(1)
+    };
+    let payload_rx = camera.start_streaming_with_buffers(config, pool).unwrap();
+    advance_until(&clock, || {
+        (payload_rx.statistics().delivered_frames == 1).then_some(())
+    });
+    // The second frame is received in real time after it's captured, then it waits for the
+    // receiver.
+    for _ in 0..3 {
+        clock.advance(Duration::from_millis(100));
+    }
+    std::thread::sleep(Duration::from_millis(500));
+    camera.stop_streaming().unwrap();
+    let pool = payload_rx.revoke_buffers().unwrap();
+    assert_eq!(pool.len(), buffer_ids.len());
+
+    // Streaming doesn't start with buffers smaller than the maximum payload size.
+    let mut pool = BufferPool::new();
+    pool.announce(vec![0u8; 640 * 480]);
+    pool.announce(vec![0u8; 1024]);
+    assert!(matches!(
+        camera.start_streaming_with_buffers(3, pool),
+        Err(CameleonError::StreamError(StreamError::BufferTooSmall))
+    ));
+
+    // The camera is still able to stream after the failure.
+    let payload_rx = camera.start_streaming(3).unwrap();
+    advance_until(&clock, || payload_rx.try_recv().ok());
+    camera.stop_streaming().unwrap();
+
+    camera.close().unwrap();
+}
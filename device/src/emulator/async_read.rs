@@ -0,0 +1,74 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains async read api of the emulator.
+//! NEVER make this module public because all functions in this module may cause UB if
+//! preconditions are not followed.
+
+use std::{collections::VecDeque, ptr::NonNull, time::Duration};
+
+use crate::u3v::Result;
+
+use super::ReceiveChannel;
+
+#[doc(hidden)]
+/// Represents a pool of asynchronous transfers, that can be polled to completion.
+///
+/// Emulated transfers are actually performed when they are polled.
+pub struct AsyncPool<'a> {
+    channel: &'a ReceiveChannel,
+    pending: VecDeque<PendingTransfer>,
+}
+
+impl<'a> AsyncPool<'a> {
+    #[doc(hidden)]
+    pub fn new(channel: &'a ReceiveChannel) -> Self {
+        Self {
+            channel,
+            pending: VecDeque::new(),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn submit(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.pending.push_back(PendingTransfer {
+            ptr: NonNull::new(buf.as_mut_ptr()).unwrap(),
+            len: buf.len(),
+        });
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    /// # Panics
+    ///
+    /// Panics if there is no pending transfer.
+    pub fn poll(&mut self, timeout: Duration) -> Result<usize> {
+        debug_assert!(!self.pending.is_empty());
+        let transfer = self.pending.front().unwrap();
+        // Safety: Caller must ensure the buffer outlives the transfer, which is the same
+        // precondition as the `libusb` backend.
+        let buf = unsafe { std::slice::from_raw_parts_mut(transfer.ptr.as_ptr(), transfer.len) };
+        let res = self.channel.recv(buf, timeout);
+        if res.is_ok() {
+            self.pending.pop_front();
+        }
+        res
+    }
+
+    #[doc(hidden)]
+    pub fn cancel_all(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Returns the number of async transfers pending.
+    #[doc(hidden)]
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+struct PendingTransfer {
+    ptr: NonNull<u8>,
+    len: usize,
+}
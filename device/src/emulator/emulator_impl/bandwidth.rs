@@ -0,0 +1,130 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains [`Bandwidth`] which limits the throughput of the stream endpoint of an
+//! emulated device.
+//!
+//! By default, an emulated device sends frames as fast as the host reads them. If a
+//! [`Bandwidth`] is passed to [`EmulatorBuilder::bandwidth`](super::EmulatorBuilder::bandwidth),
+//! the device paces transfers so that the throughput doesn't exceed the budget.
+//!
+//! When a new frame is captured while the previous frame is still being sent, i.e. the budget is
+//! too small for the frame rate or the host doesn't drain transfers fast enough, the device
+//! behaves according to [`Overflow`].
+//!
+//! Pacing runs on the device clock, so it also follows a
+//! [`VirtualClock`](super::clock::VirtualClock).
+//!
+//! # Example
+//! ```rust
+//! use cameleon_device::u3v::{
+//!     bandwidth::{Bandwidth, Overflow},
+//!     BusSpeed, EmulatorBuilder,
+//! };
+//!
+//! // A device connected to a USB2 port, which sends at most 40MB per second.
+//! EmulatorBuilder::new()
+//!     .bus_speed(BusSpeed::HighSpeed)
+//!     .bandwidth(Bandwidth::new(40_000_000).overflow(Overflow::Discard))
+//!     .build();
+//! ```
+
+use std::{convert::TryFrom, time::Duration};
+
+use crate::u3v::BusSpeed;
+
+/// Behavior of the device when a new frame is captured while the previous frame is still being
+/// sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Keep sending the previous frame, and drop the new frame.
+    ///
+    /// Dropped frames still consume block ids, so the host observes gaps of block ids.
+    Stall,
+
+    /// Discard the rest of the previous frame to catch up with the new frame.
+    ///
+    /// Remaining payload transfers are sent as zero length packets, then the trailer reports
+    /// `DataDiscarded` status with the size of the payload sent before discarding. If even the
+    /// leader of the previous frame isn't sent yet, the whole frame is dropped.
+    Discard,
+}
+
+/// Bandwidth budget of the stream endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bandwidth {
+    bytes_per_second: u64,
+    overflow: Overflow,
+}
+
+impl Bandwidth {
+    /// Construct a budget of `bytes_per_second`.
+    ///
+    /// [`Overflow::Stall`] is used by default.
+    ///
+    /// # Panics
+    /// If `bytes_per_second` is zero.
+    #[must_use]
+    pub fn new(bytes_per_second: u64) -> Self {
+        assert!(bytes_per_second > 0, "bandwidth must not be zero");
+        Self {
+            bytes_per_second,
+            overflow: Overflow::Stall,
+        }
+    }
+
+    /// Construct a budget which is equal to the signaling rate of `speed`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{bandwidth::Bandwidth, BusSpeed};
+    ///
+    /// // USB2 runs at 480Mbps.
+    /// assert_eq!(Bandwidth::from_bus_speed(BusSpeed::HighSpeed), Bandwidth::new(60_000_000));
+    /// ```
+    #[must_use]
+    pub fn from_bus_speed(speed: BusSpeed) -> Self {
+        let bits_per_second = match speed {
+            BusSpeed::LowSpeed => 1_500_000,
+            BusSpeed::FullSpeed => 12_000_000,
+            BusSpeed::HighSpeed => 480_000_000,
+            BusSpeed::SuperSpeed => 5_000_000_000,
+            BusSpeed::SuperSpeedPlus => 10_000_000_000,
+        };
+        Self::new(bits_per_second / 8)
+    }
+
+    /// Set the behavior of the device when frames overflow the budget.
+    #[must_use]
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub(super) fn overflow_kind(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Time to send `len` bytes.
+    pub(super) fn transfer_time(&self, len: usize) -> Duration {
+        let nanos = len as u128 * 1_000_000_000 / u128::from(self.bytes_per_second);
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_time() {
+        let bandwidth = Bandwidth::new(1_000_000);
+        assert_eq!(bandwidth.transfer_time(0), Duration::ZERO);
+        assert_eq!(bandwidth.transfer_time(1000), Duration::from_millis(1));
+
+        let bandwidth = Bandwidth::from_bus_speed(BusSpeed::SuperSpeed);
+        assert_eq!(bandwidth.transfer_time(625), Duration::from_micros(1));
+    }
+}
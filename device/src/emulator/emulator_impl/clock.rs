@@ -0,0 +1,184 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains [`VirtualClock`] which drives timing of an emulated device.
+//!
+//! By default, an emulated device runs on the wall clock. If a [`VirtualClock`] is passed to
+//! [`EmulatorBuilder::virtual_clock`](super::EmulatorBuilder::virtual_clock), the device time
+//! advances only when [`VirtualClock::advance`] is called. The clock drives
+//! - device timestamps, i.e. `Timestamp` register, and timestamps of payloads and events.
+//! - frame pacing which is controlled by `AcquisitionFrameRate`.
+//! - delays of control faults such as `ControlFaultKind::Pending`.
+//!
+//! Note that timeouts on the host side still run on the wall clock.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use cameleon_device::u3v::{clock::VirtualClock, EmulatorBuilder};
+//!
+//! let clock = VirtualClock::new();
+//! EmulatorBuilder::new()
+//!     .virtual_clock(clock.clone())
+//!     .build();
+//!
+//! // Advance the device time by a frame interval at 30 fps.
+//! clock.advance(Duration::from_nanos(33_333_333));
+//! assert_eq!(clock.now(), Duration::from_nanos(33_333_333));
+//! ```
+
+use std::{
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_std::task;
+use futures::channel::oneshot;
+
+/// Clock which advances only when [`VirtualClock::advance`] is called.
+///
+/// Clones of the clock share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    inner: Arc<Mutex<VirtualClockInner>>,
+}
+
+#[derive(Debug, Default)]
+struct VirtualClockInner {
+    now: Duration,
+    /// Deadlines of sleeping tasks and senders to wake them.
+    sleepers: Vec<(Duration, oneshot::Sender<()>)>,
+}
+
+impl VirtualClock {
+    /// Construct a clock whose current time is zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Elapsed time since the clock was constructed.
+    #[must_use]
+    pub fn now(&self) -> Duration {
+        self.inner.lock().unwrap().now
+    }
+
+    /// Advance the clock by `duration`, and wake up the device tasks whose deadlines have passed.
+    pub fn advance(&self, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.now += duration;
+
+        let now = inner.now;
+        let (expired, sleepers) = inner
+            .sleepers
+            .drain(..)
+            .partition(|(deadline, _)| *deadline <= now);
+        inner.sleepers = sleepers;
+        drop(inner);
+
+        for (_, waker) in expired {
+            waker.send(()).ok();
+        }
+    }
+
+    async fn sleep_until(&self, deadline: Duration) {
+        let rx = {
+            let mut inner = self.inner.lock().unwrap();
+            if deadline <= inner.now {
+                return;
+            }
+            let (tx, rx) = oneshot::channel();
+            inner.sleepers.push((deadline, tx));
+            rx
+        };
+        rx.await.ok();
+    }
+}
+
+/// Clock shared by modules of an emulated device.
+#[derive(Debug, Clone)]
+pub(super) enum Clock {
+    /// Wall clock which started at the contained instant.
+    Real(Instant),
+    Virtual(VirtualClock),
+}
+
+impl Clock {
+    pub(super) fn real() -> Self {
+        Self::Real(Instant::now())
+    }
+
+    /// Elapsed time since the clock started.
+    pub(super) fn now(&self) -> Duration {
+        match self {
+            Self::Real(start) => start.elapsed(),
+            Self::Virtual(clock) => clock.now(),
+        }
+    }
+
+    /// Sleep until the clock reaches `deadline`.
+    pub(super) async fn sleep_until(&self, deadline: Duration) {
+        match self {
+            Self::Real(_) => task::sleep(deadline.saturating_sub(self.now())).await,
+            Self::Virtual(clock) => clock.sleep_until(deadline).await,
+        }
+    }
+
+    pub(super) async fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration).await;
+    }
+}
+
+/// Device timestamp which starts from zero when the device is powered on.
+#[derive(Debug, Clone)]
+pub(super) struct Timestamp {
+    clock: Clock,
+    origin: Duration,
+}
+
+impl Timestamp {
+    pub(super) fn new(clock: Clock) -> Self {
+        let origin = clock.now();
+        Self { clock, origin }
+    }
+
+    pub(super) fn as_nanos(&self) -> u64 {
+        let elapsed = self.clock.now().saturating_sub(self.origin);
+        u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+    }
+
+    pub(super) fn clock(&self) -> &Clock {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::future::timeout;
+
+    use super::*;
+
+    const TO: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn test_virtual_clock() {
+        let clock = VirtualClock::new();
+        let device_clock = Clock::Virtual(clock.clone());
+        let timestamp = Timestamp::new(device_clock.clone());
+
+        let sleeper = task::spawn(async move {
+            device_clock.sleep_until(Duration::from_millis(10)).await;
+            device_clock.now()
+        });
+
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(timestamp.as_nanos(), 5_000_000);
+
+        clock.advance(Duration::from_millis(5));
+        let woken_at = task::block_on(timeout(TO, sleeper)).unwrap();
+        assert_eq!(woken_at, Duration::from_millis(10));
+    }
+}
@@ -2,9 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    convert::TryFrom,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use async_std::{
@@ -14,13 +18,15 @@ use async_std::{
     task,
 };
 
-use cameleon_impl::memory::{prelude::*, MemoryError};
+use cameleon_impl::memory::{prelude::*, MemoryError, MemoryResult};
 
 use super::{
-    device::Timestamp,
+    clock::Timestamp,
+    fault::{ControlFaultKind, FaultInjector},
     interface::IfaceState,
-    memory::{Memory, SBRM},
+    memory::{Memory, EIRM, SBRM, SIRM},
     memory_event_handler::MemoryEventHandler,
+    register_image::ExternalMemory,
     shared_queue::SharedQueue,
     signal::{ControlSignal, InterfaceSignal},
     IfaceKind,
@@ -31,22 +37,28 @@ use super::control_protocol::{ack, ack::AckSerialize, cmd};
 pub(super) struct ControlModule {
     iface_state: IfaceState,
     memory: Arc<Mutex<Memory>>,
+    external_memory: Option<Arc<Mutex<ExternalMemory>>>,
     timestamp: Timestamp,
     queue: SharedQueue<Vec<u8>>,
+    faults: FaultInjector,
 }
 
 impl ControlModule {
     pub(super) fn new(
         iface_state: IfaceState,
         memory: Arc<Mutex<Memory>>,
+        external_memory: Option<Arc<Mutex<ExternalMemory>>>,
         timestamp: Timestamp,
         queue: SharedQueue<Vec<u8>>,
+        faults: FaultInjector,
     ) -> Self {
         Self {
             iface_state,
             memory,
+            external_memory,
             timestamp,
             queue,
+            faults,
         }
     }
 
@@ -60,9 +72,11 @@ impl ControlModule {
         let mut worker_manager = WorkerManager::new(
             self.iface_state.clone(),
             self.memory.clone(),
+            self.external_memory.clone(),
             self.timestamp.clone(),
             event_handler,
             self.queue.clone(),
+            self.faults.clone(),
             signal_tx,
         )
         .await;
@@ -76,9 +90,18 @@ impl ControlModule {
 
                 ControlSignal::CancelJobs(_completed) => worker_manager.wait_completion().await,
 
-                ControlSignal::ClearSiRegister | ControlSignal::ClearEiRegister => {
-                    // TODO:
-                    todo!()
+                ControlSignal::ClearSiRegister => {
+                    let mut memory = self.memory.lock().await;
+                    if let Err(e) = memory.write::<SIRM::Control>(0) {
+                        log::error!("failed to clear SIRM: {}", e);
+                    }
+                }
+
+                ControlSignal::ClearEiRegister => {
+                    let mut memory = self.memory.lock().await;
+                    if let Err(e) = memory.write::<EIRM::Control>(0) {
+                        log::error!("failed to clear EIRM: {}", e);
+                    }
                 }
 
                 ControlSignal::Shutdown => {
@@ -93,9 +116,11 @@ impl ControlModule {
 struct WorkerManager {
     iface_state: IfaceState,
     memory: Arc<Mutex<Memory>>,
+    external_memory: Option<Arc<Mutex<ExternalMemory>>>,
     timestamp: Timestamp,
 
     queue: SharedQueue<Vec<u8>>,
+    faults: FaultInjector,
     signal_tx: Sender<InterfaceSignal>,
 
     on_processing: Arc<AtomicBool>,
@@ -114,12 +139,15 @@ struct WorkerManager {
 }
 
 impl WorkerManager {
+    #[allow(clippy::too_many_arguments)]
     async fn new(
         iface_state: IfaceState,
         memory: Arc<Mutex<Memory>>,
+        external_memory: Option<Arc<Mutex<ExternalMemory>>>,
         timestamp: Timestamp,
         memory_event_handler: MemoryEventHandler,
         queue: SharedQueue<Vec<u8>>,
+        faults: FaultInjector,
         signal_tx: Sender<InterfaceSignal>,
     ) -> Self {
         let (completed_tx, completed_rx) = channel::bounded(1);
@@ -137,9 +165,11 @@ impl WorkerManager {
         Self {
             iface_state,
             memory,
+            external_memory,
             timestamp,
 
             queue,
+            faults,
             signal_tx,
 
             on_processing,
@@ -158,9 +188,11 @@ impl WorkerManager {
         Worker {
             iface_state: self.iface_state.clone(),
             memory: self.memory.clone(),
+            external_memory: self.external_memory.clone(),
             timestamp: self.timestamp.clone(),
 
             queue: self.queue.clone(),
+            faults: self.faults.clone(),
             signal_tx: self.signal_tx.clone(),
 
             on_processing: self.on_processing.clone(),
@@ -170,6 +202,8 @@ impl WorkerManager {
             maximum_cmd_length: self.maximum_cmd_length,
             maximum_ack_length: self.maximum_ack_length,
 
+            ack_fault: None,
+
             _completed: self.completed_tx.clone(),
         }
     }
@@ -189,9 +223,11 @@ impl WorkerManager {
 pub(super) struct Worker {
     iface_state: IfaceState,
     pub(super) memory: Arc<Mutex<Memory>>,
+    external_memory: Option<Arc<Mutex<ExternalMemory>>>,
     pub(super) timestamp: Timestamp,
 
     queue: SharedQueue<Vec<u8>>,
+    faults: FaultInjector,
     signal_tx: Sender<InterfaceSignal>,
 
     on_processing: Arc<AtomicBool>,
@@ -201,12 +237,14 @@ pub(super) struct Worker {
     maximum_cmd_length: usize,
     maximum_ack_length: usize,
 
+    /// Fault injected into acks sent by the worker.
+    ack_fault: Option<ControlFaultKind>,
+
     _completed: Sender<()>,
 }
 
 impl Worker {
-    // TODO: Emulate pending situation.
-    async fn run(self, command: Vec<u8>) {
+    async fn run(mut self, command: Vec<u8>) {
         let cmd_packet = match self.try_parse_command(&command) {
             Some(packet) => packet,
             None => return,
@@ -239,8 +277,8 @@ impl Worker {
         // If another thread is processing command simultaneously, return busy error ack.
         if self
             .on_processing
-            .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
-            .is_ok()
+            .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
         {
             let ack = ack::ErrorAck::new(ack::GenCpStatus::Busy, ccd.scd_kind())
                 .finalize(ccd.request_id());
@@ -248,6 +286,11 @@ impl Worker {
             return;
         }
 
+        if !self.inject_fault(&cmd_packet).await {
+            self.on_processing.store(false, Ordering::Relaxed);
+            return;
+        }
+
         match ccd.scd_kind() {
             cmd::ScdKind::ReadMem => self.process_read_mem(cmd_packet).await,
             cmd::ScdKind::WriteMem => self.process_write_mem(cmd_packet).await,
@@ -258,6 +301,49 @@ impl Worker {
         self.on_processing.store(false, Ordering::Relaxed);
     }
 
+    /// Inject a fault registered to the device if any.
+    /// Returns `false` if the command must not be processed any further.
+    async fn inject_fault(&mut self, command: &cmd::CommandPacket<'_>) -> bool {
+        let ccd = command.ccd();
+        let fault = match self.faults.control_fault(&accesses(command)).await {
+            Some(fault) => fault,
+            None => return true,
+        };
+        log::info!("inject control fault: {:?}", fault);
+
+        match fault {
+            ControlFaultKind::Pending { timeout, delay } => {
+                let ack = ack::Pending::new(timeout).finalize(ccd.request_id());
+                self.enqueue_or_halt(&ack);
+                self.timestamp.clock().sleep(delay).await;
+                true
+            }
+
+            ControlFaultKind::Delay(delay) => {
+                self.timestamp.clock().sleep(delay).await;
+                true
+            }
+
+            ControlFaultKind::Status(code) => {
+                let ack = ack::ErrorAck::with_code(code, ccd.scd_kind()).finalize(ccd.request_id());
+                self.enqueue_or_halt(&ack);
+                false
+            }
+
+            ControlFaultKind::Halt(endpoint) => {
+                self.try_send_signal(InterfaceSignal::Halt(endpoint.into()));
+                false
+            }
+
+            ControlFaultKind::Drop
+            | ControlFaultKind::Corrupt
+            | ControlFaultKind::RequestIdMismatch => {
+                self.ack_fault = Some(fault);
+                true
+            }
+        }
+    }
+
     fn try_parse_command<'a>(&self, command: &'a [u8]) -> Option<cmd::CommandPacket<'a>> {
         match cmd::CommandPacket::parse(command) {
             Ok(packet) => Some(packet),
@@ -284,13 +370,15 @@ impl Worker {
         let req_id = ccd.request_id();
         let scd_kind = ccd.scd_kind();
 
-        let memory = self.memory.lock().await;
-        let address = scd.address as usize;
-        let read_length = scd.read_length as usize;
+        let address = scd.address;
+        let read_length = u64::from(scd.read_length);
 
-        match memory.read_raw(address..address + read_length) {
+        match self
+            .read_raw(address..address.saturating_add(read_length))
+            .await
+        {
             Ok(data) => {
-                let ack = ack::ReadMem::new(data).finalize(req_id);
+                let ack = ack::ReadMem::new(&data).finalize(req_id);
                 self.enqueue_or_halt(&ack);
             }
 
@@ -306,7 +394,9 @@ impl Worker {
                 self.enqueue_or_halt(&ack);
             }
 
-            Err(MemoryError::AddressNotWritable) | Err(MemoryError::InvalidRegisterData(..)) => {
+            Err(MemoryError::AddressNotWritable)
+            | Err(MemoryError::InvalidRegisterData(..))
+            | Err(MemoryError::InvalidSnapshot(..)) => {
                 unreachable!()
             }
         };
@@ -321,12 +411,8 @@ impl Worker {
         let req_id = ccd.request_id();
         let scd_kind = ccd.scd_kind();
 
-        let mut memory = self.memory.lock().await;
-        match memory.write_raw(scd.address as usize, scd.data) {
+        match self.write_raw(scd.address, scd.data).await {
             Ok(()) => {
-                // Explicitly drop memory to avoid race condition.
-                drop(memory);
-
                 let error_ack = self
                     .memory_event_handler
                     .handle_events(self, scd_kind)
@@ -351,14 +437,16 @@ impl Worker {
                 self.enqueue_or_halt(&ack);
             }
 
-            Err(MemoryError::AddressNotReadable) | Err(MemoryError::InvalidRegisterData(..)) => {
+            Err(MemoryError::AddressNotReadable)
+            | Err(MemoryError::InvalidRegisterData(..))
+            | Err(MemoryError::InvalidSnapshot(..)) => {
                 unreachable!()
             }
         };
     }
 
     async fn process_read_mem_stacked(&self, command: cmd::CommandPacket<'_>) {
-        let _scd: cmd::WriteMemStacked = match self.try_extract_scd(&command) {
+        let scd: cmd::ReadMemStacked = match self.try_extract_scd(&command) {
             Some(scd) => scd,
             None => return,
         };
@@ -366,13 +454,46 @@ impl Worker {
         let req_id = ccd.request_id();
         let scd_kind = ccd.scd_kind();
 
-        // TODO: Should we implement this command?
-        let ack = ack::ErrorAck::new(ack::GenCpStatus::NotImplemented, scd_kind).finalize(req_id);
+        let mut data = vec![];
+        // Read each entry in order. If any entry fails, no data is returned.
+        for entry in &scd.entries {
+            let address = entry.address;
+            let read_length = u64::from(entry.read_length);
+
+            match self
+                .read_raw(address..address.saturating_add(read_length))
+                .await
+            {
+                Ok(entry_data) => data.extend(entry_data),
+
+                Err(MemoryError::InvalidAddress) => {
+                    let ack = ack::ErrorAck::new(ack::GenCpStatus::InvalidAddress, scd_kind)
+                        .finalize(req_id);
+                    self.enqueue_or_halt(&ack);
+                    return;
+                }
+
+                Err(MemoryError::AddressNotReadable) => {
+                    let ack = ack::ErrorAck::new(ack::GenCpStatus::AccessDenied, scd_kind)
+                        .finalize(req_id);
+                    self.enqueue_or_halt(&ack);
+                    return;
+                }
+
+                Err(MemoryError::AddressNotWritable)
+                | Err(MemoryError::InvalidRegisterData(..))
+                | Err(MemoryError::InvalidSnapshot(..)) => {
+                    unreachable!()
+                }
+            }
+        }
+
+        let ack = ack::ReadMemStacked::new(&data).finalize(req_id);
         self.enqueue_or_halt(&ack);
     }
 
     async fn process_write_mem_stacked(&self, command: cmd::CommandPacket<'_>) {
-        let _scd: cmd::WriteMemStacked = match self.try_extract_scd(&command) {
+        let scd: cmd::WriteMemStacked = match self.try_extract_scd(&command) {
             Some(scd) => scd,
             None => return,
         };
@@ -380,11 +501,78 @@ impl Worker {
         let req_id = ccd.request_id();
         let scd_kind = ccd.scd_kind();
 
-        // TODO: Should we implement this command?
-        let ack = ack::ErrorAck::new(ack::GenCpStatus::NotImplemented, scd_kind).finalize(req_id);
+        // Write each entry in order as if each entry is sent by `WriteMem` command.
+        // If any entry fails, the rest of entries are discarded.
+        let mut lengths = Vec::with_capacity(scd.entries.len());
+        for entry in &scd.entries {
+            match self.write_raw(entry.address, entry.data).await {
+                Ok(()) => {
+                    let error_ack = self
+                        .memory_event_handler
+                        .handle_events(self, scd_kind)
+                        .await;
+                    if let Err(error_ack) = error_ack {
+                        self.enqueue_or_halt(&error_ack.finalize(req_id));
+                        return;
+                    }
+                    lengths.push(entry.data.len() as u16);
+                }
+
+                Err(MemoryError::InvalidAddress) => {
+                    let ack = ack::ErrorAck::new(ack::GenCpStatus::InvalidAddress, scd_kind)
+                        .finalize(req_id);
+                    self.enqueue_or_halt(&ack);
+                    return;
+                }
+
+                Err(MemoryError::AddressNotWritable) => {
+                    let ack = ack::ErrorAck::new(ack::GenCpStatus::WriteProtect, scd_kind)
+                        .finalize(req_id);
+                    self.enqueue_or_halt(&ack);
+                    return;
+                }
+
+                Err(MemoryError::AddressNotReadable)
+                | Err(MemoryError::InvalidRegisterData(..))
+                | Err(MemoryError::InvalidSnapshot(..)) => {
+                    unreachable!()
+                }
+            }
+        }
+
+        let ack = ack::WriteMemStacked::new(lengths).finalize(req_id);
         self.enqueue_or_halt(&ack);
     }
 
+    /// Read data from [`ExternalMemory`] if it contains the range, otherwise from [`Memory`].
+    async fn read_raw(&self, range: Range<u64>) -> MemoryResult<Vec<u8>> {
+        if let Some(external_memory) = &self.external_memory {
+            let external_memory = external_memory.lock().await;
+            if external_memory.contains(&range) {
+                return external_memory.read_raw(range).map(<[u8]>::to_vec);
+            }
+        }
+
+        let memory = self.memory.lock().await;
+        let range = usize::try_from(range.start).map_err(|_| MemoryError::InvalidAddress)?
+            ..usize::try_from(range.end).map_err(|_| MemoryError::InvalidAddress)?;
+        memory.read_raw(range).map(<[u8]>::to_vec)
+    }
+
+    /// Write data to [`ExternalMemory`] if it contains the range, otherwise to [`Memory`].
+    async fn write_raw(&self, address: u64, data: &[u8]) -> MemoryResult<()> {
+        if let Some(external_memory) = &self.external_memory {
+            let mut external_memory = external_memory.lock().await;
+            if external_memory.contains(&(address..address.saturating_add(data.len() as u64))) {
+                return external_memory.write_raw(address, data);
+            }
+        }
+
+        let mut memory = self.memory.lock().await;
+        let address = usize::try_from(address).map_err(|_| MemoryError::InvalidAddress)?;
+        memory.write_raw(address, data)
+    }
+
     fn try_extract_scd<'a, T>(&self, command: &cmd::CommandPacket<'a>) -> Option<T>
     where
         T: cmd::ParseScd<'a>,
@@ -412,7 +600,7 @@ impl Worker {
         }
 
         // If ack packet length is larger than maximu_ack_length, return error.
-        let buf = if (self.maximum_ack_length) < buf.len() {
+        let mut buf = if (self.maximum_ack_length) < buf.len() {
             let err_ack = ack::ErrorAck::new(ack::GenCpStatus::InvalidParameter, ack.ccd.scd_kind)
                 .finalize(ack.ccd.request_id);
             let mut buf = vec![];
@@ -422,6 +610,21 @@ impl Worker {
             buf
         };
 
+        // Inject a fault into the ack if any.
+        match self.ack_fault {
+            Some(ControlFaultKind::Drop) => return,
+            Some(ControlFaultKind::Corrupt) => {
+                // Break the prefix magic.
+                buf[0] = !buf[0];
+            }
+            Some(ControlFaultKind::RequestIdMismatch) => {
+                // Request id is placed at 10..12 of the ack packet.
+                let request_id = u16::from_le_bytes([buf[10], buf[11]]).wrapping_add(1);
+                buf[10..12].copy_from_slice(&request_id.to_le_bytes());
+            }
+            _ => {}
+        }
+
         if !self.queue.enqueue(buf) {
             log::warn!("control queue is full, entering a halted state");
             self.try_send_signal(InterfaceSignal::Halt(IfaceKind::Control));
@@ -437,3 +640,32 @@ impl Worker {
         }
     }
 }
+
+/// Returns address ranges which the command accesses.
+fn accesses(command: &cmd::CommandPacket<'_>) -> Vec<Range<u64>> {
+    fn range(address: u64, len: usize) -> Range<u64> {
+        address..address.saturating_add(len as u64)
+    }
+
+    match command.ccd().scd_kind() {
+        cmd::ScdKind::ReadMem => command
+            .scd_as::<cmd::ReadMem>()
+            .map(|scd| vec![range(scd.address, scd.read_length as usize)]),
+        cmd::ScdKind::WriteMem => command
+            .scd_as::<cmd::WriteMem>()
+            .map(|scd| vec![range(scd.address, scd.data.len())]),
+        cmd::ScdKind::ReadMemStacked => command.scd_as::<cmd::ReadMemStacked>().map(|scd| {
+            scd.entries
+                .iter()
+                .map(|entry| range(entry.address, entry.read_length as usize))
+                .collect()
+        }),
+        cmd::ScdKind::WriteMemStacked => command.scd_as::<cmd::WriteMemStacked>().map(|scd| {
+            scd.entries
+                .iter()
+                .map(|entry| range(entry.address, entry.data.len()))
+                .collect()
+        }),
+    }
+    .unwrap_or_default()
+}
@@ -370,7 +370,7 @@ pub(super) mod ack {
 
     impl<'a> AckSerialize for ReadMem<'a> {
         fn serialize(&self, mut buf: impl Write) -> ProtocolResult<()> {
-            buf.write_all(&self.data)?;
+            buf.write_all(self.data)?;
             Ok(())
         }
 
@@ -389,7 +389,7 @@ pub(super) mod ack {
         }
     }
 
-    impl<'a> AckSerialize for WriteMem {
+    impl AckSerialize for WriteMem {
         fn serialize(&self, mut buf: impl Write) -> ProtocolResult<()> {
             buf.write_bytes(0_u16)?;
             buf.write_bytes(self.length)?;
@@ -406,8 +406,8 @@ pub(super) mod ack {
     }
 
     impl Pending {
-        pub(in super::super) fn _new(timeout: time::Duration) -> Self {
-            debug_assert!(timeout.as_millis() <= u128::from(std::u16::MAX));
+        pub(in super::super) fn new(timeout: time::Duration) -> Self {
+            debug_assert!(timeout.as_millis() <= u128::from(u16::MAX));
             Self { timeout }
         }
     }
@@ -429,7 +429,7 @@ pub(super) mod ack {
     }
 
     impl<'a> ReadMemStacked<'a> {
-        pub(in super::super) fn _new(data: &'a [u8]) -> Self {
+        pub(in super::super) fn new(data: &'a [u8]) -> Self {
             debug_assert!(u16::try_from(data.len()).is_ok());
             Self { data }
         }
@@ -437,7 +437,7 @@ pub(super) mod ack {
 
     impl<'a> AckSerialize for ReadMemStacked<'a> {
         fn serialize(&self, mut buf: impl Write) -> ProtocolResult<()> {
-            buf.write_all(&self.data)?;
+            buf.write_all(self.data)?;
             Ok(())
         }
 
@@ -451,7 +451,7 @@ pub(super) mod ack {
     }
 
     impl WriteMemStacked {
-        pub(in super::super) fn _new(lengths: Vec<u16>) -> Self {
+        pub(in super::super) fn new(lengths: Vec<u16>) -> Self {
             debug_assert!(u16::try_from(Self::scd_len(&lengths)).is_ok());
             Self { lengths }
         }
@@ -495,6 +495,19 @@ pub(super) mod ack {
                 scd_kind: scd_kind.into(),
             }
         }
+
+        /// Construct an error ack with a raw status code.
+        pub(in super::super) fn with_code(code: u16, scd_kind: impl Into<ScdKind>) -> Self {
+            // Only the code is serialized, so the kind doesn't matter.
+            let status = Status {
+                code,
+                kind: StatusKind::DeviceSpecific,
+            };
+            Self {
+                status,
+                scd_kind: scd_kind.into(),
+            }
+        }
     }
 
     impl AckSerialize for ErrorAck {
@@ -620,7 +633,7 @@ pub(super) mod ack {
         #[test]
         fn test_pending() {
             let timeout = time::Duration::from_millis(700);
-            let command = Pending::_new(timeout).finalize(1);
+            let command = Pending::new(timeout).finalize(1);
             let mut buf = vec![];
             command.serialize(&mut buf).unwrap();
 
@@ -636,7 +649,7 @@ pub(super) mod ack {
         #[test]
         fn test_read_mem_stacked() {
             let data = &[0, 1, 2, 3, 4, 5, 6, 7, 8];
-            let command = ReadMemStacked::_new(data).finalize(1);
+            let command = ReadMemStacked::new(data).finalize(1);
             let mut buf = vec![];
             command.serialize(&mut buf).unwrap();
 
@@ -652,7 +665,7 @@ pub(super) mod ack {
         #[test]
         fn test_write_mem_stacked() {
             let lengths = vec![8, 16];
-            let command = WriteMemStacked::_new(lengths.clone()).finalize(1);
+            let command = WriteMemStacked::new(lengths.clone()).finalize(1);
             let mut buf = vec![];
             command.serialize(&mut buf).unwrap();
 
@@ -2,49 +2,85 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use std::{convert::TryInto, sync::Arc, time};
+use std::sync::Arc;
 
 use async_std::{
-    channel::{self, Receiver, Sender},
+    channel::{self, Receiver, Sender, TrySendError},
     sync::Mutex,
     task,
 };
+use cameleon_impl::memory::MemorySnapshot;
 use futures::channel::oneshot;
 
-use crate::u3v::DeviceInfo;
+use crate::u3v::{DeviceInfo, LibUsbError, Result};
 
 use super::{
+    bandwidth::Bandwidth,
+    clock::{Clock, Timestamp},
+    event::Event,
     fake_protocol::{FakeAckPacket, FakeReqPacket},
+    fault::FaultInjector,
+    frame_source::FrameSource,
     interface::Interface,
     memory::Memory,
+    register_image::ExternalMemory,
 };
 
 const REQ_PACKET_CHANNEL_CAPACITY: usize = 1;
 const ACK_PACKET_CHANNEL_CAPACITY: usize = 1;
+const EVENT_CHANNEL_CAPACITY: usize = 32;
 
 pub(super) struct Device {
-    timestamp: Timestamp,
-    memory: Arc<Mutex<Memory>>,
+    /// Memory of the device at power on. Each run starts with a copy of it.
+    initial_memory: Memory,
+    initial_external_memory: Option<ExternalMemory>,
+    /// Memory of the running device.
+    memory: Option<Arc<Mutex<Memory>>>,
+    frame_source: Arc<Mutex<Box<dyn FrameSource>>>,
+    faults: FaultInjector,
+    clock: Clock,
+    bandwidth: Option<Bandwidth>,
+    event_tx: Option<Sender<Vec<Event>>>,
     shutdown_tx: Option<oneshot::Sender<()>>,
     completion_rx: Option<oneshot::Receiver<()>>,
     device_info: DeviceInfo,
 }
 
 impl Device {
-    pub(super) fn new(memory: Memory, device_info: DeviceInfo) -> Self {
+    pub(super) fn new(
+        memory: Memory,
+        external_memory: Option<ExternalMemory>,
+        device_info: DeviceInfo,
+        frame_source: Box<dyn FrameSource>,
+        faults: FaultInjector,
+        clock: Clock,
+        bandwidth: Option<Bandwidth>,
+    ) -> Self {
         Self {
-            timestamp: Timestamp::new(),
-            memory: Arc::new(Mutex::new(memory)),
+            initial_memory: memory,
+            initial_external_memory: external_memory,
+            memory: None,
+            frame_source: Arc::new(Mutex::new(frame_source)),
+            faults,
+            clock,
+            bandwidth,
+            event_tx: None,
             shutdown_tx: None,
             completion_rx: None,
             device_info,
         }
     }
 
+    /// Power on the device and run it.
+    ///
+    /// Registers and the timestamp are reset to their initial state each time the device runs,
+    /// while the frame source, faults and the clock keep their state.
     pub(super) fn run(&mut self) -> (Sender<FakeReqPacket>, Receiver<FakeAckPacket>) {
         // Create channels for communication between device and host.
         let (req_tx, req_rx) = channel::bounded(REQ_PACKET_CHANNEL_CAPACITY);
         let (ack_tx, ack_rx) = channel::bounded(ACK_PACKET_CHANNEL_CAPACITY);
+        let (event_tx, event_rx) = channel::bounded(EVENT_CHANNEL_CAPACITY);
+        self.event_tx = Some(event_tx);
 
         // Create channel for communication between device and its internal interface.
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
@@ -52,19 +88,45 @@ impl Device {
         self.shutdown_tx = Some(shutdown_tx);
         self.completion_rx = Some(completion_rx);
 
+        let memory = Arc::new(Mutex::new(self.initial_memory.clone_without_observers()));
+        self.memory = Some(memory.clone());
+
         task::spawn(
-            Interface::new(self.memory.clone(), self.timestamp.clone()).run(
-                ack_tx,
-                req_rx,
-                shutdown_rx,
-                completion_tx,
-            ),
+            Interface::new(
+                memory,
+                self.initial_external_memory
+                    .clone()
+                    .map(|memory| Arc::new(Mutex::new(memory))),
+                Timestamp::new(self.clock.clone()),
+                self.frame_source.clone(),
+                self.faults.clone(),
+                self.bandwidth,
+            )
+            .run(ack_tx, req_rx, event_rx, shutdown_rx, completion_tx),
         );
 
         (req_tx, ack_rx)
     }
 
+    /// Trigger events on the running device.
+    pub(super) fn trigger_events(&self, events: Vec<Event>) -> Result<()> {
+        let event_tx = self.event_tx.as_ref().ok_or(LibUsbError::NoDevice)?;
+        event_tx.try_send(events).map_err(|err| match err {
+            TrySendError::Full(..) => LibUsbError::Busy,
+            TrySendError::Closed(..) => LibUsbError::NoDevice,
+        })?;
+        Ok(())
+    }
+
+    /// Take a snapshot of the memory of the running device.
+    pub(super) fn snapshot(&self) -> Result<MemorySnapshot> {
+        let memory = self.memory.as_ref().ok_or(LibUsbError::NoDevice)?;
+        Ok(task::block_on(memory.lock()).snapshot())
+    }
+
     pub(super) fn shutdown(&mut self) {
+        self.event_tx = None;
+        self.memory = None;
         if let Some(shutdown_tx) = self.shutdown_tx.take() {
             // Signal shutdown to interface.
             drop(shutdown_tx);
@@ -84,23 +146,3 @@ impl Drop for Device {
         self.shutdown();
     }
 }
-
-#[derive(Debug, Clone)]
-pub(super) struct Timestamp(Arc<Mutex<time::Instant>>);
-
-impl Timestamp {
-    pub(super) fn new() -> Self {
-        Self(Arc::new(Mutex::new(time::Instant::now())))
-    }
-
-    pub(super) async fn as_nanos(&self) -> u64 {
-        let mut inner = self.0.lock().await;
-        let ns: u64 = if let Ok(time) = inner.elapsed().as_nanos().try_into() {
-            time
-        } else {
-            *inner = time::Instant::now();
-            inner.elapsed().as_nanos() as u64
-        };
-        ns
-    }
-}
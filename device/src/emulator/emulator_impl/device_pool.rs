@@ -9,12 +9,14 @@ use async_std::{
     sync::Mutex,
     task,
 };
+use cameleon_impl::memory::MemorySnapshot;
 use lazy_static::lazy_static;
 
 use crate::u3v::{DeviceInfo, LibUsbError, Result};
 
 use super::{
     device::Device,
+    event::Event,
     fake_protocol::{FakeAckPacket, FakeReqPacket, IfaceKind},
 };
 
@@ -36,7 +38,57 @@ impl DevicePool {
     }
 
     pub(crate) fn device_ids(&self) -> Vec<u32> {
-        self.contexts.iter().map(|ctx| ctx.device_id).collect()
+        self.contexts
+            .iter()
+            .filter(|ctx| ctx.is_connected())
+            .map(|ctx| ctx.device_id)
+            .collect()
+    }
+
+    /// Disconnect a connected device which has the serial number.
+    pub(crate) fn disconnect(&mut self, serial_number: &str) -> Result<()> {
+        let ctx = self
+            .contexts
+            .iter_mut()
+            .find(|ctx| ctx.is_connected() && ctx.serial_number() == serial_number)
+            .ok_or(LibUsbError::NotFound)?;
+        ctx.disconnect();
+        Ok(())
+    }
+
+    /// Trigger events on a connected device which has the serial number.
+    pub(crate) fn trigger_events(&self, serial_number: &str, events: Vec<Event>) -> Result<()> {
+        self.contexts
+            .iter()
+            .find(|ctx| ctx.is_connected() && ctx.serial_number() == serial_number)
+            .ok_or(LibUsbError::NotFound)?
+            .device
+            .trigger_events(events)
+    }
+
+    /// Take a snapshot of the memory of a connected device which has the serial number.
+    pub(crate) fn snapshot(&self, serial_number: &str) -> Result<MemorySnapshot> {
+        self.contexts
+            .iter()
+            .find(|ctx| ctx.is_connected() && ctx.serial_number() == serial_number)
+            .ok_or(LibUsbError::NotFound)?
+            .device
+            .snapshot()
+    }
+
+    /// Reconnect a disconnected device which has the serial number.
+    /// The device is assigned a new device id.
+    pub(crate) fn reconnect(&mut self, serial_number: &str) -> Result<()> {
+        let device_id = self.next_id;
+        let ctx = self
+            .contexts
+            .iter_mut()
+            .find(|ctx| !ctx.is_connected() && ctx.serial_number() == serial_number)
+            .ok_or(LibUsbError::NotFound)?;
+        ctx.reconnect(device_id);
+
+        self.next_id += 1;
+        Ok(())
     }
 
     pub(crate) fn with<F, R>(f: F) -> R
@@ -44,7 +96,7 @@ impl DevicePool {
         F: FnOnce(&mut DevicePool) -> R,
     {
         let mut pool = task::block_on(DEVICE_POOL.lock());
-        f(&mut *pool)
+        f(&mut pool)
     }
 
     pub(super) fn claim_interface(
@@ -69,17 +121,29 @@ impl DevicePool {
     }
 
     fn ctx_mut(&mut self, id: u32) -> Result<&mut Context> {
-        self.contexts
+        let ctx = self
+            .contexts
             .iter_mut()
             .find(|ctx| ctx.device_id == id)
-            .ok_or_else(|| LibUsbError::NotFound.into())
+            .ok_or(LibUsbError::NotFound)?;
+        if ctx.is_connected() {
+            Ok(ctx)
+        } else {
+            Err(LibUsbError::NoDevice.into())
+        }
     }
 
     fn ctx(&self, id: u32) -> Result<&Context> {
-        self.contexts
+        let ctx = self
+            .contexts
             .iter()
             .find(|ctx| ctx.device_id == id)
-            .ok_or_else(|| LibUsbError::NotFound.into())
+            .ok_or(LibUsbError::NotFound)?;
+        if ctx.is_connected() {
+            Ok(ctx)
+        } else {
+            Err(LibUsbError::NoDevice.into())
+        }
     }
 
     fn new() -> Self {
@@ -94,7 +158,9 @@ impl DevicePool {
 struct Context {
     device: Device,
     device_id: u32,
-    channel: DevicePipe,
+
+    /// `None` if the device is disconnected.
+    channel: Option<DevicePipe>,
 
     /// Hold interface state.
     /// Currently just holds claimed state.
@@ -102,22 +168,40 @@ struct Context {
 }
 
 impl Context {
-    fn run(mut device: Device, device_id: u32) -> Self {
-        let channel = device.run();
-        let iface_state = vec![
+    fn run(device: Device, device_id: u32) -> Self {
+        let mut ctx = Self {
+            device,
+            device_id,
+            channel: None,
+            iface_state: HashMap::new(),
+        };
+        ctx.reconnect(device_id);
+        ctx
+    }
+
+    /// Shutdown the device. Channels which have been passed to the host are closed, so any
+    /// further operations on them fail with [`LibUsbError::NoDevice`].
+    fn disconnect(&mut self) {
+        self.device.shutdown();
+        self.channel = None;
+    }
+
+    /// Run the device again as a newly attached device.
+    fn reconnect(&mut self, device_id: u32) {
+        let channel = self.device.run();
+        self.device_id = device_id;
+        self.channel = Some(Arc::new(Mutex::new(channel)));
+        self.iface_state = vec![
             (IfaceKind::Control, false),
             (IfaceKind::Event, false),
             (IfaceKind::Stream, false),
         ]
         .into_iter()
         .collect();
+    }
 
-        Self {
-            device,
-            device_id,
-            channel: Arc::new(Mutex::new(channel)),
-            iface_state,
-        }
+    fn is_connected(&self) -> bool {
+        self.channel.is_some()
     }
 
     fn claim_interface(&mut self, iface: IfaceKind) -> Result<DevicePipe> {
@@ -125,7 +209,7 @@ impl Context {
             Err(LibUsbError::Busy.into())
         } else {
             *self.iface_state.get_mut(&iface).unwrap() = true;
-            Ok(self.channel.clone())
+            Ok(self.channel.clone().unwrap())
         }
     }
 
@@ -140,6 +224,10 @@ impl Context {
     fn device_info(&self) -> DeviceInfo {
         self.device.device_info().clone()
     }
+
+    fn serial_number(&self) -> &str {
+        &self.device.device_info().serial_number
+    }
 }
 
 impl Drop for Context {
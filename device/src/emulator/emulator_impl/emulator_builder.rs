@@ -2,24 +2,39 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use std::io::Write;
+
 use rand::seq::SliceRandom;
 use semver::Version;
+use sha1::Digest;
 use thiserror::Error;
 
 use crate::u3v::{BusSpeed, DeviceInfo};
 
 use super::{
+    bandwidth::Bandwidth,
+    clock::{Clock, VirtualClock},
     device::Device,
     device_pool::DevicePool,
-    memory::{Memory, ABRM, SBRM},
+    fault::{ControlFault, FaultInjector, StreamFault},
+    frame_source::{FrameSource, Gradient},
+    genapi,
+    memory::{ManifestTable, Memory, ABRM, MEMORY_END, SBRM},
+    register_image::{ExternalMemory, RegisterImage},
 };
 
-use cameleon_impl::memory::prelude::*;
+use cameleon_impl::memory::{prelude::*, MemorySnapshot};
 
 #[derive(Debug, Error)]
 pub enum BuilderError {
     #[error("invalid string: {0}")]
     InvalidString(String),
+
+    #[error("invalid GenApi XML: {0}")]
+    InvalidGenApiXml(String),
+
+    #[error("invalid memory snapshot: {0}")]
+    InvalidSnapshot(String),
 }
 
 pub type BuilderResult<T> = std::result::Result<T, BuilderError>;
@@ -51,6 +66,14 @@ pub type BuilderResult<T> = std::result::Result<T, BuilderError>;
 /// ```
 pub struct EmulatorBuilder {
     memory: Memory,
+    frame_source: Box<dyn FrameSource>,
+    control_faults: Vec<ControlFault>,
+    stream_faults: Vec<StreamFault>,
+    clock: Clock,
+    bandwidth: Option<Bandwidth>,
+    /// GenApi XML and register image passed by [`EmulatorBuilder::from_genapi_xml`].
+    genapi_xml: Option<(String, RegisterImage)>,
+    zip_genapi_xml: bool,
 }
 
 impl EmulatorBuilder {
@@ -67,7 +90,100 @@ impl EmulatorBuilder {
             .collect();
         memory.write::<ABRM::SerialNumber>(serial_number).unwrap();
 
-        Self { memory }
+        Self {
+            memory,
+            frame_source: Box::new(Gradient),
+            control_faults: vec![],
+            stream_faults: vec![],
+            clock: Clock::real(),
+            bandwidth: None,
+            genapi_xml: None,
+            zip_genapi_xml: false,
+        }
+    }
+
+    /// Construct a builder of a device which hosts `xml` instead of the built-in GenApi XML.
+    ///
+    /// Registers described in `xml` are backed by `register_image`, which is readable and
+    /// writable from the host. Bootstrap registers, i.e. `ABRM`, `SBRM`, `SIRM` and the manifest
+    /// table, are still provided by the emulator, so the image can't override them.
+    ///
+    /// The file version and schema version in the manifest table are taken from the attributes of
+    /// `RegisterDescription`. `VendorName` and `ModelName` are also written to ABRM if they are
+    /// valid as ABRM strings.
+    ///
+    /// NOTE: The emulator doesn't know the semantics of vendor registers, so streaming is
+    /// configured only via `SIRM` and frames are produced with the built-in image format.
+    ///
+    /// # Errors
+    /// If `xml` doesn't have `RegisterDescription` element or its version attributes, then
+    /// [`BuilderError::InvalidGenApiXml`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{EmulatorBuilder, RegisterImage};
+    ///
+    /// let xml = r#"<RegisterDescription ModelName="Vendor Camera" VendorName="Vendor"
+    ///     MajorVersion="1" MinorVersion="0" SubMinorVersion="0"
+    ///     SchemaMajorVersion="1" SchemaMinorVersion="1" SchemaSubMinorVersion="0">
+    ///     </RegisterDescription>"#;
+    /// let image = RegisterImage::new().segment(0x0010_0000, vec![0; 16]);
+    ///
+    /// EmulatorBuilder::from_genapi_xml(xml, image)
+    ///     .unwrap()
+    ///     .zip_genapi_xml()
+    ///     .build();
+    /// ```
+    pub fn from_genapi_xml(
+        xml: impl Into<String>,
+        register_image: RegisterImage,
+    ) -> BuilderResult<Self> {
+        let xml = xml.into();
+        let mut builder = Self::new();
+
+        let start = xml.find("<RegisterDescription").ok_or_else(|| {
+            BuilderError::InvalidGenApiXml("`RegisterDescription` is not found".into())
+        })?;
+        let tag = &xml[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let version = |name: &str| {
+            xml_attribute(tag, name)
+                .and_then(|value| value.parse::<u8>().ok())
+                .ok_or_else(|| {
+                    BuilderError::InvalidGenApiXml(format!("`{}` is missing or invalid", name))
+                })
+        };
+
+        let memory = &mut builder.memory;
+        memory
+            .write::<ManifestTable::GenICamFileVersionMajor>(version("MajorVersion")?)
+            .unwrap();
+        memory
+            .write::<ManifestTable::GenICamFileVersionMinor>(version("MinorVersion")?)
+            .unwrap();
+        memory
+            .write::<ManifestTable::GenICamFileVersionSubMinor>(version("SubMinorVersion")?.into())
+            .unwrap();
+        memory
+            .write::<ManifestTable::SchemaVersionMajor>(version("SchemaMajorVersion")?.into())
+            .unwrap();
+        memory
+            .write::<ManifestTable::SchemaVersionMinor>(version("SchemaMinorVersion")?.into())
+            .unwrap();
+
+        // Names which can't be stored in ABRM are just ignored because they are informative.
+        if let Some(vendor_name) = xml_attribute(tag, "VendorName") {
+            memory
+                .write::<ABRM::ManufacturerName>(vendor_name.into())
+                .ok();
+        }
+        if let Some(model_name) = xml_attribute(tag, "ModelName") {
+            memory.write::<ABRM::ModelName>(model_name.into()).ok();
+        }
+
+        builder.genapi_xml = Some((xml, register_image));
+        Ok(builder)
     }
 
     /// Build an emulator and pass it to the device pool. User can't control the emulator itself
@@ -89,12 +205,39 @@ impl EmulatorBuilder {
     /// EmulatorBuilder::new().user_defined_name("My Camera").unwrap().serial_number("CAM1984").unwrap().build();
     ///
     /// ```
-    pub fn build(self) {
+    pub fn build(mut self) {
         let device_info = self.build_device_info();
-        let device = Device::new(self.memory, device_info);
+        let external_memory = self.host_genapi_xml();
+        let faults = FaultInjector::new(self.control_faults, self.stream_faults);
+        let device = Device::new(
+            self.memory,
+            external_memory,
+            device_info,
+            self.frame_source,
+            faults,
+            self.clock,
+            self.bandwidth,
+        );
         DevicePool::with(|pool| pool.pool_and_run(device));
     }
 
+    /// Host the GenApi XML compressed in ZIP format.
+    ///
+    /// By default, the GenApi XML is hosted uncompressed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::EmulatorBuilder;
+    ///
+    /// EmulatorBuilder::new().zip_genapi_xml().build();
+    /// ```
+    #[must_use]
+    pub fn zip_genapi_xml(mut self) -> Self {
+        self.zip_genapi_xml = true;
+        self
+    }
+
     /// Setter of serial number of the device. The data is flushed to ABRM segment of the device memory.
     ///
     /// If serial number isn't set, 8 length digit is set at random.
@@ -115,7 +258,7 @@ impl EmulatorBuilder {
     /// ```
     pub fn serial_number(mut self, serial: &str) -> BuilderResult<Self> {
         self.memory
-            .write::<ABRM::UserDefinedName>(serial.into())
+            .write::<ABRM::SerialNumber>(serial.into())
             .map_err(|e| BuilderError::InvalidString(format! {"{}", e}))?;
         Ok(self)
     }
@@ -145,6 +288,239 @@ impl EmulatorBuilder {
         Ok(self)
     }
 
+    /// Restore the registers of the device from `snapshot`, which is taken by
+    /// [`snapshot_device`](crate::u3v::snapshot_device).
+    ///
+    /// All registers including the serial number are overwritten, so settings configured by the
+    /// other setters should be done after this method.
+    ///
+    /// NOTE: A snapshot taken while the device is streaming locks the transport layer parameters.
+    /// The host needs to unlock them by writing `0` to `TLParamsLocked` before changing them.
+    ///
+    /// # Errors
+    /// If `snapshot` is taken from a memory layout which differs from the device, e.g. a device
+    /// emulated by another version of this crate, then [`BuilderError::InvalidSnapshot`] is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{self, EmulatorBuilder};
+    ///
+    /// EmulatorBuilder::new().serial_number("CAM0053").unwrap().build();
+    /// let snapshot = u3v::snapshot_device("CAM0053").unwrap();
+    ///
+    /// EmulatorBuilder::new()
+    ///     .memory_snapshot(snapshot)
+    ///     .unwrap()
+    ///     .serial_number("CAM0054")
+    ///     .unwrap()
+    ///     .build();
+    /// ```
+    pub fn memory_snapshot(mut self, snapshot: MemorySnapshot) -> BuilderResult<Self> {
+        self.memory
+            .restore(snapshot)
+            .map_err(|e| BuilderError::InvalidSnapshot(format! {"{}", e}))?;
+        Ok(self)
+    }
+
+    /// Setter of the frame source which produces images sent from the device.
+    ///
+    /// If frame source isn't set, [`Gradient`] is used.
+    ///
+    /// See [`frame_source`](super::frame_source) for built-in frame sources.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{frame_source::MovingBar, EmulatorBuilder};
+    ///
+    /// EmulatorBuilder::new().frame_source(MovingBar::new(8, 4)).build();
+    /// ```
+    #[must_use]
+    pub fn frame_source(mut self, source: impl FrameSource) -> Self {
+        self.frame_source = Box::new(source);
+        self
+    }
+
+    /// Drive the device with a virtual clock instead of the wall clock.
+    ///
+    /// The device time advances only when [`VirtualClock::advance`] is called on `clock` or its
+    /// clones. See [`clock`](super::clock) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use cameleon_device::u3v::{clock::VirtualClock, EmulatorBuilder};
+    ///
+    /// let clock = VirtualClock::new();
+    /// EmulatorBuilder::new().virtual_clock(clock.clone()).build();
+    /// clock.advance(Duration::from_millis(100));
+    /// ```
+    #[must_use]
+    pub fn virtual_clock(mut self, clock: VirtualClock) -> Self {
+        self.clock = Clock::Virtual(clock);
+        self
+    }
+
+    /// Setter of the bus speed which the device reports in `SBRM`.
+    ///
+    /// If bus speed isn't set, [`BusSpeed::SuperSpeed`] is reported.
+    ///
+    /// NOTE: The bus speed doesn't limit the throughput of the device. Use
+    /// [`EmulatorBuilder::bandwidth`] to limit it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{bandwidth::Bandwidth, BusSpeed, EmulatorBuilder};
+    ///
+    /// let speed = BusSpeed::HighSpeed;
+    /// EmulatorBuilder::new()
+    ///     .bus_speed(speed)
+    ///     .bandwidth(Bandwidth::from_bus_speed(speed))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn bus_speed(mut self, speed: BusSpeed) -> Self {
+        let speed = match speed {
+            BusSpeed::LowSpeed => 0b00001,
+            BusSpeed::FullSpeed => 0b00010,
+            BusSpeed::HighSpeed => 0b00100,
+            BusSpeed::SuperSpeed => 0b01000,
+            BusSpeed::SuperSpeedPlus => 0b10000,
+        };
+        self.memory.write::<SBRM::CurrentSpeed>(speed).unwrap();
+        self
+    }
+
+    /// Limit the throughput of the stream endpoint to the bandwidth budget.
+    ///
+    /// If bandwidth isn't set, the device sends frames as fast as the host reads them.
+    ///
+    /// See [`bandwidth`](super::bandwidth) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{
+    ///     bandwidth::{Bandwidth, Overflow},
+    ///     EmulatorBuilder,
+    /// };
+    ///
+    /// EmulatorBuilder::new()
+    ///     .bandwidth(Bandwidth::new(100_000_000).overflow(Overflow::Discard))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn bandwidth(mut self, bandwidth: Bandwidth) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    /// Register a fault injected into the control endpoint.
+    ///
+    /// This method can be called multiple times. If multiple faults match a command, the fault
+    /// registered first is applied.
+    ///
+    /// See [`fault`](super::fault) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use cameleon_device::u3v::{
+    ///     fault::{ControlFault, ControlFaultKind},
+    ///     EmulatorBuilder,
+    /// };
+    ///
+    /// let pending = ControlFaultKind::Pending {
+    ///     timeout: Duration::from_millis(100),
+    ///     delay: Duration::from_millis(50),
+    /// };
+    /// EmulatorBuilder::new()
+    ///     .control_fault(ControlFault::new(pending).times(1))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn control_fault(mut self, fault: ControlFault) -> Self {
+        self.control_faults.push(fault);
+        self
+    }
+
+    /// Register a fault injected into the stream endpoint.
+    ///
+    /// This method can be called multiple times. If multiple faults match a frame, the fault
+    /// registered first is applied.
+    ///
+    /// See [`fault`](super::fault) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cameleon_device::u3v::{
+    ///     fault::{StreamFault, StreamFaultKind},
+    ///     EmulatorBuilder,
+    /// };
+    ///
+    /// EmulatorBuilder::new()
+    ///     .stream_fault(StreamFault::new(StreamFaultKind::Truncate(1024)).block_ids(0..1))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn stream_fault(mut self, fault: StreamFault) -> Self {
+        self.stream_faults.push(fault);
+        self
+    }
+
+    /// Fill the manifest entry of the GenApi XML, and returns [`ExternalMemory`] if the XML is
+    /// hosted outside of the built-in register maps.
+    fn host_genapi_xml(&mut self) -> Option<ExternalMemory> {
+        let (xml, image) = match self.genapi_xml.take() {
+            Some((xml, image)) => (xml, image),
+            None if self.zip_genapi_xml => (genapi::GENAPI_XML.to_string(), RegisterImage::new()),
+            None => {
+                // The built-in XML is already placed in the memory.
+                let hash = sha1::Sha1::digest(genapi::GENAPI_XML.as_bytes());
+                self.memory
+                    .write::<ManifestTable::Sha1Hash>(hash.to_vec())
+                    .unwrap();
+                return None;
+            }
+        };
+
+        let file = if self.zip_genapi_xml {
+            self.memory.write::<ManifestTable::FileFormat>(1).unwrap();
+            zip_xml(&xml)
+        } else {
+            xml.into_bytes()
+        };
+        let hash = sha1::Sha1::digest(&file);
+
+        // Place the file after the memory and the image.
+        let xml_address = (MEMORY_END as u64)
+            .max(image.end())
+            .checked_add(0xfff)
+            .expect("no room to place GenApi XML")
+            & !0xfff;
+
+        let memory = &mut self.memory;
+        memory
+            .write::<ManifestTable::RegisterAddress>(xml_address)
+            .unwrap();
+        memory
+            .write::<ManifestTable::FileSize>(file.len() as u64)
+            .unwrap();
+        memory
+            .write::<ManifestTable::Sha1Hash>(hash.to_vec())
+            .unwrap();
+
+        Some(ExternalMemory::new(image, xml_address, file))
+    }
+
     fn build_device_info(&self) -> DeviceInfo {
         use ABRM::{
             DeviceVersion, FamilyName, GenCpVersionMajor, GenCpVersionMinor, ManufacturerInfo,
@@ -168,8 +544,13 @@ impl EmulatorBuilder {
         let supported_speed = self.current_speed();
         let serial_number = self.memory.read::<SerialNumber>().unwrap();
 
-        // TODO: Read from SBRM.
-        let u3v_version = Version::new(1, 0, 0);
+        let u3v_version_major = self.memory.read::<SBRM::U3VVersionMajor>().unwrap();
+        let u3v_version_minor = self.memory.read::<SBRM::U3VVersionMinor>().unwrap();
+        let u3v_version = Version::new(
+            u64::from(u3v_version_major),
+            u64::from(u3v_version_minor),
+            0,
+        );
 
         // Device guid consists of 12 characters.
         // First 4 characters are vendor ID and last 8 characters are unique id assigned by a vendor.
@@ -178,7 +559,7 @@ impl EmulatorBuilder {
         let guid = if serial_len > 8 {
             format!("EMU-{}", &serial_number[serial_len - 8..])
         } else {
-            let pad: String = std::iter::repeat('0').take(8 - serial_len).collect();
+            let pad = "0".repeat(8 - serial_len);
             format!("EMU-{}{}", pad, serial_number)
         };
 
@@ -215,3 +596,43 @@ impl Default for EmulatorBuilder {
         Self::new()
     }
 }
+
+/// Returns the value of the attribute `name` in the start `tag`.
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.match_indices(name).find_map(|(pos, _)| {
+        // Make sure that `name` isn't a part of another attribute name.
+        if !tag[..pos].ends_with(char::is_whitespace) {
+            return None;
+        }
+
+        let rest = tag[pos + name.len()..].trim_start();
+        let rest = rest.strip_prefix('=')?.trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let rest = &rest[1..];
+        rest.find(quote).map(|end| &rest[..end])
+    })
+}
+
+/// Compress the XML into a ZIP archive which contains only the XML file.
+fn zip_xml(xml: &str) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    zip.start_file("genapi.xml", zip::write::FileOptions::default())
+        .unwrap();
+    zip.write_all(xml.as_bytes()).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_attribute() {
+        let tag = r#"<RegisterDescription SchemaMajorVersion="1" MajorVersion = '2'
+            ModelName="Camera""#;
+        assert_eq!(xml_attribute(tag, "MajorVersion"), Some("2"));
+        assert_eq!(xml_attribute(tag, "SchemaMajorVersion"), Some("1"));
+        assert_eq!(xml_attribute(tag, "ModelName"), Some("Camera"));
+        assert_eq!(xml_attribute(tag, "MinorVersion"), None);
+    }
+}
@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains [`Event`] which can be sent from an emulated device.
+//!
+//! Events are triggered by [`trigger_events`](crate::u3v::trigger_events) and sent to the host
+//! while the event interface of the device is enabled. If the host sets the multi event enable
+//! bit of `DeviceConfiguration` in ABRM, events triggered at once are packed into a single event
+//! packet, otherwise each event is sent in its own packet.
+//!
+//! # Example
+//! ```rust
+//! use cameleon_device::u3v::{self, event::Event, EmulatorBuilder};
+//!
+//! EmulatorBuilder::new().serial_number("CAM0007").unwrap().build();
+//!
+//! u3v::trigger_events(
+//!     "CAM0007",
+//!     vec![
+//!         Event::new(Event::EXPOSURE_END).data(vec![0x01, 0x02]),
+//!         Event::new(Event::FRAME_START),
+//!     ],
+//! )
+//! .unwrap();
+//! ```
+
+/// Event sent from an emulated device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub(super) id: u16,
+    pub(super) data: Vec<u8>,
+}
+
+impl Event {
+    /// Event id of `FrameStart` event.
+    pub const FRAME_START: u16 = 0x9000;
+
+    /// Event id of `FrameEnd` event.
+    pub const FRAME_END: u16 = 0x9001;
+
+    /// Event id of `ExposureStart` event.
+    pub const EXPOSURE_START: u16 = 0x9002;
+
+    /// Event id of `ExposureEnd` event.
+    pub const EXPOSURE_END: u16 = 0x9003;
+
+    /// Construct an event of `id` without event data.
+    ///
+    /// Event ids in `0x9000..=0xFFFF` are device specific. Built-in ids are defined as associated
+    /// constants.
+    #[must_use]
+    pub fn new(id: u16) -> Self {
+        Self { id, data: vec![] }
+    }
+
+    /// Set event data which follows the event header.
+    #[must_use]
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// Event id of the event.
+    #[must_use]
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+}
@@ -2,12 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use std::{convert::TryFrom, sync::Arc};
+
 use async_std::{
     channel::{Receiver, Sender},
     prelude::*,
+    sync::Mutex,
 };
 
+use cameleon_impl::memory::prelude::*;
+
 use super::{
+    clock::Timestamp,
+    event::Event,
+    memory::{Memory, ABRM, EIRM},
     shared_queue::SharedQueue,
     signal::{EventSignal, InterfaceSignal},
     IfaceKind,
@@ -15,16 +23,24 @@ use super::{
 
 pub(super) struct EventModule {
     queue: SharedQueue<Vec<u8>>,
-    timestamp: u64,
+    memory: Arc<Mutex<Memory>>,
+    timestamp: Timestamp,
+    next_request_id: u16,
 
     enabled: bool,
 }
 
 impl EventModule {
-    pub(super) fn new(queue: SharedQueue<Vec<u8>>, timestamp: u64) -> Self {
+    pub(super) fn new(
+        queue: SharedQueue<Vec<u8>>,
+        memory: Arc<Mutex<Memory>>,
+        timestamp: Timestamp,
+    ) -> Self {
         Self {
             queue,
+            memory,
             timestamp,
+            next_request_id: 0,
             enabled: false,
         }
     }
@@ -36,23 +52,15 @@ impl EventModule {
     ) {
         while let Some(signal) = signal_rx.next().await {
             match signal {
-                EventSignal::_EventData {
-                    event_id,
-                    data,
-                    request_id,
-                } => {
+                EventSignal::EventData(events) => {
                     if self.enabled {
-                        self.enqueue_or_halt(event_id, &data, request_id, &signal_tx)
+                        self.send_events(&events, &signal_tx).await;
                     } else {
                         log::warn! {"receive event data signal, but event module is currently disabled"}
                     }
                 }
 
-                EventSignal::UpdateTimestamp(timestamp) => {
-                    self.timestamp = timestamp;
-                }
-
-                EventSignal::_Enable => {
+                EventSignal::Enable => {
                     if self.enabled {
                         log::warn! {"receive event enable signal, but event module is already enabled"}
                     } else {
@@ -77,39 +85,87 @@ impl EventModule {
         }
     }
 
-    fn enqueue_or_halt(
-        &mut self,
-        event_id: u16,
-        data: &[u8],
-        request_id: u16,
-        signal_tx: &Sender<InterfaceSignal>,
-    ) {
-        let scd = match event_packet::EventScd::single_event(event_id, data, self.timestamp) {
-            Ok(scd) => scd,
+    /// Send events to the host.
+    /// If multi event is enabled, events are packed into as few packets as possible.
+    async fn send_events(&mut self, events: &[Event], signal_tx: &Sender<InterfaceSignal>) {
+        let timestamp = self.timestamp.as_nanos();
+        let scds = if self.is_multi_event_enabled().await {
+            events
+                .iter()
+                .map(|event| event_packet::EventScd::multi_event(event.id, &event.data, timestamp))
+                .collect::<event_packet::ProtocolResult<Vec<_>>>()
+        } else {
+            events
+                .iter()
+                .map(|event| event_packet::EventScd::single_event(event.id, &event.data, timestamp))
+                .collect()
+        };
+        let scds = match scds {
+            Ok(scds) => scds,
             Err(e) => {
                 log::error!("can't generate event packet: cause {}", e);
                 return;
             }
         };
 
-        let mut bytes = vec![];
-        if let Err(e) = scd.finalize(request_id).serialize(&mut bytes) {
-            log::error!("cant't serialize event packet: cause {}", e);
-            return;
+        let max_scd_len = self.max_scd_len().await;
+        let (scds, too_large): (Vec<_>, Vec<_>) = scds
+            .into_iter()
+            .partition(|scd| scd.scd_len_unchecked() <= max_scd_len);
+        for scd in too_large {
+            log::error!(
+                "event {:#x} is dropped because it exceeds the maximum event transfer length",
+                scd.event_id
+            );
         }
 
-        if !self.queue.enqueue(bytes) {
-            log::warn!("event queue is full, entering a halted state",);
+        for packet in event_packet::EventPacket::pack(scds, max_scd_len, &mut self.next_request_id)
+        {
+            let mut bytes = vec![];
+            if let Err(e) = packet.serialize(&mut bytes) {
+                log::error!("cant't serialize event packet: cause {}", e);
+                return;
+            }
 
-            let signal = InterfaceSignal::Halt(IfaceKind::Event);
+            if !self.enqueue_or_halt(bytes, signal_tx) {
+                return;
+            }
+        }
+    }
 
-            match signal_tx.try_send(signal) {
-                Ok(()) => {}
-                Err(_) => {
-                    log::error!("Control module -> Interface channel is full");
-                }
+    async fn is_multi_event_enabled(&self) -> bool {
+        let memory = self.memory.lock().await;
+        matches!(memory.read::<ABRM::DeviceConfiguration>(), Ok(config) if config[0] & 0b10 != 0)
+    }
+
+    /// Maximum SCD length which fits in `EIRM::MaximumEventTransferLength`.
+    async fn max_scd_len(&self) -> u16 {
+        let memory = self.memory.lock().await;
+        let max_transfer_len = memory
+            .read::<EIRM::MaximumEventTransferLength>()
+            .unwrap_or(u32::MAX);
+        let max_scd_len =
+            max_transfer_len.saturating_sub(u32::from(event_packet::EventPacket::CCD_LEN));
+        u16::try_from(max_scd_len).unwrap_or(u16::MAX)
+    }
+
+    /// Returns `false` if the queue is full and the interface enters a halted state.
+    fn enqueue_or_halt(&mut self, bytes: Vec<u8>, signal_tx: &Sender<InterfaceSignal>) -> bool {
+        if self.queue.enqueue(bytes) {
+            return true;
+        }
+
+        log::warn!("event queue is full, entering a halted state",);
+
+        let signal = InterfaceSignal::Halt(IfaceKind::Event);
+
+        match signal_tx.try_send(signal) {
+            Ok(()) => {}
+            Err(_) => {
+                log::error!("Control module -> Interface channel is full");
             }
         }
+        false
     }
 }
 
@@ -136,22 +192,47 @@ mod event_packet {
     pub(super) type ProtocolResult<T> = std::result::Result<T, ProtocolError>;
 
     pub(super) struct EventPacket<'a> {
-        _scd_len: u16,
         request_id: u16,
-        scd: EventScd<'a>,
+        scds: Vec<EventScd<'a>>,
     }
 
     impl<'a> EventPacket<'a> {
         const PREFIX_MAGIC: u32 = 0x4556_3355;
         const COMMAND_FLAG: u16 = 0b1 << 14;
         const COMMAND_ID: u16 = 0x0C00;
+        pub(super) const CCD_LEN: u16 = 12;
+
+        /// Pack SCDs into packets.
+        ///
+        /// A single event SCD always occupies its own packet, and consecutive multi event SCDs are
+        /// packed into a packet as long as the SCD length doesn't exceed `max_scd_len`.
+        /// Request id is assigned to each packet starting from `next_request_id`.
+        pub(super) fn pack(
+            scds: Vec<EventScd<'a>>,
+            max_scd_len: u16,
+            next_request_id: &mut u16,
+        ) -> Vec<Self> {
+            let mut packets: Vec<Self> = vec![];
+            for scd in scds {
+                if let Some(last) = packets.last_mut() {
+                    let fits = matches!(
+                        last.scd_len_unchecked().checked_add(scd.scd_len_unchecked()),
+                        Some(len) if len <= max_scd_len
+                    );
+                    if scd.is_multi_event() && last.scds[0].is_multi_event() && fits {
+                        last.scds.push(scd);
+                        continue;
+                    }
+                }
 
-        fn from_scd(scd: EventScd<'a>, request_id: u16) -> Self {
-            Self {
-                _scd_len: scd.scd_len_unchecked(),
-                request_id,
-                scd,
+                packets.push(Self {
+                    request_id: *next_request_id,
+                    scds: vec![scd],
+                });
+                *next_request_id = next_request_id.wrapping_add(1);
             }
+
+            packets
         }
 
         pub(super) fn serialize(&self, mut buf: impl Write) -> ProtocolResult<()> {
@@ -159,16 +240,21 @@ mod event_packet {
             buf.write_bytes(Self::PREFIX_MAGIC)?;
             buf.write_bytes(Self::COMMAND_FLAG)?;
             buf.write_bytes(Self::COMMAND_ID)?;
-            buf.write_bytes(self.scd.scd_len_unchecked())?;
+            buf.write_bytes(self.scd_len_unchecked())?;
             buf.write_bytes(self.request_id)?;
 
             // Serialize SCD.
-            self.scd.serialize(buf)?;
+            for scd in &self.scds {
+                scd.serialize(&mut buf)?;
+            }
             Ok(())
         }
+
+        fn scd_len_unchecked(&self) -> u16 {
+            self.scds.iter().map(EventScd::scd_len_unchecked).sum()
+        }
     }
 
-    // TODO: Implement Multievent.
     impl<'a> EventScd<'a> {
         pub(super) fn single_event(
             event_id: u16,
@@ -185,8 +271,18 @@ mod event_packet {
             Ok(scd)
         }
 
-        pub(super) fn finalize(self, request_id: u16) -> EventPacket<'a> {
-            EventPacket::from_scd(self, request_id)
+        pub(super) fn multi_event(
+            event_id: u16,
+            data: &'a [u8],
+            timestamp: u64,
+        ) -> ProtocolResult<Self> {
+            let mut scd = Self::single_event(event_id, data, timestamp)?;
+            scd.event_size = scd.scd_len_unchecked();
+            Ok(scd)
+        }
+
+        fn is_multi_event(&self) -> bool {
+            self.event_size != 0
         }
 
         fn serialize(&self, mut buf: impl Write) -> ProtocolResult<()> {
@@ -197,7 +293,7 @@ mod event_packet {
             Ok(())
         }
 
-        fn scd_len_unchecked(&self) -> u16 {
+        pub(super) fn scd_len_unchecked(&self) -> u16 {
             self.scd_len_checked().unwrap()
         }
 
@@ -224,12 +320,17 @@ mod event_packet {
             let data = &[1, 2, 3];
             let timestamp = 123_456_789;
             let event_id = 0xff;
-            let event_pacekt = EventScd::single_event(event_id, data, timestamp)
-                .unwrap()
-                .finalize(10);
-            let mut buf = vec![];
-            event_pacekt.serialize(&mut buf).unwrap();
+            let scds = vec![
+                EventScd::single_event(event_id, data, timestamp).unwrap(),
+                EventScd::single_event(event_id, data, timestamp).unwrap(),
+            ];
+            let mut request_id = 10;
+            let packets = EventPacket::pack(scds, u16::MAX, &mut request_id);
+            assert_eq!(packets.len(), 2);
+            assert_eq!(request_id, 12);
 
+            let mut buf = vec![];
+            packets[0].serialize(&mut buf).unwrap();
             let parsed = event::EventPacket::parse(&buf).unwrap();
 
             assert_eq!(parsed.request_id(), 10);
@@ -238,6 +339,47 @@ mod event_packet {
             assert_eq!(parsed.scd[0].timestamp, timestamp);
             assert_eq!(parsed.scd[0].data, data);
         }
+
+        #[test]
+        fn test_multi_event() {
+            let data = &[1, 2, 3];
+            let scds = vec![
+                EventScd::multi_event(0x10, data, 1).unwrap(),
+                EventScd::multi_event(0x11, &[], 2).unwrap(),
+            ];
+            let mut request_id = 0;
+            let packets = EventPacket::pack(scds, u16::MAX, &mut request_id);
+            assert_eq!(packets.len(), 1);
+            assert_eq!(request_id, 1);
+
+            let mut buf = vec![];
+            packets[0].serialize(&mut buf).unwrap();
+            let parsed = event::EventPacket::parse(&buf).unwrap();
+
+            assert_eq!(parsed.request_id(), 0);
+            assert_eq!(parsed.scd.len(), 2);
+            assert_eq!(parsed.scd[0].event_id, 0x10);
+            assert_eq!(parsed.scd[0].timestamp, 1);
+            assert_eq!(parsed.scd[0].data, data);
+            assert_eq!(parsed.scd[1].event_id, 0x11);
+            assert_eq!(parsed.scd[1].timestamp, 2);
+            assert!(parsed.scd[1].data.is_empty());
+        }
+
+        #[test]
+        fn test_max_scd_len() {
+            let scds = vec![
+                EventScd::multi_event(0x10, &[0; 4], 0).unwrap(),
+                EventScd::multi_event(0x11, &[0; 4], 0).unwrap(),
+                EventScd::multi_event(0x12, &[0; 4], 0).unwrap(),
+            ];
+            // Each SCD is 16 bytes long, so only two SCDs fit in a packet.
+            let mut request_id = 0;
+            let packets = EventPacket::pack(scds, 40, &mut request_id);
+            assert_eq!(packets.len(), 2);
+            assert_eq!(packets[0].scds.len(), 2);
+            assert_eq!(packets[1].scds.len(), 1);
+        }
     }
 }
 
@@ -249,11 +391,17 @@ mod tests {
 
     use crate::u3v::protocol::event;
 
-    use super::*;
+    use super::{
+        super::clock::{Clock, VirtualClock},
+        *,
+    };
 
     const TO: Duration = Duration::from_millis(100);
 
-    fn spawn_module() -> (
+    fn spawn_module(
+        memory: Memory,
+        clock: VirtualClock,
+    ) -> (
         Sender<EventSignal>,
         Receiver<InterfaceSignal>,
         SharedQueue<Vec<u8>>,
@@ -261,7 +409,8 @@ mod tests {
         let (signal_tx, signal_rx) = channel::bounded(10);
         let (iface_signal_tx, iface_signal_rx) = channel::bounded(10);
         let queue = SharedQueue::new(10);
-        let event_module = EventModule::new(queue.clone(), 0);
+        let timestamp = Timestamp::new(Clock::Virtual(clock));
+        let event_module = EventModule::new(queue.clone(), Arc::new(Mutex::new(memory)), timestamp);
         task::spawn(event_module.run(iface_signal_tx, signal_rx));
 
         (signal_tx, iface_signal_rx, queue)
@@ -280,7 +429,7 @@ mod tests {
 
     #[test]
     fn test_run_and_stop() {
-        let (signal_tx, mut iface_signal_rx, _) = spawn_module();
+        let (signal_tx, mut iface_signal_rx, _) = spawn_module(Memory::new(), VirtualClock::new());
 
         assert!(signal_tx.try_send(EventSignal::Shutdown).is_ok());
         task::block_on(timeout(TO, iface_signal_rx.next())).unwrap();
@@ -288,43 +437,67 @@ mod tests {
 
     #[test]
     fn test_signal() {
-        let (signal_tx, mut iface_signal_rx, queue) = spawn_module();
-        signal_tx.try_send(EventSignal::_Enable).unwrap();
+        let clock = VirtualClock::new();
+        let (signal_tx, mut iface_signal_rx, queue) = spawn_module(Memory::new(), clock.clone());
+        signal_tx.try_send(EventSignal::Enable).unwrap();
 
         // Test EventData signal.
         let event_id = 10;
-        let request_id = 20;
         let data = vec![1, 2, 3];
+        let events = vec![
+            Event::new(event_id).data(data.clone()),
+            Event::new(event_id).data(data.clone()),
+        ];
         signal_tx
-            .try_send(EventSignal::_EventData {
-                event_id,
-                data: data.clone(),
-                request_id,
-            })
+            .try_send(EventSignal::EventData(events.clone()))
             .unwrap();
 
-        let received = receive_data(&queue).unwrap();
+        // Each event is sent in its own packet.
+        for request_id in 0..2 {
+            let received = receive_data(&queue).unwrap();
+            let event_packet = event::EventPacket::parse(&received).unwrap();
+            assert_eq!(event_packet.request_id(), request_id);
+            assert_eq!(event_packet.scd.len(), 1);
+            assert_eq!(&event_packet.scd[0].data, &data.as_slice());
+        }
 
+        // Events are stamped with the device time.
+        clock.advance(Duration::from_nanos(123_456_789));
+        signal_tx.try_send(EventSignal::EventData(events)).unwrap();
+        let received = receive_data(&queue).unwrap();
         let event_packet = event::EventPacket::parse(&received).unwrap();
-        assert_eq!(event_packet.request_id(), request_id);
-        assert_eq!(event_packet.scd.len(), 1);
-        assert_eq!(&event_packet.scd[0].data, &data.as_slice());
+        assert_eq!(event_packet.scd[0].timestamp, 123_456_789);
 
-        // Test UpdateTimestamp signal.
-        let timestamp = 123_456_789;
-        signal_tx
-            .try_send(EventSignal::UpdateTimestamp(timestamp))
-            .unwrap();
-        signal_tx
-            .try_send(EventSignal::_EventData {
-                event_id,
-                data,
-                request_id,
-            })
-            .unwrap();
+        // Clean up.
+        assert!(signal_tx.try_send(EventSignal::Shutdown).is_ok());
+        task::block_on(timeout(TO, iface_signal_rx.next())).unwrap();
+    }
+
+    #[test]
+    fn test_multi_event() {
+        // Enable multi event.
+        let mut memory = Memory::new();
+        let mut config = memory.read::<ABRM::DeviceConfiguration>().unwrap();
+        config[0] |= 0b10;
+        memory.write::<ABRM::DeviceConfiguration>(config).unwrap();
+
+        let (signal_tx, mut iface_signal_rx, queue) = spawn_module(memory, VirtualClock::new());
+        signal_tx.try_send(EventSignal::Enable).unwrap();
+
+        let events = vec![
+            Event::new(Event::FRAME_START).data(vec![1, 2]),
+            Event::new(Event::EXPOSURE_END),
+        ];
+        signal_tx.try_send(EventSignal::EventData(events)).unwrap();
+
+        // Events are packed into a single packet.
         let received = receive_data(&queue).unwrap();
         let event_packet = event::EventPacket::parse(&received).unwrap();
-        assert_eq!(event_packet.scd[0].timestamp, timestamp);
+        assert_eq!(event_packet.scd.len(), 2);
+        assert_eq!(event_packet.scd[0].event_id, Event::FRAME_START);
+        assert_eq!(event_packet.scd[0].data, &[1, 2]);
+        assert_eq!(event_packet.scd[1].event_id, Event::EXPOSURE_END);
+        assert!(receive_data(&queue).is_none());
 
         // Clean up.
         assert!(signal_tx.try_send(EventSignal::Shutdown).is_ok());
@@ -0,0 +1,267 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains faults which can be injected into an emulated device.
+//!
+//! Faults are registered to [`EmulatorBuilder`](super::EmulatorBuilder) and applied to commands
+//! or frames which match the fault condition. If multiple faults match, the fault registered
+//! first is applied.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use cameleon_device::u3v::{
+//!     fault::{ControlFault, ControlFaultKind, StreamFault, StreamFaultKind},
+//!     EmulatorBuilder,
+//! };
+//!
+//! EmulatorBuilder::new()
+//!     // Drop acks for the first two commands which access `0x0000..0x0004`.
+//!     .control_fault(
+//!         ControlFault::new(ControlFaultKind::Drop)
+//!             .address_range(0x0000..0x0004)
+//!             .times(2),
+//!     )
+//!     // Stall transfers of the third frame for 100ms.
+//!     .stream_fault(
+//!         StreamFault::new(StreamFaultKind::Stall(Duration::from_millis(100))).block_ids(2..3),
+//!     )
+//!     .build();
+//! ```
+
+use std::{ops::Range, sync::Arc, time::Duration};
+
+use async_std::sync::Mutex;
+
+use super::IfaceKind;
+
+/// Endpoint of an emulated device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Control,
+    Event,
+    Stream,
+}
+
+impl From<Endpoint> for IfaceKind {
+    fn from(endpoint: Endpoint) -> Self {
+        match endpoint {
+            Endpoint::Control => Self::Control,
+            Endpoint::Event => Self::Event,
+            Endpoint::Stream => Self::Stream,
+        }
+    }
+}
+
+/// Kind of fault injected into the control endpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlFaultKind {
+    /// Send a pending ack with `timeout` first, then send the actual ack after `delay`.
+    Pending { timeout: Duration, delay: Duration },
+
+    /// Send the ack after the duration.
+    Delay(Duration),
+
+    /// Process the command, but never send the ack.
+    Drop,
+
+    /// Process the command, but break the prefix magic of the ack.
+    Corrupt,
+
+    /// Process the command, but send the ack with a request id which differs from the command's
+    /// one.
+    RequestIdMismatch,
+
+    /// Send an error ack with the raw status code without processing the command.
+    ///
+    /// e.g. `0x8006` for `AccessDenied`, or `0xC000..=0xDFFF` for device specific errors.
+    Status(u16),
+
+    /// Halt the endpoint. The command is discarded.
+    Halt(Endpoint),
+}
+
+/// Fault injected into the control endpoint.
+#[derive(Debug, Clone)]
+pub struct ControlFault {
+    kind: ControlFaultKind,
+    address_range: Option<Range<u64>>,
+    remaining: Option<usize>,
+}
+
+impl ControlFault {
+    /// Construct a fault which is applied to all commands.
+    #[must_use]
+    pub fn new(kind: ControlFaultKind) -> Self {
+        Self {
+            kind,
+            address_range: None,
+            remaining: None,
+        }
+    }
+
+    /// Restrict the fault to commands which access any address in the range.
+    #[must_use]
+    pub fn address_range(mut self, range: Range<u64>) -> Self {
+        self.address_range = Some(range);
+        self
+    }
+
+    /// Restrict the fault to the first `times` commands which match the condition.
+    #[must_use]
+    pub fn times(mut self, times: usize) -> Self {
+        self.remaining = Some(times);
+        self
+    }
+
+    fn matches(&self, accesses: &[Range<u64>]) -> bool {
+        if self.remaining == Some(0) {
+            return false;
+        }
+
+        match &self.address_range {
+            Some(range) => accesses
+                .iter()
+                .any(|access| access.start < range.end && range.start < access.end),
+            None => true,
+        }
+    }
+}
+
+/// Kind of fault injected into the stream endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFaultKind {
+    /// Stop sending transfers of the frame for the duration after the leader is sent.
+    Stall(Duration),
+
+    /// Truncate each payload transfer of the frame to at most the length.
+    /// The trailer still reports the original payload size.
+    Truncate(usize),
+
+    /// Halt the stream endpoint instead of sending the frame. The endpoint is halted after the host
+    /// reads the previous frames.
+    Halt,
+}
+
+/// Fault injected into the stream endpoint.
+#[derive(Debug, Clone)]
+pub struct StreamFault {
+    kind: StreamFaultKind,
+    block_ids: Option<Range<u64>>,
+}
+
+impl StreamFault {
+    /// Construct a fault which is applied to all frames.
+    #[must_use]
+    pub fn new(kind: StreamFaultKind) -> Self {
+        Self {
+            kind,
+            block_ids: None,
+        }
+    }
+
+    /// Restrict the fault to frames whose block id is in the range.
+    #[must_use]
+    pub fn block_ids(mut self, range: Range<u64>) -> Self {
+        self.block_ids = Some(range);
+        self
+    }
+
+    fn matches(&self, block_id: u64) -> bool {
+        match &self.block_ids {
+            Some(range) => range.contains(&block_id),
+            None => true,
+        }
+    }
+}
+
+/// Holds faults registered to a device and selects a fault to inject.
+#[derive(Debug, Clone, Default)]
+pub(super) struct FaultInjector {
+    control: Arc<Mutex<Vec<ControlFault>>>,
+    stream: Arc<Mutex<Vec<StreamFault>>>,
+}
+
+impl FaultInjector {
+    pub(super) fn new(control: Vec<ControlFault>, stream: Vec<StreamFault>) -> Self {
+        Self {
+            control: Arc::new(Mutex::new(control)),
+            stream: Arc::new(Mutex::new(stream)),
+        }
+    }
+
+    /// Returns a fault to inject into a command which accesses `accesses`.
+    pub(super) async fn control_fault(&self, accesses: &[Range<u64>]) -> Option<ControlFaultKind> {
+        let mut faults = self.control.lock().await;
+        let fault = faults.iter_mut().find(|fault| fault.matches(accesses))?;
+        if let Some(remaining) = &mut fault.remaining {
+            *remaining -= 1;
+        }
+        Some(fault.kind)
+    }
+
+    /// Returns a fault to inject into a frame of `block_id`.
+    pub(super) async fn stream_fault(&self, block_id: u64) -> Option<StreamFaultKind> {
+        let faults = self.stream.lock().await;
+        faults
+            .iter()
+            .find(|fault| fault.matches(block_id))
+            .map(|fault| fault.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use async_std::task;
+
+    fn access(start: u64, end: u64) -> Vec<Range<u64>> {
+        std::iter::once(start..end).collect()
+    }
+
+    #[test]
+    fn test_control_fault() {
+        let injector = FaultInjector::new(
+            vec![
+                ControlFault::new(ControlFaultKind::Drop)
+                    .address_range(0x10..0x14)
+                    .times(1),
+                ControlFault::new(ControlFaultKind::Corrupt).address_range(0x10..0x20),
+            ],
+            vec![],
+        );
+
+        task::block_on(async {
+            assert_eq!(injector.control_fault(&access(0x0, 0x10)).await, None);
+            assert_eq!(
+                injector.control_fault(&access(0x0, 0x11)).await,
+                Some(ControlFaultKind::Drop)
+            );
+            assert_eq!(
+                injector.control_fault(&access(0x0, 0x11)).await,
+                Some(ControlFaultKind::Corrupt)
+            );
+            assert_eq!(injector.control_fault(&access(0x20, 0x24)).await, None);
+        });
+    }
+
+    #[test]
+    fn test_stream_fault() {
+        let injector = FaultInjector::new(
+            vec![],
+            vec![StreamFault::new(StreamFaultKind::Truncate(16)).block_ids(2..4)],
+        );
+
+        task::block_on(async {
+            assert_eq!(injector.stream_fault(1).await, None);
+            assert_eq!(
+                injector.stream_fault(2).await,
+                Some(StreamFaultKind::Truncate(16))
+            );
+            assert_eq!(injector.stream_fault(4).await, None);
+        });
+    }
+}
@@ -0,0 +1,324 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains [`FrameSource`] which produces images sent from an emulated device, and
+//! built-in frame sources.
+//!
+//! # Example
+//! ```rust
+//! use cameleon_device::u3v::{
+//!     frame_source::{Checkerboard, FrameCounter},
+//!     EmulatorBuilder,
+//! };
+//!
+//! // Checkerboard pattern whose first 8 bytes are overwritten with the block id.
+//! EmulatorBuilder::new()
+//!     .frame_source(FrameCounter::new(Checkerboard::new(16)))
+//!     .build();
+//!
+//! // Closure also works as a frame source.
+//! EmulatorBuilder::new()
+//!     .frame_source(|info: &cameleon_device::u3v::frame_source::FrameInfo| {
+//!         vec![info.block_id as u8; info.image_size()]
+//!     })
+//!     .build();
+//! ```
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::PixelFormat;
+
+/// Information of a frame which [`FrameSource`] should produce.
+#[derive(Debug, Clone)]
+pub struct FrameInfo {
+    /// Block id of the frame, which starts from 0 when streaming is enabled.
+    pub block_id: u64,
+
+    /// Timestamp of the frame in nanoseconds.
+    pub timestamp: u64,
+
+    /// Pixel format of the image.
+    pub pixel_format: PixelFormat,
+
+    /// Width of the image.
+    pub width: u32,
+
+    /// Height of the image.
+    pub height: u32,
+
+    /// Horizontal offset of the image.
+    pub x_offset: u32,
+
+    /// Vertical offset of the image.
+    pub y_offset: u32,
+}
+
+impl FrameInfo {
+    /// Bytes per pixel of the image.
+    #[must_use]
+    pub fn bytes_per_pixel(&self) -> usize {
+        let pixel_format: u32 = self.pixel_format.into();
+        ((pixel_format >> 16 & 0xff) / 8) as usize
+    }
+
+    /// Size of the image in bytes.
+    #[must_use]
+    pub fn image_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.bytes_per_pixel()
+    }
+}
+
+/// A source of images sent from an emulated device.
+///
+/// [`FrameSource::next_frame`] is called each time the device captures a frame. If the length of
+/// the returned image differs from [`FrameInfo::image_size`], the image is truncated or padded with
+/// zero.
+///
+/// Closures of `FnMut(&FrameInfo) -> Vec<u8>` also implement this trait.
+pub trait FrameSource: Send + 'static {
+    /// Returns an image of the frame.
+    fn next_frame(&mut self, info: &FrameInfo) -> Vec<u8>;
+}
+
+impl<F> FrameSource for F
+where
+    F: FnMut(&FrameInfo) -> Vec<u8> + Send + 'static,
+{
+    fn next_frame(&mut self, info: &FrameInfo) -> Vec<u8> {
+        self(info)
+    }
+}
+
+/// Produces images whose pixel values increase along the x-axis.
+///
+/// The value of a pixel at `x` is `(x + x_offset) as u8`. All bytes of a pixel have the same
+/// value.
+///
+/// This is the default frame source of an emulated device.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gradient;
+
+impl FrameSource for Gradient {
+    fn next_frame(&mut self, info: &FrameInfo) -> Vec<u8> {
+        fill_with(info, |x, _| (x + info.x_offset as usize) as u8)
+    }
+}
+
+/// Produces checkerboard images.
+///
+/// A cell which contains the origin is filled with 0xff, and its neighbors are filled with 0x00.
+/// Cells are placed relative to the origin of the sensor, so the pattern doesn't move when the
+/// offset is changed.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkerboard {
+    cell_size: u32,
+}
+
+impl Checkerboard {
+    /// Construct a checkerboard source with square cells of `cell_size` pixels.
+    ///
+    /// # Panics
+    /// If `cell_size` is zero, this method will panic.
+    #[must_use]
+    pub fn new(cell_size: u32) -> Self {
+        assert!(cell_size != 0);
+        Self { cell_size }
+    }
+}
+
+impl FrameSource for Checkerboard {
+    fn next_frame(&mut self, info: &FrameInfo) -> Vec<u8> {
+        let cell_size = self.cell_size as usize;
+        fill_with(info, |x, y| {
+            let cell_x = (x + info.x_offset as usize) / cell_size;
+            let cell_y = (y + info.y_offset as usize) / cell_size;
+            if (cell_x + cell_y) & 1 == 0 {
+                0xff
+            } else {
+                0x00
+            }
+        })
+    }
+}
+
+/// Produces images of a vertical bar moving along the x-axis.
+///
+/// The bar is filled with 0xff and the background is filled with 0x00. The left edge of the bar
+/// is placed at `(block_id * speed) % width` in the frame.
+#[derive(Debug, Clone, Copy)]
+pub struct MovingBar {
+    bar_width: u32,
+    speed: u32,
+}
+
+impl MovingBar {
+    /// Construct a moving bar source whose bar is `bar_width` pixels wide and moves `speed`
+    /// pixels per frame.
+    #[must_use]
+    pub fn new(bar_width: u32, speed: u32) -> Self {
+        Self { bar_width, speed }
+    }
+}
+
+impl FrameSource for MovingBar {
+    fn next_frame(&mut self, info: &FrameInfo) -> Vec<u8> {
+        let width = u64::from(info.width.max(1));
+        let start = (info.block_id * u64::from(self.speed) % width) as usize;
+        let end = start + self.bar_width as usize;
+        fill_with(info, |x, _| {
+            if (start..end).contains(&x) {
+                0xff
+            } else {
+                0x00
+            }
+        })
+    }
+}
+
+/// Wraps another frame source and stamps the block id to the produced images.
+///
+/// The first 8 bytes of the image are overwritten with the block id in little endian. If the
+/// image is shorter than 8 bytes, the block id is truncated.
+#[derive(Debug, Clone)]
+pub struct FrameCounter<S> {
+    inner: S,
+}
+
+impl<S> FrameCounter<S> {
+    /// Construct a frame counter source which stamps the block id to images produced by
+    /// `inner`.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: FrameSource> FrameSource for FrameCounter<S> {
+    fn next_frame(&mut self, info: &FrameInfo) -> Vec<u8> {
+        let mut image = self.inner.next_frame(info);
+        let stamp = info.block_id.to_le_bytes();
+        let len = stamp.len().min(image.len());
+        image[..len].copy_from_slice(&stamp[..len]);
+        image
+    }
+}
+
+/// Cycles through raw image files in a directory.
+///
+/// Files are sent in the lexicographical order of their names, and the first file is sent again
+/// after the last one. Each file must contain raw pixel data of the current image format of
+/// the device, otherwise the image is truncated or padded with zero.
+#[derive(Debug, Clone)]
+pub struct RawFiles {
+    images: Vec<Vec<u8>>,
+    next: usize,
+}
+
+impl RawFiles {
+    /// Load all files in `dir`. Sub directories are ignored.
+    ///
+    /// # Errors
+    /// If failed to read the directory or files in it, or there is no file in the directory,
+    /// then [`io::Error`] is returned.
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()?;
+        paths.retain(|path| path.is_file());
+        paths.sort();
+
+        if paths.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no file is found in the directory",
+            ));
+        }
+
+        let images = paths.iter().map(fs::read).collect::<io::Result<_>>()?;
+        Ok(Self { images, next: 0 })
+    }
+}
+
+impl FrameSource for RawFiles {
+    fn next_frame(&mut self, _: &FrameInfo) -> Vec<u8> {
+        let image = self.images[self.next].clone();
+        self.next = (self.next + 1) % self.images.len();
+        image
+    }
+}
+
+/// Returns an image whose pixels are filled with `f(x, y)`.
+fn fill_with(info: &FrameInfo, f: impl Fn(usize, usize) -> u8) -> Vec<u8> {
+    let width = info.width as usize;
+    let height = info.height as usize;
+    let bytes_per_pixel = info.bytes_per_pixel();
+    let mut image = Vec::with_capacity(info.image_size());
+    for y in 0..height {
+        for x in 0..width {
+            let value = f(x, y);
+            image.extend((0..bytes_per_pixel).map(|_| value));
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_info(block_id: u64) -> FrameInfo {
+        FrameInfo {
+            block_id,
+            timestamp: 0,
+            pixel_format: PixelFormat::Mono8,
+            width: 8,
+            height: 4,
+            x_offset: 2,
+            y_offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_gradient() {
+        let image = Gradient.next_frame(&frame_info(0));
+        assert_eq!(image.len(), 32);
+        assert_eq!(&image[..8], &[2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(&image[8..16], &[2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let image = Checkerboard::new(2).next_frame(&frame_info(0));
+        // The pattern is shifted by `x_offset`.
+        assert_eq!(&image[..8], &[0, 0, 0xff, 0xff, 0, 0, 0xff, 0xff]);
+        assert_eq!(&image[16..24], &[0xff, 0xff, 0, 0, 0xff, 0xff, 0, 0]);
+    }
+
+    #[test]
+    fn test_moving_bar() {
+        let mut source = MovingBar::new(2, 3);
+        let image = source.next_frame(&frame_info(1));
+        assert_eq!(&image[..8], &[0, 0, 0, 0xff, 0xff, 0, 0, 0]);
+        let image = source.next_frame(&frame_info(3));
+        assert_eq!(&image[..8], &[0, 0xff, 0xff, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_frame_counter() {
+        let image = FrameCounter::new(Gradient).next_frame(&frame_info(0x0102));
+        assert_eq!(&image[..8], &[2, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&image[8..16], &[2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_bytes_per_pixel() {
+        let mut info = frame_info(0);
+        info.pixel_format = PixelFormat::RGB8;
+        assert_eq!(info.bytes_per_pixel(), 3);
+        assert_eq!(info.image_size(), 96);
+        assert_eq!(Gradient.next_frame(&info).len(), 96);
+    }
+}
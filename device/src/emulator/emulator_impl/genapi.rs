@@ -3,9 +3,9 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use cameleon_impl::memory::{prelude::*, register_map};
-use const_format::formatcp;
+use const_format::{concatcp, formatcp};
 
-use super::memory::GENAPI_XML_ADDRESS;
+use super::memory::GENAPI_REG_ADDRESS;
 
 pub(super) const MODEL_NAME: &str = "CameleonU3VEmulator";
 pub(super) const VENDOR_NAME: &str = "CameleonProjectDevelopers";
@@ -22,11 +22,119 @@ pub(super) const TOOL_TIP: &str = "CameleonU3VEmulator";
 
 pub(super) const PORT_NAME: &str = "Device";
 
+/// Maximum width of images sent from the emulator.
+pub(super) const WIDTH_MAX: u32 = 1280;
+/// Maximum height of images sent from the emulator.
+pub(super) const HEIGHT_MAX: u32 = 960;
+/// Initial width of images sent from the emulator.
+const WIDTH: u32 = 640;
+/// Initial height of images sent from the emulator.
+const HEIGHT: u32 = 480;
+
+/// `PFNC` values of pixel formats supported by the emulator.
+pub(super) const MONO8: u32 = 0x0108_0001;
+pub(super) const MONO16: u32 = 0x0110_0007;
+pub(super) const RGB8: u32 = 0x0218_0014;
+
+/// Initial payload size of the emulator.
+pub(super) const PAYLOAD_SIZE: u64 = WIDTH as u64 * HEIGHT as u64 * bytes_per_pixel(MONO8) as u64;
+
+pub(super) const ACQUISITION_MODE_CONTINUOUS: u32 = 0;
+pub(super) const ACQUISITION_MODE_SINGLE_FRAME: u32 = 1;
+
+pub(super) const TRIGGER_MODE_OFF: u32 = 0;
+pub(super) const TRIGGER_MODE_ON: u32 = 1;
+
+/// `ChunkSelector` values of chunks supported by the emulator.
+pub(super) const CHUNK_SELECTOR_IMAGE: u32 = 0;
+pub(super) const CHUNK_SELECTOR_TIMESTAMP: u32 = 1;
+pub(super) const CHUNK_SELECTOR_FRAME_ID: u32 = 2;
+pub(super) const CHUNK_SELECTOR_EXPOSURE_TIME: u32 = 3;
+pub(super) const CHUNK_SELECTOR_GAIN: u32 = 4;
+
+/// Chunk ids of chunks supported by the emulator.
+pub(super) const CHUNK_ID_IMAGE: u32 = 0x4000_0001;
+pub(super) const CHUNK_ID_TIMESTAMP: u32 = 0x4000_0002;
+pub(super) const CHUNK_ID_FRAME_ID: u32 = 0x4000_0003;
+pub(super) const CHUNK_ID_EXPOSURE_TIME: u32 = 0x4000_0004;
+pub(super) const CHUNK_ID_GAIN: u32 = 0x4000_0005;
+
+/// Length of chunk data of each chunk except for the image chunk.
+pub(super) const CHUNK_DATA_LEN: u32 = 8;
+/// Length of chunk id and chunk length fields which follow chunk data.
+pub(super) const CHUNK_TRAILER_LEN: u32 = 8;
+
 const PRODUCT_GUID: &str = "eaabe337-2c3b-4e0b-b9b9-e67b347c4da8";
 const VERSION_GUID: &str = "0d29949b-5cd9-4f08-93fb-eea24950de3f";
 
-#[register_map(base=GENAPI_XML_ADDRESS, endianness=LE)]
+/// Return bytes per pixel of the pixel format.
+/// Bits per pixel are encoded in the third byte of `PFNC` value.
+pub(super) const fn bytes_per_pixel(pixel_format: u32) -> u32 {
+    ((pixel_format >> 16) & 0xff) / 8
+}
+
+#[register_map(base=GENAPI_REG_ADDRESS, endianness=LE)]
 pub(super) enum GenApiReg {
+    /// Transport layer parameters are locked when the register is set to 1.
+    #[register(len = 4, access = RW, ty = u32)]
+    TLParamsLocked,
+
+    /// Width of the image.
+    #[register(len = 4, access = RW, ty = u32)]
+    Width = WIDTH,
+
+    /// Height of the image.
+    #[register(len = 4, access = RW, ty = u32)]
+    Height = HEIGHT,
+
+    /// Maximum width of the image.
+    #[register(len = 4, access = RO, ty = u32)]
+    WidthMax = WIDTH_MAX,
+
+    /// Maximum height of the image.
+    #[register(len = 4, access = RO, ty = u32)]
+    HeightMax = HEIGHT_MAX,
+
+    /// Horizontal offset from the origin to the region of interest.
+    #[register(len = 4, access = RW, ty = u32)]
+    OffsetX,
+
+    /// Vertical offset from the origin to the region of interest.
+    #[register(len = 4, access = RW, ty = u32)]
+    OffsetY,
+
+    /// `PFNC` value of the pixel format.
+    #[register(len = 4, access = RW, ty = u32)]
+    PixelFormat = MONO8,
+
+    /// Size of a payload in bytes.
+    #[register(len = 4, access = RO, ty = u32)]
+    PayloadSize = PAYLOAD_SIZE as u32,
+
+    /// Exposure time in microseconds.
+    #[register(len = 8, access = RW, ty = f64)]
+    ExposureTime = 10000.0,
+
+    /// Gain in dB.
+    #[register(len = 8, access = RW, ty = f64)]
+    Gain = 0.0,
+
+    /// 0: Off, 1: On.
+    #[register(len = 4, access = RW, ty = u32)]
+    TriggerMode = TRIGGER_MODE_OFF,
+
+    /// Generate a software trigger when the register is set to 1.
+    #[register(len = 1, access = WO, ty = u8)]
+    TriggerSoftware,
+
+    /// 0: Continuous, 1: SingleFrame.
+    #[register(len = 4, access = RW, ty = u32)]
+    AcquisitionMode = ACQUISITION_MODE_CONTINUOUS,
+
+    /// Device temperature in degrees Celsius.
+    #[register(len = 8, access = RO, ty = f64)]
+    DeviceTemperature = 40.0,
+
     /// Start acquisition of images when the register is set to 1.
     #[register(len = 1, access = WO, ty = u8)]
     AcquisitionStart,
@@ -34,10 +142,85 @@ pub(super) enum GenApiReg {
     /// Stop the acquisition of images when the register is set to 1.
     #[register(len = 1, access = WO, ty = u8)]
     AcquisitionStop,
+
+    /// Frame rate in Hz.
+    #[register(len = 8, access = RW, ty = f64)]
+    AcquisitionFrameRate = 30.0,
+
+    /// Chunk data are appended to images when the register is set to 1.
+    #[register(len = 4, access = RW, ty = u32)]
+    ChunkModeActive,
+
+    /// Chunk which `ChunkEnable` controls.
+    #[register(len = 4, access = RW, ty = u32)]
+    ChunkSelector = CHUNK_SELECTOR_IMAGE,
+
+    /// The image chunk is always enabled when chunk mode is active.
+    #[register(len = 4, access = RO, ty = u32)]
+    ChunkEnableImage = 1,
+
+    /// Timestamp chunk is appended when the register is set to 1.
+    #[register(len = 4, access = RW, ty = u32)]
+    ChunkEnableTimestamp,
+
+    /// Frame id chunk is appended when the register is set to 1.
+    #[register(len = 4, access = RW, ty = u32)]
+    ChunkEnableFrameID,
+
+    /// Exposure time chunk is appended when the register is set to 1.
+    #[register(len = 4, access = RW, ty = u32)]
+    ChunkEnableExposureTime,
+
+    /// Gain chunk is appended when the register is set to 1.
+    #[register(len = 4, access = RW, ty = u32)]
+    ChunkEnableGain,
 }
 
-// TODO: Add node to this XML.
-pub(super) const GENAPI_XML: &str = formatcp!(
+/// Expands to an `IntReg` node description of the register.
+macro_rules! int_reg {
+    ($name:literal, $reg:path $(, $invalidator:literal)*) => {
+        formatcp!(
+            r#"
+    <IntReg Name="{name}" NameSpace="Custom">
+        <Address>{address}</Address>
+        <Length>{length}</Length>
+        <AccessMode>{access}</AccessMode>
+        <pPort>{PORT_NAME}</pPort>{invalidators}
+        <Sign>Unsigned</Sign>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+"#,
+            name = $name,
+            address = <$reg as Register>::ADDRESS,
+            length = <$reg as Register>::LENGTH,
+            access = <$reg as Register>::ACCESS_RIGHT.as_str(),
+            invalidators = concatcp!($("\n        <pInvalidator>", $invalidator, "</pInvalidator>",)*),
+        )
+    };
+}
+
+/// Expands to a `FloatReg` node description of the register.
+macro_rules! float_reg {
+    ($name:literal, $reg:path) => {
+        formatcp!(
+            r#"
+    <FloatReg Name="{name}" NameSpace="Custom">
+        <Address>{address}</Address>
+        <Length>{length}</Length>
+        <AccessMode>{access}</AccessMode>
+        <pPort>{PORT_NAME}</pPort>
+        <Endianess>LittleEndian</Endianess>
+    </FloatReg>
+"#,
+            name = $name,
+            address = <$reg as Register>::ADDRESS,
+            length = <$reg as Register>::LENGTH,
+            access = <$reg as Register>::ACCESS_RIGHT.as_str(),
+        )
+    };
+}
+
+const XML_HEADER: &str = formatcp!(
     r#"<?xml version="1.0" encoding="UTF-8"?>
 <RegisterDescription
 ModelName="{MODEL_NAME}"
@@ -59,20 +242,188 @@ xsi:schemaLocation="http://www.genicam.org/GenApi/Version_1_1 http://www.genicam
     <Category Name="Root" NameSpace="Standard">
         <Description>Provides the Root of the GenICam features tree.</Description>
         <Visibility>Beginner</Visibility>
+        <pFeature>DeviceControl</pFeature>
+        <pFeature>ImageFormatControl</pFeature>
         <pFeature>AcquisitionControl</pFeature>
+        <pFeature>AnalogControl</pFeature>
+        <pFeature>ChunkDataControl</pFeature>
+        <pFeature>TransportLayerControl</pFeature>
     </Category>
 
     <Port Name="{PORT_NAME}" NameSpace="Standard">
         <Description>The GenICam port through which the Interface module is accessed.</Description>
         <Visibility>Invisible</Visibility>
     </Port>
+"#
+);
+
+const DEVICE_CONTROL: &str = concatcp!(
+    r#"
+    <Category Name="DeviceControl" NameSpace="Standard">
+        <DisplayName>Device Control</DisplayName>
+        <pFeature>DeviceTemperature</pFeature>
+    </Category>
+
+    <Float Name="DeviceTemperature" NameSpace="Standard">
+        <ToolTip>Device temperature in degrees Celsius (C).</ToolTip>
+        <DisplayName>Device Temperature</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>DeviceTemperatureReg</pValue>
+        <Unit>C</Unit>
+    </Float>
+"#,
+    float_reg!("DeviceTemperatureReg", GenApiReg::DeviceTemperature),
+);
+
+const IMAGE_FORMAT_CONTROL: &str = concatcp!(
+    formatcp!(
+        r#"
+    <Category Name="ImageFormatControl" NameSpace="Standard">
+        <DisplayName>Image Format Control</DisplayName>
+        <pFeature>Width</pFeature>
+        <pFeature>Height</pFeature>
+        <pFeature>WidthMax</pFeature>
+        <pFeature>HeightMax</pFeature>
+        <pFeature>OffsetX</pFeature>
+        <pFeature>OffsetY</pFeature>
+        <pFeature>PixelFormat</pFeature>
+    </Category>
+
+    <Integer Name="Width" NameSpace="Standard">
+        <ToolTip>Width of the image provided by the device (in pixels).</ToolTip>
+        <DisplayName>Width</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pIsLocked>TLParamsLocked</pIsLocked>
+        <pValue>WidthReg</pValue>
+        <Min>1</Min>
+        <pMax>WidthMaxMinusOffsetX</pMax>
+    </Integer>
+
+    <IntSwissKnife Name="WidthMaxMinusOffsetX" NameSpace="Custom">
+        <Visibility>Invisible</Visibility>
+        <pVariable Name="WMAX">WidthMax</pVariable>
+        <pVariable Name="OX">OffsetX</pVariable>
+        <Formula>WMAX - OX</Formula>
+    </IntSwissKnife>
+
+    <Integer Name="Height" NameSpace="Standard">
+        <ToolTip>Height of the image provided by the device (in pixels).</ToolTip>
+        <DisplayName>Height</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pIsLocked>TLParamsLocked</pIsLocked>
+        <pValue>HeightReg</pValue>
+        <Min>1</Min>
+        <pMax>HeightMaxMinusOffsetY</pMax>
+    </Integer>
+
+    <IntSwissKnife Name="HeightMaxMinusOffsetY" NameSpace="Custom">
+        <Visibility>Invisible</Visibility>
+        <pVariable Name="HMAX">HeightMax</pVariable>
+        <pVariable Name="OY">OffsetY</pVariable>
+        <Formula>HMAX - OY</Formula>
+    </IntSwissKnife>
+
+    <Integer Name="WidthMax" NameSpace="Standard">
+        <ToolTip>Maximum width of the image (in pixels).</ToolTip>
+        <DisplayName>Width Max</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>WidthMaxReg</pValue>
+    </Integer>
 
+    <Integer Name="HeightMax" NameSpace="Standard">
+        <ToolTip>Maximum height of the image (in pixels).</ToolTip>
+        <DisplayName>Height Max</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>HeightMaxReg</pValue>
+    </Integer>
+
+    <Integer Name="OffsetX" NameSpace="Standard">
+        <ToolTip>Horizontal offset from the origin to the region of interest (in pixels).</ToolTip>
+        <DisplayName>Offset X</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pValue>OffsetXReg</pValue>
+        <Min>0</Min>
+        <pMax>WidthMaxMinusWidth</pMax>
+    </Integer>
+
+    <IntSwissKnife Name="WidthMaxMinusWidth" NameSpace="Custom">
+        <Visibility>Invisible</Visibility>
+        <pVariable Name="WMAX">WidthMax</pVariable>
+        <pVariable Name="W">Width</pVariable>
+        <Formula>WMAX - W</Formula>
+    </IntSwissKnife>
+
+    <Integer Name="OffsetY" NameSpace="Standard">
+        <ToolTip>Vertical offset from the origin to the region of interest (in pixels).</ToolTip>
+        <DisplayName>Offset Y</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pValue>OffsetYReg</pValue>
+        <Min>0</Min>
+        <pMax>HeightMaxMinusHeight</pMax>
+    </Integer>
+
+    <IntSwissKnife Name="HeightMaxMinusHeight" NameSpace="Custom">
+        <Visibility>Invisible</Visibility>
+        <pVariable Name="HMAX">HeightMax</pVariable>
+        <pVariable Name="H">Height</pVariable>
+        <Formula>HMAX - H</Formula>
+    </IntSwissKnife>
+
+    <Enumeration Name="PixelFormat" NameSpace="Standard">
+        <ToolTip>Format of the pixels provided by the device.</ToolTip>
+        <DisplayName>Pixel Format</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pIsLocked>TLParamsLocked</pIsLocked>
+        <EnumEntry Name="Mono8" NameSpace="Standard">
+            <Value>{MONO8}</Value>
+        </EnumEntry>
+        <EnumEntry Name="Mono16" NameSpace="Standard">
+            <Value>{MONO16}</Value>
+        </EnumEntry>
+        <EnumEntry Name="RGB8" NameSpace="Standard">
+            <Value>{RGB8}</Value>
+        </EnumEntry>
+        <pValue>PixelFormatReg</pValue>
+    </Enumeration>
+"#
+    ),
+    int_reg!("WidthReg", GenApiReg::Width),
+    int_reg!("HeightReg", GenApiReg::Height),
+    int_reg!("WidthMaxReg", GenApiReg::WidthMax),
+    int_reg!("HeightMaxReg", GenApiReg::HeightMax),
+    int_reg!("OffsetXReg", GenApiReg::OffsetX),
+    int_reg!("OffsetYReg", GenApiReg::OffsetY),
+    int_reg!("PixelFormatReg", GenApiReg::PixelFormat),
+);
+
+const ACQUISITION_CONTROL: &str = concatcp!(
+    formatcp!(
+        r#"
     <Category Name="AcquisitionControl" NameSpace="Standard">
         <DisplayName>Acquisition Control</DisplayName>
+        <pFeature>AcquisitionMode</pFeature>
         <pFeature>AcquisitionStart</pFeature>
         <pFeature>AcquisitionStop</pFeature>
+        <pFeature>AcquisitionFrameRate</pFeature>
+        <pFeature>TriggerMode</pFeature>
+        <pFeature>TriggerSoftware</pFeature>
+        <pFeature>ExposureTime</pFeature>
+        <pFeature>PayloadSize</pFeature>
     </Category>
 
+    <Enumeration Name="AcquisitionMode" NameSpace="Standard">
+        <ToolTip>Sets the acquisition mode of the device.</ToolTip>
+        <DisplayName>Acquisition Mode</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <EnumEntry Name="Continuous" NameSpace="Standard">
+            <Value>{ACQUISITION_MODE_CONTINUOUS}</Value>
+        </EnumEntry>
+        <EnumEntry Name="SingleFrame" NameSpace="Standard">
+            <Value>{ACQUISITION_MODE_SINGLE_FRAME}</Value>
+        </EnumEntry>
+        <pValue>AcquisitionModeReg</pValue>
+    </Enumeration>
+
     <Command Name="AcquisitionStart" NameSpace="Standard">
         <ToolTip>Starts the acquisition of images.</ToolTip>
         <Description>This command starts the acquisition of images.</Description>
@@ -81,16 +432,7 @@ xsi:schemaLocation="http://www.genicam.org/GenApi/Version_1_1 http://www.genicam
         <CommandValue>1</CommandValue>
     </Command>
 
-    <IntReg Name="AcquisitionStartReg" NameSpace="Custom">
-        <Address>{acquisition_start_addr}</Address>
-        <Length>{acquisition_start_len}</Length>
-        <AccessMode>{acquisition_start_access}</AccessMode>
-        <pPort>{PORT_NAME}</pPort>
-        <Sign>Unsigned</Sign>
-        <Endianess>LittleEndian</Endianess>
-    </IntReg>
-
-   <Command Name="AcquisitionStop" NameSpace="Standard">
+    <Command Name="AcquisitionStop" NameSpace="Standard">
         <ToolTip>Stops the acquisition of images.</ToolTip>
         <Description>This command stop the acquisition of images.</Description>
         <DisplayName>Acquisition Stop</DisplayName>
@@ -98,20 +440,281 @@ xsi:schemaLocation="http://www.genicam.org/GenApi/Version_1_1 http://www.genicam
         <CommandValue>1</CommandValue>
     </Command>
 
-    <IntReg Name="AcquisitionStopReg" NameSpace="Custom">
-        <Address>{acquisition_stop_addr}</Address>
-        <Length>{acquisition_stop_len}</Length>
-        <AccessMode>{acquisition_stop_access}</AccessMode>
+    <Float Name="AcquisitionFrameRate" NameSpace="Standard">
+        <ToolTip>Controls the acquisition rate (in Hertz) at which the frames are captured.</ToolTip>
+        <DisplayName>Acquisition Frame Rate</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pValue>AcquisitionFrameRateReg</pValue>
+        <Min>1</Min>
+        <Max>1000</Max>
+        <Unit>Hz</Unit>
+    </Float>
+
+    <Enumeration Name="TriggerMode" NameSpace="Standard">
+        <ToolTip>Controls if the frame start trigger is active.</ToolTip>
+        <DisplayName>Trigger Mode</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <EnumEntry Name="Off" NameSpace="Standard">
+            <Value>{TRIGGER_MODE_OFF}</Value>
+        </EnumEntry>
+        <EnumEntry Name="On" NameSpace="Standard">
+            <Value>{TRIGGER_MODE_ON}</Value>
+        </EnumEntry>
+        <pValue>TriggerModeReg</pValue>
+    </Enumeration>
+
+    <Command Name="TriggerSoftware" NameSpace="Standard">
+        <ToolTip>Generates an internal trigger.</ToolTip>
+        <DisplayName>Trigger Software</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pValue>TriggerSoftwareReg</pValue>
+        <CommandValue>1</CommandValue>
+    </Command>
+
+    <Float Name="ExposureTime" NameSpace="Standard">
+        <ToolTip>Exposure time in microseconds.</ToolTip>
+        <DisplayName>Exposure Time</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pValue>ExposureTimeReg</pValue>
+        <Min>10</Min>
+        <Max>1000000</Max>
+        <Unit>us</Unit>
+    </Float>
+
+    <Integer Name="PayloadSize" NameSpace="Standard">
+        <ToolTip>Size of the payload in bytes.</ToolTip>
+        <DisplayName>Payload Size</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>PayloadSizeReg</pValue>
+    </Integer>
+"#
+    ),
+    int_reg!("AcquisitionModeReg", GenApiReg::AcquisitionMode),
+    int_reg!("AcquisitionStartReg", GenApiReg::AcquisitionStart),
+    int_reg!("AcquisitionStopReg", GenApiReg::AcquisitionStop),
+    float_reg!("AcquisitionFrameRateReg", GenApiReg::AcquisitionFrameRate),
+    int_reg!("TriggerModeReg", GenApiReg::TriggerMode),
+    int_reg!("TriggerSoftwareReg", GenApiReg::TriggerSoftware),
+    float_reg!("ExposureTimeReg", GenApiReg::ExposureTime),
+    int_reg!(
+        "PayloadSizeReg",
+        GenApiReg::PayloadSize,
+        "WidthReg",
+        "HeightReg",
+        "PixelFormatReg",
+        "ChunkModeActiveReg",
+        "ChunkEnableReg"
+    ),
+);
+
+const ANALOG_CONTROL: &str = concatcp!(
+    r#"
+    <Category Name="AnalogControl" NameSpace="Standard">
+        <DisplayName>Analog Control</DisplayName>
+        <pFeature>Gain</pFeature>
+    </Category>
+
+    <Float Name="Gain" NameSpace="Standard">
+        <ToolTip>Gain in dB.</ToolTip>
+        <DisplayName>Gain</DisplayName>
+        <Visibility>Beginner</Visibility>
+        <pValue>GainReg</pValue>
+        <Min>0</Min>
+        <Max>24</Max>
+        <Unit>dB</Unit>
+    </Float>
+"#,
+    float_reg!("GainReg", GenApiReg::Gain),
+);
+
+/// Expands to a `Port` node description of the chunk.
+macro_rules! chunk_port {
+    ($name:literal, $chunk_id:expr) => {
+        formatcp!(
+            r#"
+    <Port Name="{name}" NameSpace="Custom">
+        <Visibility>Invisible</Visibility>
+        <ChunkID>{chunk_id:X}</ChunkID>
+    </Port>
+"#,
+            name = $name,
+            chunk_id = $chunk_id,
+        )
+    };
+}
+
+/// Expands to a register node description of the chunk data which is placed at the beginning of
+/// the chunk port.
+macro_rules! chunk_reg {
+    ($kind:literal, $name:literal, $port:literal $(, $extra:literal)?) => {
+        formatcp!(
+            r#"
+    <{kind} Name="{name}" NameSpace="Custom">
+        <Address>0</Address>
+        <Length>{CHUNK_DATA_LEN}</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>{port}</pPort>
+        <Cachable>NoCache</Cachable>{extra}
+        <Endianess>LittleEndian</Endianess>
+    </{kind}>
+"#,
+            kind = $kind,
+            name = $name,
+            port = $port,
+            extra = concatcp!($("\n        ", $extra,)?),
+        )
+    };
+}
+
+const CHUNK_DATA_CONTROL: &str = concatcp!(
+    formatcp!(
+        r#"
+    <Category Name="ChunkDataControl" NameSpace="Standard">
+        <DisplayName>Chunk Data Control</DisplayName>
+        <pFeature>ChunkModeActive</pFeature>
+        <pFeature>ChunkSelector</pFeature>
+        <pFeature>ChunkEnable</pFeature>
+        <pFeature>ChunkTimestamp</pFeature>
+        <pFeature>ChunkFrameID</pFeature>
+        <pFeature>ChunkExposureTime</pFeature>
+        <pFeature>ChunkGain</pFeature>
+    </Category>
+
+    <Boolean Name="ChunkModeActive" NameSpace="Standard">
+        <ToolTip>Activates the inclusion of Chunk data in the payload of the image.</ToolTip>
+        <DisplayName>Chunk Mode Active</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pIsLocked>TLParamsLocked</pIsLocked>
+        <pValue>ChunkModeActiveReg</pValue>
+        <OnValue>1</OnValue>
+        <OffValue>0</OffValue>
+    </Boolean>
+
+    <Enumeration Name="ChunkSelector" NameSpace="Standard">
+        <ToolTip>Selects which Chunk to enable or control.</ToolTip>
+        <DisplayName>Chunk Selector</DisplayName>
+        <Visibility>Expert</Visibility>
+        <EnumEntry Name="Image" NameSpace="Standard">
+            <Value>{CHUNK_SELECTOR_IMAGE}</Value>
+        </EnumEntry>
+        <EnumEntry Name="Timestamp" NameSpace="Standard">
+            <Value>{CHUNK_SELECTOR_TIMESTAMP}</Value>
+        </EnumEntry>
+        <EnumEntry Name="FrameID" NameSpace="Standard">
+            <Value>{CHUNK_SELECTOR_FRAME_ID}</Value>
+        </EnumEntry>
+        <EnumEntry Name="ExposureTime" NameSpace="Standard">
+            <Value>{CHUNK_SELECTOR_EXPOSURE_TIME}</Value>
+        </EnumEntry>
+        <EnumEntry Name="Gain" NameSpace="Standard">
+            <Value>{CHUNK_SELECTOR_GAIN}</Value>
+        </EnumEntry>
+        <pValue>ChunkSelectorReg</pValue>
+        <pSelected>ChunkEnable</pSelected>
+    </Enumeration>
+
+    <Boolean Name="ChunkEnable" NameSpace="Standard">
+        <ToolTip>Enables the inclusion of the selected Chunk data in the payload of the image.</ToolTip>
+        <DisplayName>Chunk Enable</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pIsLocked>TLParamsLocked</pIsLocked>
+        <pValue>ChunkEnableReg</pValue>
+        <OnValue>1</OnValue>
+        <OffValue>0</OffValue>
+    </Boolean>
+
+    <IntReg Name="ChunkEnableReg" NameSpace="Custom">
+        <Address>{chunk_enable_address}</Address>
+        <pIndex Offset="{chunk_enable_len}">ChunkSelectorReg</pIndex>
+        <Length>{chunk_enable_len}</Length>
+        <AccessMode>RW</AccessMode>
         <pPort>{PORT_NAME}</pPort>
+        <pInvalidator>ChunkSelectorReg</pInvalidator>
         <Sign>Unsigned</Sign>
         <Endianess>LittleEndian</Endianess>
     </IntReg>
 
-</RegisterDescription>"#,
-    acquisition_start_addr = GenApiReg::AcquisitionStart::ADDRESS,
-    acquisition_start_len = GenApiReg::AcquisitionStart::LENGTH,
-    acquisition_start_access = GenApiReg::AcquisitionStart::ACCESS_RIGHT.as_str(),
-    acquisition_stop_addr = GenApiReg::AcquisitionStop::ADDRESS,
-    acquisition_stop_len = GenApiReg::AcquisitionStop::LENGTH,
-    acquisition_stop_access = GenApiReg::AcquisitionStop::ACCESS_RIGHT.as_str(),
+    <Integer Name="ChunkTimestamp" NameSpace="Standard">
+        <ToolTip>Returns the Timestamp of the image included in the payload.</ToolTip>
+        <DisplayName>Chunk Timestamp</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>ChunkTimestampReg</pValue>
+    </Integer>
+
+    <Integer Name="ChunkFrameID" NameSpace="Standard">
+        <ToolTip>Returns the unique Identifier of the frame included in the payload.</ToolTip>
+        <DisplayName>Chunk Frame ID</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>ChunkFrameIDReg</pValue>
+    </Integer>
+
+    <Float Name="ChunkExposureTime" NameSpace="Standard">
+        <ToolTip>Returns the exposure time used to capture the image.</ToolTip>
+        <DisplayName>Chunk Exposure Time</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>ChunkExposureTimeReg</pValue>
+        <Unit>us</Unit>
+    </Float>
+
+    <Float Name="ChunkGain" NameSpace="Standard">
+        <ToolTip>Returns the gain used to capture the image.</ToolTip>
+        <DisplayName>Chunk Gain</DisplayName>
+        <Visibility>Expert</Visibility>
+        <pValue>ChunkGainReg</pValue>
+        <Unit>dB</Unit>
+    </Float>
+"#,
+        chunk_enable_address = <GenApiReg::ChunkEnableImage as Register>::ADDRESS,
+        chunk_enable_len = <GenApiReg::ChunkEnableImage as Register>::LENGTH,
+    ),
+    int_reg!("ChunkModeActiveReg", GenApiReg::ChunkModeActive),
+    int_reg!("ChunkSelectorReg", GenApiReg::ChunkSelector),
+    chunk_port!("ChunkTimestampPort", CHUNK_ID_TIMESTAMP),
+    chunk_port!("ChunkFrameIDPort", CHUNK_ID_FRAME_ID),
+    chunk_port!("ChunkExposureTimePort", CHUNK_ID_EXPOSURE_TIME),
+    chunk_port!("ChunkGainPort", CHUNK_ID_GAIN),
+    chunk_reg!(
+        "IntReg",
+        "ChunkTimestampReg",
+        "ChunkTimestampPort",
+        "<Sign>Unsigned</Sign>"
+    ),
+    chunk_reg!(
+        "IntReg",
+        "ChunkFrameIDReg",
+        "ChunkFrameIDPort",
+        "<Sign>Unsigned</Sign>"
+    ),
+    chunk_reg!("FloatReg", "ChunkExposureTimeReg", "ChunkExposureTimePort"),
+    chunk_reg!("FloatReg", "ChunkGainReg", "ChunkGainPort"),
+);
+
+const TRANSPORT_LAYER_CONTROL: &str = concatcp!(
+    r#"
+    <Category Name="TransportLayerControl" NameSpace="Standard">
+        <DisplayName>Transport Layer Control</DisplayName>
+        <pFeature>TLParamsLocked</pFeature>
+    </Category>
+
+    <Integer Name="TLParamsLocked" NameSpace="Standard">
+        <ToolTip>Used by the Transport Layer to prevent critical features from changing during acquisition.</ToolTip>
+        <DisplayName>TL Params Locked</DisplayName>
+        <Visibility>Invisible</Visibility>
+        <pValue>TLParamsLockedReg</pValue>
+        <Min>0</Min>
+        <Max>1</Max>
+    </Integer>
+"#,
+    int_reg!("TLParamsLockedReg", GenApiReg::TLParamsLocked),
+);
+
+pub(super) const GENAPI_XML: &str = concatcp!(
+    XML_HEADER,
+    DEVICE_CONTROL,
+    IMAGE_FORMAT_CONTROL,
+    ACQUISITION_CONTROL,
+    ANALOG_CONTROL,
+    CHUNK_DATA_CONTROL,
+    TRANSPORT_LAYER_CONTROL,
+    "\n</RegisterDescription>"
 );
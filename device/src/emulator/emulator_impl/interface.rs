@@ -13,11 +13,16 @@ use async_std::{
 use futures::{channel::oneshot, select, FutureExt};
 
 use super::{
+    bandwidth::Bandwidth,
+    clock::Timestamp,
     control_module::ControlModule,
-    device::Timestamp,
+    event::Event,
     event_module::EventModule,
     fake_protocol::{FakeAckKind, FakeAckPacket, FakeReqKind, FakeReqPacket, IfaceKind},
+    fault::FaultInjector,
+    frame_source::FrameSource,
     memory::Memory,
+    register_image::ExternalMemory,
     shared_queue::SharedQueue,
     signal::{ControlSignal, EventSignal, InterfaceSignal, StreamSignal},
     stream_module::StreamModule,
@@ -26,7 +31,11 @@ use super::{
 pub(super) struct Interface {
     iface_state: IfaceState,
     memory: Arc<Mutex<Memory>>,
+    external_memory: Option<Arc<Mutex<ExternalMemory>>>,
     timestamp: Timestamp,
+    frame_source: Arc<Mutex<Box<dyn FrameSource>>>,
+    faults: FaultInjector,
+    bandwidth: Option<Bandwidth>,
 
     ctrl_queue: SharedQueue<Vec<u8>>,
     event_queue: SharedQueue<Vec<u8>>,
@@ -37,11 +46,22 @@ const SHARED_QUEUE_SIZE: usize = 32;
 const CHANNEL_CAPACITY: usize = 128;
 
 impl Interface {
-    pub(super) fn new(memory: Arc<Mutex<Memory>>, timestamp: Timestamp) -> Self {
+    pub(super) fn new(
+        memory: Arc<Mutex<Memory>>,
+        external_memory: Option<Arc<Mutex<ExternalMemory>>>,
+        timestamp: Timestamp,
+        frame_source: Arc<Mutex<Box<dyn FrameSource>>>,
+        faults: FaultInjector,
+        bandwidth: Option<Bandwidth>,
+    ) -> Self {
         Self {
             iface_state: IfaceState::new(),
             memory,
+            external_memory,
             timestamp,
+            frame_source,
+            faults,
+            bandwidth,
 
             ctrl_queue: SharedQueue::new(SHARED_QUEUE_SIZE),
             event_queue: SharedQueue::new(SHARED_QUEUE_SIZE),
@@ -55,6 +75,7 @@ impl Interface {
         self,
         fake_ack_tx: Sender<FakeAckPacket>,
         fake_req_rx: Receiver<FakeReqPacket>,
+        event_rx: Receiver<Vec<Event>>,
         shutdown: oneshot::Receiver<()>,
         _completed: oneshot::Sender<()>,
     ) {
@@ -72,6 +93,7 @@ impl Interface {
 
         let mut signal_rx = signal_rx.fuse();
         let mut fake_req_rx = fake_req_rx.fuse();
+        let mut event_rx = event_rx.fuse();
         let mut shutdown = shutdown.fuse();
 
         loop {
@@ -85,6 +107,15 @@ impl Interface {
                     }
                 },
 
+                events = event_rx.next().fuse() => {
+                    if let Some(events) = events {
+                        signal_tx.send_event(EventSignal::EventData(events));
+                    } else {
+                        // The device is dropped.
+                        break
+                    }
+                },
+
                 signal = signal_rx.next().fuse() => {
                     if let Some(signal) = signal {
                         self.handle_signal(signal, &signal_tx).await
@@ -116,8 +147,10 @@ impl Interface {
         let control_module = ControlModule::new(
             self.iface_state.clone(),
             self.memory.clone(),
+            self.external_memory.clone(),
             self.timestamp.clone(),
             self.ctrl_queue.clone(),
+            self.faults.clone(),
         );
         task::spawn(control_module.run(signal_tx, ctrl_signal_rx));
 
@@ -128,7 +161,11 @@ impl Interface {
         let (event_signal_tx, event_signal_rx) = channel::bounded(CHANNEL_CAPACITY);
 
         // Construct and spawn control module.
-        let event_module = EventModule::new(self.event_queue.clone(), 0);
+        let event_module = EventModule::new(
+            self.event_queue.clone(),
+            self.memory.clone(),
+            self.timestamp.clone(),
+        );
         task::spawn(event_module.run(signal_tx, event_signal_rx));
 
         event_signal_tx
@@ -138,7 +175,14 @@ impl Interface {
         let (stream_signal_tx, stream_signal_rx) = channel::bounded(CHANNEL_CAPACITY);
 
         // Construct and spawn control module.
-        let stream_module = StreamModule::new(self.timestamp.clone(), self.stream_queue.clone());
+        let stream_module = StreamModule::new(
+            self.memory.clone(),
+            self.timestamp.clone(),
+            self.stream_queue.clone(),
+            self.frame_source.clone(),
+            self.faults.clone(),
+            self.bandwidth,
+        );
         task::spawn(stream_module.run(signal_tx, stream_signal_rx));
 
         stream_signal_tx
@@ -159,14 +203,14 @@ impl Interface {
             self.iface_state
                 .set_state(iface, IfaceStateKind::Ready)
                 .await;
-            send_ack(&ack_tx, iface, FakeAckKind::ClearHaltAck);
+            send_ack(ack_tx, iface, FakeAckKind::ClearHaltAck);
             return;
         }
 
         // Handle set halt request.
         if req_kind.is_set_halt() {
             self.set_halt(iface, signal_tx).await;
-            send_ack(&ack_tx, iface, FakeAckKind::SetHaltAck);
+            send_ack(ack_tx, iface, FakeAckKind::SetHaltAck);
             return;
         }
 
@@ -189,12 +233,12 @@ impl Interface {
                     Some(data) => FakeAckKind::RecvAck(data),
                     None => FakeAckKind::RecvNak,
                 };
-                send_ack(&ack_tx, iface, ack_kind);
+                send_ack(ack_tx, iface, ack_kind);
             }
 
             (IfaceKind::Control, FakeReqKind::Send(data)) => {
                 signal_tx.send_ctrl(ControlSignal::ReceiveData(data));
-                send_ack(&ack_tx, iface, FakeAckKind::SendAck);
+                send_ack(ack_tx, iface, FakeAckKind::SendAck);
             }
 
             (iface, req) => {
@@ -203,7 +247,7 @@ impl Interface {
                     iface,
                     req
                 );
-                send_ack(&ack_tx, iface, FakeAckKind::BrokenReq);
+                send_ack(ack_tx, iface, FakeAckKind::BrokenReq);
             }
         };
     }
@@ -4,14 +4,17 @@
 
 use cameleon_impl::memory::{memory, register_map, Register};
 
-use super::genapi;
+use super::genapi::{self, GenApiReg};
 
 const ABRM_ADDRESS: usize = 0;
 const SBRM_ADDRESS: usize = 0xffff;
 const SIRM_ADDRESS: usize = SBRM::base() + SBRM::size();
-const MANIFEST_TABLE_ADDRESS: usize = SIRM::base() + SIRM::size();
-pub(super) const GENAPI_XML_ADDRESS: usize = ManifestTable::base() + ManifestTable::size();
+const EIRM_ADDRESS: usize = SIRM::base() + SIRM::size();
+const MANIFEST_TABLE_ADDRESS: usize = EIRM::base() + EIRM::size();
+pub(super) const GENAPI_REG_ADDRESS: usize = ManifestTable::base() + ManifestTable::size();
+const GENAPI_XML_ADDRESS: usize = GenApiReg::base() + GenApiReg::size();
 const GENAPI_XML_LENGTH: usize = genapi::GENAPI_XML.len();
+pub(super) const MEMORY_END: usize = GENAPI_XML_ADDRESS + GENAPI_XML_LENGTH;
 
 /// Offset | Value | Description.
 ///      0 |     1 | User Defined Name is supported.
@@ -23,13 +26,13 @@ const GENAPI_XML_LENGTH: usize = genapi::GENAPI_XML.len();
 ///      9 |     1 | SBRM is supported.
 ///     10 |     1 | Endianness Register is supported.
 ///     11 |     1 | Written Length Field is supported.
-///     12 |     0 | Multi Event is currently NOT supported.
-///     13 |     0 | Stacked Commands is NOT supported.
+///     12 |     1 | Multi Event is supported.
+///     13 |     1 | Stacked Commands is supported.
 ///     14 |     1 | Device Software Interface Version is supported.
 ///  15-63 |     0 | Reserved. All remained bits are set to 0.
 const DEVICE_CAPABILITY: &[u8] = &[
     0b0000_1001,
-    0b0100_1111,
+    0b0111_1111,
     0b0000_0000,
     0b0000_0000,
     0b0000_0000,
@@ -74,7 +77,9 @@ pub(super) struct Memory {
     abrm: ABRM,
     sbrm: SBRM,
     sirm: SIRM,
+    eirm: EIRM,
     manifest_table: ManifestTable,
+    genapi_reg: GenApiReg,
     genapi_xml: GenApiXml,
 }
 
@@ -180,10 +185,10 @@ pub(super) enum SBRM {
     SirmLength = SIRM::size() as u32,
 
     #[register(len = 8, access = RO, ty = u64)]
-    EirmAddress, // TODO: Filled after SIRM register map is implemented.
+    EirmAddress = EIRM_ADDRESS as u64,
 
     #[register(len = 4, access = RO, ty = u32)]
-    EirmLength, // TODO: Filled after EIRM register map is implmeneted.
+    EirmLength = EIRM::size() as u32,
 
     #[register(len = 8, access = NA, ty = u64)]
     Iidc2Address,
@@ -206,7 +211,7 @@ pub(super) enum SIRM {
     Control = 0,
 
     #[register(len = 8, access = RO, ty = u64)]
-    RequiredPayloadSize = 15_151_104,
+    RequiredPayloadSize = genapi::PAYLOAD_SIZE,
 
     #[register(len = 4, access = RO, ty = u32)]
     RequiredLeaderSize = 1024,
@@ -233,6 +238,25 @@ pub(super) enum SIRM {
     MaximumTrailerSize = 0,
 }
 
+/// Event id of the event sent when 1 is written to `EIRM::EventTestControl`.
+pub(super) const EVENT_TEST_ID: u16 = 0x4FFF;
+
+/// Minimum value of `EIRM::MaximumEventTransferLength`, which is the length of an event packet
+/// without event data.
+pub(super) const MINIMUM_EVENT_TRANSFER_LENGTH: u32 = 24;
+
+#[register_map(base = EIRM_ADDRESS, endianness = LE)]
+pub(super) enum EIRM {
+    #[register(len = 4, access = RW, ty = u32)]
+    Control = 0,
+
+    #[register(len = 4, access = RW, ty = u32)]
+    MaximumEventTransferLength = 1024,
+
+    #[register(len = 4, access = RW, ty = u32)]
+    EventTestControl = 0,
+}
+
 const MANIFEST_ENTRY0_BF_OFFSET: usize = (ManifestTable::GenICamFileVersionMajor::ADDRESS
     + ManifestTable::GenICamFileVersionMajor::LENGTH)
     - MANIFEST_TABLE_ADDRESS;
@@ -271,7 +295,7 @@ pub(super) enum ManifestTable {
     FileSize = GENAPI_XML_LENGTH as u64,
 
     #[register(len = 20, access = RO, ty = Bytes)]
-    Sha1Hash, // Hash is not available.
+    Sha1Hash, // Filled by `EmulatorBuilder::build`.
 
     #[register(len = 20, access = NA, ty = Bytes)]
     _Reserved,
@@ -5,12 +5,16 @@
 use async_std::channel::{self, Receiver, Sender};
 use futures::channel::oneshot;
 
-use cameleon_impl::memory::{prelude::*, MemoryObserver};
+use cameleon_impl::memory::{prelude::*, AccessRight, MemoryObserver};
 
 use super::{
     control_module::Worker,
     control_protocol::{ack, cmd},
-    memory::{Memory, ABRM, SIRM, SIRM_ALIGNMENT},
+    event::Event,
+    genapi::{self, GenApiReg},
+    memory::{
+        Memory, ABRM, EIRM, EVENT_TEST_ID, MINIMUM_EVENT_TRANSFER_LENGTH, SIRM, SIRM_ALIGNMENT,
+    },
     signal::{EventSignal, StreamSignal},
 };
 
@@ -103,16 +107,8 @@ impl TimestampLatchHandler {
         }
 
         // Write current time stamp to `TimeStamp` register.
-        let timestamp_ns = worker.timestamp.as_nanos().await;
-        write_memory::<ABRM::Timestamp>(timestamp_ns, &mut memory, scd_kind)?;
-
-        drop(memory);
-
-        // Send signal to [`super::event_module::EventModule`] to notify `TimeStamp` register is updated.
-        let signal = EventSignal::UpdateTimestamp(timestamp_ns);
-        worker.try_send_signal(signal);
-
-        Ok(())
+        let timestamp_ns = worker.timestamp.as_nanos();
+        write_memory::<ABRM::Timestamp>(timestamp_ns, &mut memory, scd_kind)
     }
 }
 
@@ -237,6 +233,402 @@ impl SiControlHandler {
     }
 }
 
+define_handler!(EiControlHandler, EIRM::Control, MemoryEvent::EiControl);
+impl EiControlHandler {
+    /// Handle `MemoryEvent::EiControl`.
+    ///
+    /// `EIRM::MaximumEventTransferLength` becomes read only while the event interface is enabled.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let value = Self::read(&memory, scd_kind)?;
+
+        if value == 1 {
+            memory.set_access_right::<EIRM::MaximumEventTransferLength>(AccessRight::RO);
+            worker.try_send_signal(EventSignal::Enable);
+            Ok(())
+        } else if value == 0 {
+            memory.set_access_right::<EIRM::MaximumEventTransferLength>(AccessRight::RW);
+            drop(memory);
+            let (completed_tx, completed_rx) = oneshot::channel();
+            worker.try_send_signal(EventSignal::Disable(completed_tx));
+            completed_rx.await.ok();
+            Ok(())
+        } else {
+            Self::write(0, &mut memory, scd_kind)?;
+            memory.set_access_right::<EIRM::MaximumEventTransferLength>(AccessRight::RW);
+            Err(ack::ErrorAck::new(
+                ack::GenCpStatus::InvalidParameter,
+                scd_kind,
+            ))
+        }
+    }
+}
+
+define_handler!(
+    MaximumEventTransferLengthHandler,
+    EIRM::MaximumEventTransferLength,
+    MemoryEvent::MaximumEventTransferLength
+);
+impl MaximumEventTransferLengthHandler {
+    /// Handle `MemoryEvent::MaximumEventTransferLength`.
+    ///
+    /// Writes are rejected while the event interface is enabled because the register is read only
+    /// then, see [`EiControlHandler`].
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<EIRM::MaximumEventTransferLength>(
+            MINIMUM_EVENT_TRANSFER_LENGTH,
+            u32::MAX,
+            &mut memory,
+            scd_kind,
+        )
+    }
+}
+
+define_handler!(
+    EventTestControlHandler,
+    EIRM::EventTestControl,
+    MemoryEvent::EventTestControl
+);
+impl EventTestControlHandler {
+    /// Handle `MemoryEvent::EventTestControl`.
+    ///
+    /// If 1 is written to `EventTestControl`, a test event is sent to the host and the register is
+    /// cleared.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        match Self::read(&memory, scd_kind)? {
+            // The register has been cleared by the handler itself.
+            0 => Ok(()),
+            1 => {
+                Self::write(0, &mut memory, scd_kind)?;
+                drop(memory);
+                let signal = EventSignal::EventData(vec![Event::new(EVENT_TEST_ID)]);
+                worker.try_send_signal(signal);
+                Ok(())
+            }
+            _ => {
+                Self::write(0, &mut memory, scd_kind)?;
+                Err(ack::ErrorAck::new(
+                    ack::GenCpStatus::InvalidParameter,
+                    scd_kind,
+                ))
+            }
+        }
+    }
+}
+
+define_handler!(
+    AcquisitionStartHandler,
+    GenApiReg::AcquisitionStart,
+    MemoryEvent::AcquisitionStart
+);
+impl AcquisitionStartHandler {
+    /// Handle `MemoryEvent::AcquisitionStart`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let value = Self::read(&*worker.memory.lock().await, scd_kind)?;
+        if value != 1 {
+            return Err(ack::ErrorAck::new(ack::GenCpStatus::GenericError, scd_kind));
+        }
+
+        worker.try_send_signal(StreamSignal::StartAcquisition);
+        Ok(())
+    }
+}
+
+define_handler!(
+    AcquisitionStopHandler,
+    GenApiReg::AcquisitionStop,
+    MemoryEvent::AcquisitionStop
+);
+impl AcquisitionStopHandler {
+    /// Handle `MemoryEvent::AcquisitionStop`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let value = Self::read(&*worker.memory.lock().await, scd_kind)?;
+        if value != 1 {
+            return Err(ack::ErrorAck::new(ack::GenCpStatus::GenericError, scd_kind));
+        }
+
+        worker.try_send_signal(StreamSignal::StopAcquisition);
+        Ok(())
+    }
+}
+
+define_handler!(
+    TLParamsLockedHandler,
+    GenApiReg::TLParamsLocked,
+    MemoryEvent::TLParamsLocked
+);
+impl TLParamsLockedHandler {
+    /// Handle `MemoryEvent::TLParamsLocked`.
+    ///
+    /// Features which affect the payload size become read only while `TLParamsLocked` is set to 1.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let access_right = match Self::read(&memory, scd_kind)? {
+            0 => AccessRight::RW,
+            1 => AccessRight::RO,
+            _ => {
+                Self::write(0, &mut memory, scd_kind)?;
+                return Err(ack::ErrorAck::new(
+                    ack::GenCpStatus::InvalidParameter,
+                    scd_kind,
+                ));
+            }
+        };
+
+        memory.set_access_right::<GenApiReg::Width>(access_right);
+        memory.set_access_right::<GenApiReg::Height>(access_right);
+        memory.set_access_right::<GenApiReg::PixelFormat>(access_right);
+        memory.set_access_right::<GenApiReg::ChunkModeActive>(access_right);
+        memory.set_access_right::<GenApiReg::ChunkEnableTimestamp>(access_right);
+        memory.set_access_right::<GenApiReg::ChunkEnableFrameID>(access_right);
+        memory.set_access_right::<GenApiReg::ChunkEnableExposureTime>(access_right);
+        memory.set_access_right::<GenApiReg::ChunkEnableGain>(access_right);
+        Ok(())
+    }
+}
+
+define_handler!(WidthHandler, GenApiReg::Width, MemoryEvent::Width);
+impl WidthHandler {
+    /// Handle `MemoryEvent::Width`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let max = read_memory::<GenApiReg::WidthMax>(&memory, scd_kind)?
+            .saturating_sub(read_memory::<GenApiReg::OffsetX>(&memory, scd_kind)?);
+        let res = verify_range::<GenApiReg::Width>(1, max, &mut memory, scd_kind);
+        update_payload_size(&mut memory, scd_kind)?;
+        res
+    }
+}
+
+define_handler!(HeightHandler, GenApiReg::Height, MemoryEvent::Height);
+impl HeightHandler {
+    /// Handle `MemoryEvent::Height`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let max = read_memory::<GenApiReg::HeightMax>(&memory, scd_kind)?
+            .saturating_sub(read_memory::<GenApiReg::OffsetY>(&memory, scd_kind)?);
+        let res = verify_range::<GenApiReg::Height>(1, max, &mut memory, scd_kind);
+        update_payload_size(&mut memory, scd_kind)?;
+        res
+    }
+}
+
+define_handler!(OffsetXHandler, GenApiReg::OffsetX, MemoryEvent::OffsetX);
+impl OffsetXHandler {
+    /// Handle `MemoryEvent::OffsetX`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let max = read_memory::<GenApiReg::WidthMax>(&memory, scd_kind)?
+            .saturating_sub(read_memory::<GenApiReg::Width>(&memory, scd_kind)?);
+        verify_range::<GenApiReg::OffsetX>(0, max, &mut memory, scd_kind)
+    }
+}
+
+define_handler!(OffsetYHandler, GenApiReg::OffsetY, MemoryEvent::OffsetY);
+impl OffsetYHandler {
+    /// Handle `MemoryEvent::OffsetY`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let max = read_memory::<GenApiReg::HeightMax>(&memory, scd_kind)?
+            .saturating_sub(read_memory::<GenApiReg::Height>(&memory, scd_kind)?);
+        verify_range::<GenApiReg::OffsetY>(0, max, &mut memory, scd_kind)
+    }
+}
+
+define_handler!(
+    PixelFormatHandler,
+    GenApiReg::PixelFormat,
+    MemoryEvent::PixelFormat
+);
+impl PixelFormatHandler {
+    /// Handle `MemoryEvent::PixelFormat`.
+    ///
+    /// If an unsupported pixel format is written, the pixel format is reset to `Mono8`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let res = match Self::read(&memory, scd_kind)? {
+            genapi::MONO8 | genapi::MONO16 | genapi::RGB8 => Ok(()),
+            _ => {
+                Self::write(genapi::MONO8, &mut memory, scd_kind)?;
+                Err(ack::ErrorAck::new(
+                    ack::GenCpStatus::InvalidParameter,
+                    scd_kind,
+                ))
+            }
+        };
+        update_payload_size(&mut memory, scd_kind)?;
+        res
+    }
+}
+
+define_handler!(
+    ExposureTimeHandler,
+    GenApiReg::ExposureTime,
+    MemoryEvent::ExposureTime
+);
+impl ExposureTimeHandler {
+    /// Handle `MemoryEvent::ExposureTime`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<GenApiReg::ExposureTime>(10.0, 1_000_000.0, &mut memory, scd_kind)
+    }
+}
+
+define_handler!(
+    AcquisitionFrameRateHandler,
+    GenApiReg::AcquisitionFrameRate,
+    MemoryEvent::AcquisitionFrameRate
+);
+impl AcquisitionFrameRateHandler {
+    /// Handle `MemoryEvent::AcquisitionFrameRate`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<GenApiReg::AcquisitionFrameRate>(1.0, 1000.0, &mut memory, scd_kind)
+    }
+}
+
+define_handler!(GainHandler, GenApiReg::Gain, MemoryEvent::Gain);
+impl GainHandler {
+    /// Handle `MemoryEvent::Gain`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<GenApiReg::Gain>(0.0, 24.0, &mut memory, scd_kind)
+    }
+}
+
+define_handler!(
+    TriggerModeHandler,
+    GenApiReg::TriggerMode,
+    MemoryEvent::TriggerMode
+);
+impl TriggerModeHandler {
+    /// Handle `MemoryEvent::TriggerMode`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<GenApiReg::TriggerMode>(
+            genapi::TRIGGER_MODE_OFF,
+            genapi::TRIGGER_MODE_ON,
+            &mut memory,
+            scd_kind,
+        )
+    }
+}
+
+define_handler!(
+    TriggerSoftwareHandler,
+    GenApiReg::TriggerSoftware,
+    MemoryEvent::TriggerSoftware
+);
+impl TriggerSoftwareHandler {
+    /// Handle `MemoryEvent::TriggerSoftware`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let value = Self::read(&*worker.memory.lock().await, scd_kind)?;
+        if value != 1 {
+            return Err(ack::ErrorAck::new(ack::GenCpStatus::GenericError, scd_kind));
+        }
+
+        worker.try_send_signal(StreamSignal::TriggerSoftware);
+        Ok(())
+    }
+}
+
+define_handler!(
+    AcquisitionModeHandler,
+    GenApiReg::AcquisitionMode,
+    MemoryEvent::AcquisitionMode
+);
+impl AcquisitionModeHandler {
+    /// Handle `MemoryEvent::AcquisitionMode`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<GenApiReg::AcquisitionMode>(
+            genapi::ACQUISITION_MODE_CONTINUOUS,
+            genapi::ACQUISITION_MODE_SINGLE_FRAME,
+            &mut memory,
+            scd_kind,
+        )
+    }
+}
+
+define_handler!(
+    ChunkModeActiveHandler,
+    GenApiReg::ChunkModeActive,
+    MemoryEvent::ChunkModeActive
+);
+impl ChunkModeActiveHandler {
+    /// Handle `MemoryEvent::ChunkModeActive`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        let res = verify_range::<GenApiReg::ChunkModeActive>(0, 1, &mut memory, scd_kind);
+        update_payload_size(&mut memory, scd_kind)?;
+        res
+    }
+}
+
+define_handler!(
+    ChunkSelectorHandler,
+    GenApiReg::ChunkSelector,
+    MemoryEvent::ChunkSelector
+);
+impl ChunkSelectorHandler {
+    /// Handle `MemoryEvent::ChunkSelector`.
+    async fn handle_events(worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+        let mut memory = worker.memory.lock().await;
+        verify_range::<GenApiReg::ChunkSelector>(
+            genapi::CHUNK_SELECTOR_IMAGE,
+            genapi::CHUNK_SELECTOR_GAIN,
+            &mut memory,
+            scd_kind,
+        )
+    }
+}
+
+/// This macro defines handler for `ChunkEnable` registers.
+///
+/// A handler defined by this macro verifies the written value is either 0 or 1, then updates
+/// the payload size.
+macro_rules! define_handler_for_chunk_enable {
+    ($handler_name:ident, $reg:path, $event:path) => {
+        define_handler!($handler_name, $reg, $event);
+
+        impl $handler_name {
+            async fn handle_events(
+                worker: &Worker,
+                scd_kind: cmd::ScdKind,
+            ) -> Result<(), ack::ErrorAck> {
+                let mut memory = worker.memory.lock().await;
+                let res = verify_range::<$reg>(0, 1, &mut memory, scd_kind);
+                update_payload_size(&mut memory, scd_kind)?;
+                res
+            }
+        }
+    };
+}
+
+// Define handlers related to chunk enable registers.
+define_handler_for_chunk_enable!(
+    ChunkEnableTimestampHandler,
+    GenApiReg::ChunkEnableTimestamp,
+    MemoryEvent::ChunkEnableTimestamp
+);
+define_handler_for_chunk_enable!(
+    ChunkEnableFrameIDHandler,
+    GenApiReg::ChunkEnableFrameID,
+    MemoryEvent::ChunkEnableFrameID
+);
+define_handler_for_chunk_enable!(
+    ChunkEnableExposureTimeHandler,
+    GenApiReg::ChunkEnableExposureTime,
+    MemoryEvent::ChunkEnableExposureTime
+);
+define_handler_for_chunk_enable!(
+    ChunkEnableGainHandler,
+    GenApiReg::ChunkEnableGain,
+    MemoryEvent::ChunkEnableGain
+);
+
 /// This macro defines handler for registers of SIRM which are related to streaming data size.
 ///
 /// A handler defined by this macro works as a verifier which verify the written size has correct
@@ -295,22 +687,55 @@ define_handler_for_sirm!(
 enum MemoryEvent {
     TimestampLatch,
     SiControl,
+    EiControl,
+    MaximumEventTransferLength,
+    EventTestControl,
     MaximumLeaderSize,
     PayloadTransferSize,
     PayloadFinalTransferSize1,
     PayloadFinalTransferSize2,
     MaximumTrailerSize,
+    AcquisitionStart,
+    AcquisitionStop,
+    TLParamsLocked,
+    Width,
+    Height,
+    OffsetX,
+    OffsetY,
+    PixelFormat,
+    ExposureTime,
+    AcquisitionFrameRate,
+    Gain,
+    TriggerMode,
+    TriggerSoftware,
+    AcquisitionMode,
+    ChunkModeActive,
+    ChunkSelector,
+    ChunkEnableTimestamp,
+    ChunkEnableFrameID,
+    ChunkEnableExposureTime,
+    ChunkEnableGain,
 }
 
 impl MemoryEvent {
     async fn process(self, worker: &Worker, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
         use MemoryEvent::{
-            MaximumLeaderSize, MaximumTrailerSize, PayloadFinalTransferSize1,
-            PayloadFinalTransferSize2, PayloadTransferSize, SiControl, TimestampLatch,
+            AcquisitionFrameRate, AcquisitionMode, AcquisitionStart, AcquisitionStop,
+            ChunkEnableExposureTime, ChunkEnableFrameID, ChunkEnableGain, ChunkEnableTimestamp,
+            ChunkModeActive, ChunkSelector, EiControl, EventTestControl, ExposureTime, Gain,
+            Height, MaximumEventTransferLength, MaximumLeaderSize, MaximumTrailerSize, OffsetX,
+            OffsetY, PayloadFinalTransferSize1, PayloadFinalTransferSize2, PayloadTransferSize,
+            PixelFormat, SiControl, TLParamsLocked, TimestampLatch, TriggerMode, TriggerSoftware,
+            Width,
         };
         match self {
             TimestampLatch => TimestampLatchHandler::handle_events(worker, scd_kind).await,
             SiControl => SiControlHandler::handle_events(worker, scd_kind).await,
+            EiControl => EiControlHandler::handle_events(worker, scd_kind).await,
+            MaximumEventTransferLength => {
+                MaximumEventTransferLengthHandler::handle_events(worker, scd_kind).await
+            }
+            EventTestControl => EventTestControlHandler::handle_events(worker, scd_kind).await,
             MaximumLeaderSize => MaximumLeaderSizeHandler::handle_events(worker, scd_kind).await,
             PayloadTransferSize => {
                 PayloadTransferSizeHandler::handle_events(worker, scd_kind).await
@@ -322,17 +747,66 @@ impl MemoryEvent {
                 PayloadFinalTransferSize2Handler::handle_events(worker, scd_kind).await
             }
             MaximumTrailerSize => MaximumTrailerSizeHandler::handle_events(worker, scd_kind).await,
+            AcquisitionStart => AcquisitionStartHandler::handle_events(worker, scd_kind).await,
+            AcquisitionStop => AcquisitionStopHandler::handle_events(worker, scd_kind).await,
+            TLParamsLocked => TLParamsLockedHandler::handle_events(worker, scd_kind).await,
+            Width => WidthHandler::handle_events(worker, scd_kind).await,
+            Height => HeightHandler::handle_events(worker, scd_kind).await,
+            OffsetX => OffsetXHandler::handle_events(worker, scd_kind).await,
+            OffsetY => OffsetYHandler::handle_events(worker, scd_kind).await,
+            PixelFormat => PixelFormatHandler::handle_events(worker, scd_kind).await,
+            ExposureTime => ExposureTimeHandler::handle_events(worker, scd_kind).await,
+            AcquisitionFrameRate => {
+                AcquisitionFrameRateHandler::handle_events(worker, scd_kind).await
+            }
+            Gain => GainHandler::handle_events(worker, scd_kind).await,
+            TriggerMode => TriggerModeHandler::handle_events(worker, scd_kind).await,
+            TriggerSoftware => TriggerSoftwareHandler::handle_events(worker, scd_kind).await,
+            AcquisitionMode => AcquisitionModeHandler::handle_events(worker, scd_kind).await,
+            ChunkModeActive => ChunkModeActiveHandler::handle_events(worker, scd_kind).await,
+            ChunkSelector => ChunkSelectorHandler::handle_events(worker, scd_kind).await,
+            ChunkEnableTimestamp => {
+                ChunkEnableTimestampHandler::handle_events(worker, scd_kind).await
+            }
+            ChunkEnableFrameID => ChunkEnableFrameIDHandler::handle_events(worker, scd_kind).await,
+            ChunkEnableExposureTime => {
+                ChunkEnableExposureTimeHandler::handle_events(worker, scd_kind).await
+            }
+            ChunkEnableGain => ChunkEnableGainHandler::handle_events(worker, scd_kind).await,
         }
     }
 
     fn register_events(memory: &mut Memory, sender: &Sender<Self>) {
         TimestampLatchHandler::register(memory, sender);
         SiControlHandler::register(memory, sender);
+        EiControlHandler::register(memory, sender);
+        MaximumEventTransferLengthHandler::register(memory, sender);
+        EventTestControlHandler::register(memory, sender);
         MaximumLeaderSizeHandler::register(memory, sender);
         PayloadTransferSizeHandler::register(memory, sender);
         PayloadFinalTransferSize1Handler::register(memory, sender);
         PayloadFinalTransferSize2Handler::register(memory, sender);
         MaximumTrailerSizeHandler::register(memory, sender);
+        AcquisitionStartHandler::register(memory, sender);
+        AcquisitionStopHandler::register(memory, sender);
+        TLParamsLockedHandler::register(memory, sender);
+        WidthHandler::register(memory, sender);
+        HeightHandler::register(memory, sender);
+        OffsetXHandler::register(memory, sender);
+        OffsetYHandler::register(memory, sender);
+        PixelFormatHandler::register(memory, sender);
+        ExposureTimeHandler::register(memory, sender);
+        AcquisitionFrameRateHandler::register(memory, sender);
+        GainHandler::register(memory, sender);
+        TriggerModeHandler::register(memory, sender);
+        TriggerSoftwareHandler::register(memory, sender);
+        AcquisitionModeHandler::register(memory, sender);
+        ChunkModeActiveHandler::register(memory, sender);
+        ChunkSelectorHandler::register(memory, sender);
+        ChunkEnableTimestampHandler::register(memory, sender);
+        ChunkEnableFrameIDHandler::register(memory, sender);
+        ChunkEnableExposureTimeHandler::register(memory, sender);
+        ChunkEnableGainHandler::register(memory, sender);
     }
 }
 
@@ -356,3 +830,58 @@ fn write_memory<T: Register>(
         ack::ErrorAck::new(ack::GenCpStatus::GenericError, scd_kind)
     })
 }
+
+/// Verify the value of the register is in `[min, max]`.
+///
+/// If the value is out of the range, the value is clamped into the range and `InvalidParameter`
+/// error is returned.
+fn verify_range<T>(
+    min: T::Ty,
+    max: T::Ty,
+    memory: &mut Memory,
+    scd_kind: cmd::ScdKind,
+) -> Result<(), ack::ErrorAck>
+where
+    T: Register,
+    T::Ty: PartialOrd,
+{
+    let value = read_memory::<T>(memory, scd_kind)?;
+    let clamped = if value < min {
+        min
+    } else if value > max {
+        max
+    } else {
+        return Ok(());
+    };
+
+    write_memory::<T>(clamped, memory, scd_kind)?;
+    Err(ack::ErrorAck::new(
+        ack::GenCpStatus::InvalidParameter,
+        scd_kind,
+    ))
+}
+
+/// Update `PayloadSize` and `SIRM::RequiredPayloadSize` according to current image format and
+/// enabled chunks.
+fn update_payload_size(memory: &mut Memory, scd_kind: cmd::ScdKind) -> Result<(), ack::ErrorAck> {
+    let width = read_memory::<GenApiReg::Width>(memory, scd_kind)?;
+    let height = read_memory::<GenApiReg::Height>(memory, scd_kind)?;
+    let pixel_format = read_memory::<GenApiReg::PixelFormat>(memory, scd_kind)?;
+    let mut payload_size = width * height * genapi::bytes_per_pixel(pixel_format);
+
+    if read_memory::<GenApiReg::ChunkModeActive>(memory, scd_kind)? == 1 {
+        let chunk_enables = [
+            read_memory::<GenApiReg::ChunkEnableTimestamp>(memory, scd_kind)?,
+            read_memory::<GenApiReg::ChunkEnableFrameID>(memory, scd_kind)?,
+            read_memory::<GenApiReg::ChunkEnableExposureTime>(memory, scd_kind)?,
+            read_memory::<GenApiReg::ChunkEnableGain>(memory, scd_kind)?,
+        ];
+        let enabled_chunks = chunk_enables.iter().filter(|enable| **enable == 1).count() as u32;
+        // The image chunk is always included.
+        payload_size += genapi::CHUNK_TRAILER_LEN
+            + enabled_chunks * (genapi::CHUNK_DATA_LEN + genapi::CHUNK_TRAILER_LEN);
+    }
+
+    write_memory::<GenApiReg::PayloadSize>(payload_size, memory, scd_kind)?;
+    write_memory::<SIRM::RequiredPayloadSize>(u64::from(payload_size), memory, scd_kind)
+}
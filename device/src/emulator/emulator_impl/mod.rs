@@ -2,23 +2,30 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+pub mod bandwidth;
+pub mod clock;
 mod control_module;
 mod control_protocol;
 mod device;
 mod device_handle;
 mod device_pool;
 mod emulator_builder;
+pub mod event;
 mod event_module;
 mod fake_protocol;
+pub mod fault;
+pub mod frame_source;
 mod genapi;
 mod interface;
 mod memory;
 mod memory_event_handler;
+mod register_image;
 mod shared_queue;
 mod signal;
 mod stream_module;
 
 pub use emulator_builder::*;
+pub use register_image::RegisterImage;
 
 pub(super) use device_handle::*;
 pub(super) use device_pool::DevicePool;
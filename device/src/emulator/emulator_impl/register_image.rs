@@ -0,0 +1,236 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::{collections::BTreeMap, iter::FromIterator, ops::Range};
+
+use cameleon_impl::memory::{MemoryError, MemoryResult};
+
+use super::memory::GENAPI_REG_ADDRESS;
+
+/// Image of registers which backs a GenApi XML passed to
+/// [`EmulatorBuilder::from_genapi_xml`](super::EmulatorBuilder::from_genapi_xml).
+///
+/// An image consists of segments of raw register values. All segments are readable and writable
+/// from the host. Overlapping or adjacent segments are merged, and data of the segment added
+/// later takes precedence.
+///
+/// # Example
+/// ```rust
+/// use cameleon_device::u3v::RegisterImage;
+///
+/// // Width and Height registers of a vendor camera.
+/// let image = RegisterImage::new()
+///     .segment(0x0003_0000, 1280_u32.to_le_bytes())
+///     .segment(0x0003_0004, 960_u32.to_le_bytes());
+///
+/// // Segments can be also collected from a register dump.
+/// let dump = vec![(0x0003_0000, vec![0; 8]), (0x0003_1000, vec![0; 4])];
+/// let image: RegisterImage = dump.into_iter().collect();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RegisterImage {
+    /// Map of start address to data. Segments never overlap or adjoin each other.
+    segments: BTreeMap<u64, Vec<u8>>,
+}
+
+impl RegisterImage {
+    /// Construct an empty image.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a segment which starts at `address`.
+    ///
+    /// # Panics
+    /// If the end of the segment overflows `u64`, this method will panic.
+    #[must_use]
+    pub fn segment(mut self, address: u64, data: impl Into<Vec<u8>>) -> Self {
+        self.insert(address, data.into());
+        self
+    }
+
+    fn insert(&mut self, address: u64, data: Vec<u8>) {
+        let end = address.checked_add(data.len() as u64).unwrap();
+
+        // Remove segments which overlap or adjoin the new one to merge them.
+        let starts: Vec<u64> = self
+            .segments
+            .range(..=end)
+            .filter(|(start, seg)| address <= *start + seg.len() as u64)
+            .map(|(start, _)| *start)
+            .collect();
+        let merged: Vec<(u64, Vec<u8>)> = starts
+            .into_iter()
+            .map(|start| (start, self.segments.remove(&start).unwrap()))
+            .collect();
+
+        let start = merged
+            .first()
+            .map_or(address, |(start, _)| (*start).min(address));
+        let merged_end = merged
+            .iter()
+            .map(|(start, seg)| start + seg.len() as u64)
+            .fold(end, u64::max);
+
+        let mut buf = vec![0; (merged_end - start) as usize];
+        for (seg_start, seg) in merged {
+            let offset = (seg_start - start) as usize;
+            buf[offset..offset + seg.len()].copy_from_slice(&seg);
+        }
+        let offset = (address - start) as usize;
+        buf[offset..offset + data.len()].copy_from_slice(&data);
+
+        self.segments.insert(start, buf);
+    }
+
+    /// End address of the last segment.
+    pub(super) fn end(&self) -> u64 {
+        self.segments
+            .iter()
+            .next_back()
+            .map_or(0, |(start, seg)| start + seg.len() as u64)
+    }
+
+    fn segment_mut(&mut self, range: &Range<u64>) -> Option<(u64, &mut Vec<u8>)> {
+        let (start, seg) = self.segments.range_mut(..=range.start).next_back()?;
+        if range.end <= start + seg.len() as u64 {
+            Some((*start, seg))
+        } else {
+            None
+        }
+    }
+
+    fn segment_ref(&self, range: &Range<u64>) -> Option<(u64, &[u8])> {
+        let (start, seg) = self.segments.range(..=range.start).next_back()?;
+        if range.end <= start + seg.len() as u64 {
+            Some((*start, seg))
+        } else {
+            None
+        }
+    }
+}
+
+impl FromIterator<(u64, Vec<u8>)> for RegisterImage {
+    fn from_iter<T: IntoIterator<Item = (u64, Vec<u8>)>>(iter: T) -> Self {
+        let mut image = Self::new();
+        for (address, data) in iter {
+            image.insert(address, data);
+        }
+        image
+    }
+}
+
+/// Memory placed outside of bootstrap register maps, which hosts a register image and a GenApi
+/// XML file.
+///
+/// Addresses of bootstrap register maps, i.e. `ABRM`, `SBRM`, `SIRM` and the manifest table, are
+/// always handled by [`super::memory::Memory`] even if the image covers them.
+#[derive(Debug, Clone)]
+pub(super) struct ExternalMemory {
+    image: RegisterImage,
+    xml_address: u64,
+    xml: Vec<u8>,
+}
+
+impl ExternalMemory {
+    pub(super) fn new(image: RegisterImage, xml_address: u64, xml: Vec<u8>) -> Self {
+        Self {
+            image,
+            xml_address,
+            xml,
+        }
+    }
+
+    /// Returns `true` if the whole range is handled by the memory.
+    pub(super) fn contains(&self, range: &Range<u64>) -> bool {
+        range.start >= GENAPI_REG_ADDRESS as u64
+            && (self.xml_range_of(range).is_some() || self.image.segment_ref(range).is_some())
+    }
+
+    pub(super) fn read_raw(&self, range: Range<u64>) -> MemoryResult<&[u8]> {
+        if let Some(xml_range) = self.xml_range_of(&range) {
+            return Ok(&self.xml[xml_range]);
+        }
+
+        let (start, seg) = self
+            .image
+            .segment_ref(&range)
+            .ok_or(MemoryError::InvalidAddress)?;
+        Ok(&seg[(range.start - start) as usize..(range.end - start) as usize])
+    }
+
+    pub(super) fn write_raw(&mut self, address: u64, data: &[u8]) -> MemoryResult<()> {
+        let range = address..address + data.len() as u64;
+        if self.xml_range_of(&range).is_some() {
+            return Err(MemoryError::AddressNotWritable);
+        }
+
+        let (start, seg) = self
+            .image
+            .segment_mut(&range)
+            .ok_or(MemoryError::InvalidAddress)?;
+        seg[(range.start - start) as usize..(range.end - start) as usize].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn xml_range_of(&self, range: &Range<u64>) -> Option<Range<usize>> {
+        let xml_end = self.xml_address + self.xml.len() as u64;
+        if self.xml_address <= range.start && range.end <= xml_end {
+            let start = (range.start - self.xml_address) as usize;
+            let end = (range.end - self.xml_address) as usize;
+            Some(start..end)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u64 = 0x0010_0000;
+
+    #[test]
+    fn test_merge_segments() {
+        let image = RegisterImage::new()
+            .segment(BASE, vec![1; 4])
+            .segment(BASE + 8, vec![2; 4])
+            .segment(BASE + 2, vec![3; 6])
+            .segment(BASE + 16, vec![4; 4]);
+
+        let memory = ExternalMemory::new(image, BASE + 0x1000, vec![]);
+        assert_eq!(
+            memory.read_raw(BASE..BASE + 12).unwrap(),
+            &[1, 1, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2]
+        );
+        assert!(memory.read_raw(BASE + 8..BASE + 20).is_err());
+        assert_eq!(memory.read_raw(BASE + 16..BASE + 20).unwrap(), &[4; 4]);
+    }
+
+    #[test]
+    fn test_read_write() {
+        let image = RegisterImage::new().segment(BASE, vec![0; 8]);
+        let mut memory = ExternalMemory::new(image, BASE + 0x1000, b"xml".to_vec());
+
+        memory.write_raw(BASE + 4, &[1, 2]).unwrap();
+        assert_eq!(memory.read_raw(BASE + 4..BASE + 6).unwrap(), &[1, 2]);
+        assert_eq!(
+            memory.read_raw(BASE + 0x1000..BASE + 0x1003).unwrap(),
+            b"xml"
+        );
+
+        assert!(memory.contains(&(BASE..BASE + 8)));
+        assert!(!memory.contains(&(BASE..BASE + 9)));
+        assert!(matches!(
+            memory.write_raw(BASE + 0x1000, &[0]),
+            Err(MemoryError::AddressNotWritable)
+        ));
+        assert!(matches!(
+            memory.write_raw(BASE + 6, &[0; 4]),
+            Err(MemoryError::InvalidAddress)
+        ));
+    }
+}
@@ -24,6 +24,17 @@ impl<T> SharedQueue<T> {
         }
     }
 
+    /// Enqueue the element, or give it back if the queue is full.
+    pub(super) fn try_enqueue(&self, elem: T) -> Result<(), T> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.len() < self.cap {
+            inner.push_front(elem);
+            Ok(())
+        } else {
+            Err(elem)
+        }
+    }
+
     pub(super) fn dequeue(&self) -> Option<T> {
         self.inner.lock().unwrap().pop_back()
     }
@@ -36,6 +47,10 @@ impl<T> SharedQueue<T> {
         }
     }
 
+    pub(super) fn is_empty(&self) -> bool {
+        self.inner.lock().unwrap().is_empty()
+    }
+
     pub(super) fn clear(&self) {
         self.inner.lock().unwrap().clear()
     }
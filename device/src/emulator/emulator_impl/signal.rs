@@ -4,7 +4,7 @@
 
 use futures::channel::oneshot;
 
-use super::IfaceKind;
+use super::{event::Event, IfaceKind};
 
 /// Signal sent to control module.
 pub(super) enum ControlSignal {
@@ -26,20 +26,13 @@ pub(super) enum ControlSignal {
 
 /// Signal sent to event module.
 pub(super) enum EventSignal {
-    /// Signal to send event data to tha host.
-    _EventData {
-        event_id: u16,
-        data: Vec<u8>,
-        request_id: u16,
-    },
+    /// Signal to send events to tha host.
+    EventData(Vec<Event>),
 
-    /// Signal to update timestamp
-    UpdateTimestamp(u64),
-
-    /// signal to enable event module.
-    _Enable,
+    /// Signal to enable event module.
+    Enable,
 
-    /// signal to disable event module.
+    /// Signal to disable event module.
     Disable(oneshot::Sender<()>),
 
     /// Signal to shutdown.
@@ -54,6 +47,15 @@ pub(super) enum StreamSignal {
     /// Signal to disable stream module.
     Disable(oneshot::Sender<()>),
 
+    /// Signal to start acquisition of images.
+    StartAcquisition,
+
+    /// Signal to stop acquisition of images.
+    StopAcquisition,
+
+    /// Signal to generate a software trigger.
+    TriggerSoftware,
+
     /// Signal to shutdown.
     Shutdown,
 }
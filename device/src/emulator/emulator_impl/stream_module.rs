@@ -2,63 +2,905 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use std::{collections::VecDeque, convert::TryInto, sync::Arc, time::Duration};
+
 use async_std::{
     channel::{Receiver, Sender},
     prelude::*,
+    sync::Mutex,
+    task,
 };
+use futures::{pin_mut, select, FutureExt};
+
+use cameleon_impl::memory::prelude::*;
 
 use super::{
-    device::Timestamp,
+    bandwidth::{Bandwidth, Overflow},
+    clock::Timestamp,
+    fault::{FaultInjector, StreamFaultKind},
+    frame_source::{FrameInfo, FrameSource},
+    genapi::{self, GenApiReg},
+    memory::{Memory, SIRM},
     shared_queue::SharedQueue,
     signal::{InterfaceSignal, StreamSignal},
+    IfaceKind,
 };
 
-// TODO: Implement stream module.
+/// Interval to retry sending transfers when the stream queue is full.
+const RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
 pub(super) struct StreamModule {
-    _queue: SharedQueue<Vec<u8>>,
-    _timestamp: Timestamp,
+    memory: Arc<Mutex<Memory>>,
+    queue: SharedQueue<Vec<u8>>,
+    timestamp: Timestamp,
+    frame_source: Arc<Mutex<Box<dyn FrameSource>>>,
+    faults: FaultInjector,
+    bandwidth: Option<Bandwidth>,
 
     enabled: bool,
+    acquiring: bool,
+    block_id: u64,
+    /// Device time to capture the next frame.
+    next_frame: Duration,
+    /// The number of software triggers which are not consumed yet.
+    triggers: usize,
+
+    /// Leader and payload transfers of the current frame which are not sent to the host yet.
+    pending: VecDeque<Vec<u8>>,
+    /// Trailer of the current frame, which is sent after all pending transfers.
+    trailer: Option<stream_packet::Trailer>,
+    /// Size of the payload of the current frame sent to the host. `None` if the leader is not
+    /// sent yet.
+    sent_payload_size: Option<usize>,
+    /// Duration to stall after the leader of the current frame is sent.
+    stall: Option<Duration>,
+    /// Device time to resume sending pending transfers.
+    resume_at: Option<Duration>,
+    /// `true` if the stream endpoint is halted once the host reads all transfers in the queue.
+    halt: bool,
 }
 
 impl StreamModule {
-    pub(super) fn new(timestamp: Timestamp, queue: SharedQueue<Vec<u8>>) -> Self {
+    pub(super) fn new(
+        memory: Arc<Mutex<Memory>>,
+        timestamp: Timestamp,
+        queue: SharedQueue<Vec<u8>>,
+        frame_source: Arc<Mutex<Box<dyn FrameSource>>>,
+        faults: FaultInjector,
+        bandwidth: Option<Bandwidth>,
+    ) -> Self {
         Self {
-            _timestamp: timestamp,
-            _queue: queue,
+            memory,
+            queue,
+            timestamp,
+            frame_source,
+            faults,
+            bandwidth,
             enabled: false,
+            acquiring: false,
+            block_id: 0,
+            next_frame: Duration::ZERO,
+            triggers: 0,
+            pending: VecDeque::new(),
+            trailer: None,
+            sent_payload_size: None,
+            stall: None,
+            resume_at: None,
+            halt: false,
         }
     }
 
     pub(super) async fn run(
         mut self,
-        _signal_tx: Sender<InterfaceSignal>,
+        signal_tx: Sender<InterfaceSignal>,
         mut signal_rx: Receiver<StreamSignal>,
     ) {
-        while let Some(signal) = signal_rx.next().await {
+        loop {
+            let signal = if self.is_streaming() {
+                let signal = {
+                    let wake_up = self.wake_up().fuse();
+                    pin_mut!(wake_up);
+                    select! {
+                        signal = signal_rx.next().fuse() => Some(signal),
+                        () = wake_up => None,
+                    }
+                };
+                match signal {
+                    Some(signal) => signal,
+                    None => {
+                        self.process_frame(&signal_tx).await;
+                        continue;
+                    }
+                }
+            } else {
+                signal_rx.next().await
+            };
+
             match signal {
-                StreamSignal::Enable => {
+                Some(StreamSignal::Enable) => {
                     if self.enabled {
                         log::warn! {"receive stream enable signal, but stream module is already enabled"}
                     } else {
                         self.enabled = true;
+                        self.block_id = 0;
                         log::info! {"stream module is enabled"};
                     }
                 }
 
-                StreamSignal::Disable(_completed) => {
+                Some(StreamSignal::Disable(_completed)) => {
                     if self.enabled {
                         self.enabled = false;
+                        self.clear_pending();
+                        // Flush transfers which the host didn't read.
+                        self.queue.clear();
                         log::info! {"stream module is disabled"};
                     } else {
                         log::warn! {"receive stream disable signal, but stream module is already disabled"}
                     }
                 }
 
-                StreamSignal::Shutdown => {
+                Some(StreamSignal::StartAcquisition) => {
+                    self.acquiring = true;
+                    self.next_frame = self.now();
+                    self.triggers = 0;
+                    log::info! {"acquisition is started"};
+                }
+
+                Some(StreamSignal::StopAcquisition) => {
+                    self.acquiring = false;
+                    self.triggers = 0;
+                    self.clear_pending();
+                    log::info! {"acquisition is stopped"};
+                }
+
+                Some(StreamSignal::TriggerSoftware) => {
+                    if self.acquiring {
+                        self.triggers += 1;
+                    } else {
+                        log::warn! {"receive software trigger, but acquisition is not started"}
+                    }
+                }
+
+                Some(StreamSignal::Shutdown) | None => {
                     break;
                 }
             }
         }
     }
+
+    /// Returns `true` if there is a frame to capture or remaining transfers to send.
+    fn is_streaming(&self) -> bool {
+        self.enabled && (self.acquiring || self.in_flight() || self.halt)
+    }
+
+    /// Returns `true` if the current frame is still being sent.
+    fn in_flight(&self) -> bool {
+        !self.pending.is_empty() || self.trailer.is_some()
+    }
+
+    /// Wait until the next frame needs to be processed.
+    ///
+    /// Retrying on a full queue waits on the wall clock because the queue is drained by the host.
+    async fn wake_up(&self) {
+        let clock = self.timestamp.clock();
+        if self.halt {
+            task::sleep(RETRY_INTERVAL).await;
+        } else if !self.in_flight() {
+            clock.sleep_until(self.next_frame).await;
+        } else if let Some(resume_at) = self.resume_at {
+            // Frames overflow the bandwidth budget if the next frame is captured while the
+            // current frame is being sent.
+            let deadline = match self.bandwidth {
+                Some(_) if self.acquiring => resume_at.min(self.next_frame),
+                _ => resume_at,
+            };
+            clock.sleep_until(deadline).await;
+        } else {
+            task::sleep(RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Current device time.
+    fn now(&self) -> Duration {
+        self.timestamp.clock().now()
+    }
+
+    fn clear_pending(&mut self) {
+        self.pending.clear();
+        self.trailer = None;
+        self.sent_payload_size = None;
+        self.stall = None;
+        self.resume_at = None;
+        self.halt = false;
+    }
+
+    /// Send remaining transfers to the host, or capture a new frame if there is no remaining
+    /// transfer.
+    async fn process_frame(&mut self, signal_tx: &Sender<InterfaceSignal>) {
+        if self.halt {
+            // Halt the endpoint after the host reads the transfers of the previous frames.
+            if self.queue.is_empty() {
+                self.halt = false;
+                if signal_tx
+                    .try_send(InterfaceSignal::Halt(IfaceKind::Stream))
+                    .is_err()
+                {
+                    log::error!("Stream module -> Interface channel is full");
+                }
+            }
+            return;
+        }
+
+        if let Some(bandwidth) = self.bandwidth {
+            if self.in_flight() {
+                self.handle_overflow(bandwidth.overflow_kind()).await;
+            }
+        }
+
+        if !self.in_flight() {
+            let now = self.now();
+            if !self.acquiring || now < self.next_frame {
+                return;
+            }
+            let frame_interval = self.frame_interval().await;
+            self.next_frame = (self.next_frame + frame_interval).max(now);
+            self.capture_frame().await;
+        }
+
+        self.send_transfers();
+    }
+
+    /// Send transfers of the current frame until the queue gets full or the bandwidth budget is
+    /// used up.
+    fn send_transfers(&mut self) {
+        let now = self.now();
+        // Device time when the bus becomes free.
+        let mut free_at = match self.resume_at {
+            Some(resume_at) if now < resume_at => return,
+            Some(resume_at) => resume_at,
+            None => now,
+        };
+        self.resume_at = None;
+
+        loop {
+            let len = if let Some(transfer) = self.pending.pop_front() {
+                let len = transfer.len();
+                if let Err(transfer) = self.queue.try_enqueue(transfer) {
+                    self.pending.push_front(transfer);
+                    break;
+                }
+                // The first transfer of a frame is the leader.
+                self.sent_payload_size = Some(self.sent_payload_size.map_or(0, |sent| sent + len));
+                len
+            } else if let Some(trailer) = self.trailer.take() {
+                let mut buf = vec![];
+                if let Err(e) = trailer.serialize(&mut buf) {
+                    log::error!("failed to serialize trailer: {}", e);
+                    break;
+                }
+                let len = buf.len();
+                if self.queue.try_enqueue(buf).is_err() {
+                    self.trailer = Some(trailer);
+                    break;
+                }
+                len
+            } else {
+                break;
+            };
+
+            if let Some(bandwidth) = self.bandwidth {
+                free_at += bandwidth.transfer_time(len);
+            }
+            if let Some(stall) = self.stall.take() {
+                free_at += stall;
+            }
+            if free_at > now {
+                self.resume_at = Some(free_at);
+                break;
+            }
+        }
+    }
+
+    /// Handle the next frame which is captured while the current frame is still being sent.
+    async fn handle_overflow(&mut self, overflow: Overflow) {
+        let now = self.now();
+        if !self.acquiring || now < self.next_frame {
+            return;
+        }
+
+        let frame_interval = self.frame_interval().await;
+        let trigger_mode = self
+            .memory
+            .lock()
+            .await
+            .read::<GenApiReg::TriggerMode>()
+            .unwrap_or(genapi::TRIGGER_MODE_OFF);
+        let is_triggered = trigger_mode == genapi::TRIGGER_MODE_ON;
+        if is_triggered && self.triggers == 0 {
+            // No frame is due until a trigger arrives, so the current frame is kept intact.
+            self.next_frame = (self.next_frame + frame_interval).max(now);
+            return;
+        }
+
+        if overflow == Overflow::Discard && self.discard_frame() {
+            // The next frame is captured as soon as the trailer of the current frame is sent,
+            // which consumes the trigger.
+            return;
+        }
+
+        self.next_frame = (self.next_frame + frame_interval).max(now);
+        if is_triggered {
+            self.triggers -= 1;
+        }
+
+        log::info!(
+            "drop block {} because the previous frame is still being sent",
+            self.block_id
+        );
+        self.block_id += 1;
+    }
+
+    /// Discard the rest of the payload of the current frame.
+    ///
+    /// Returns `false` if there is nothing to discard, i.e. only the trailer remains.
+    fn discard_frame(&mut self) -> bool {
+        let sent_payload_size = match self.sent_payload_size {
+            Some(sent_payload_size) => sent_payload_size,
+            None => {
+                log::info!("drop the current frame before its leader is sent");
+                self.pending.clear();
+                self.trailer = None;
+                self.stall = None;
+                return true;
+            }
+        };
+        if self.pending.iter().all(Vec::is_empty) {
+            return false;
+        }
+
+        // Send remaining payload transfers as zero length packets so that the host finds the
+        // trailer where it expects.
+        log::info!("discard the rest of the payload of the current frame");
+        for transfer in &mut self.pending {
+            transfer.clear();
+        }
+        if let Some(trailer) = &mut self.trailer {
+            trailer.discard(sent_payload_size as u64);
+        }
+        true
+    }
+
+    /// Interval between frames which is derived from `AcquisitionFrameRate`.
+    async fn frame_interval(&self) -> Duration {
+        let memory = self.memory.lock().await;
+        let frame_rate = memory
+            .read::<GenApiReg::AcquisitionFrameRate>()
+            .unwrap_or(30.0);
+        Duration::from_secs_f64(1.0 / frame_rate.max(1.0))
+    }
+
+    /// Capture a frame and split it into leader, payload and trailer transfers according to
+    /// `SIRM` settings.
+    async fn capture_frame(&mut self) {
+        let (params, settings) = {
+            let memory = self.memory.lock().await;
+            match TransferParams::from_memory(&memory)
+                .and_then(|params| Ok((params, FrameSettings::from_memory(&memory)?)))
+            {
+                Ok(res) => res,
+                Err(e) => {
+                    log::error!("failed to read memory: {}", e);
+                    return;
+                }
+            }
+        };
+
+        if settings.trigger_mode == genapi::TRIGGER_MODE_ON {
+            if self.triggers == 0 {
+                return;
+            }
+            self.triggers -= 1;
+        }
+        if settings.acquisition_mode == genapi::ACQUISITION_MODE_SINGLE_FRAME {
+            self.acquiring = false;
+        }
+
+        let pixel_format = match settings.pixel_format.try_into() {
+            Ok(pixel_format) => pixel_format,
+            Err(e) => {
+                log::error!("invalid pixel format: {}", e);
+                return;
+            }
+        };
+
+        let timestamp = self.timestamp.as_nanos();
+        let block_id = self.block_id;
+        self.block_id += 1;
+
+        let fault = self.faults.stream_fault(block_id).await;
+        if let Some(fault) = fault {
+            log::info!("inject stream fault to block {}: {:?}", block_id, fault);
+        }
+        if fault == Some(StreamFaultKind::Halt) {
+            self.halt = true;
+            return;
+        }
+        self.stall = match fault {
+            Some(StreamFaultKind::Stall(duration)) => Some(duration),
+            _ => None,
+        };
+        let truncate_len = match fault {
+            Some(StreamFaultKind::Truncate(len)) => Some(len),
+            _ => None,
+        };
+
+        let frame_info = FrameInfo {
+            block_id,
+            timestamp,
+            pixel_format,
+            width: settings.width,
+            height: settings.height,
+            x_offset: settings.offset_x,
+            y_offset: settings.offset_y,
+        };
+        let mut image = self.frame_source.lock().await.next_frame(&frame_info);
+        if image.len() != frame_info.image_size() {
+            log::warn!(
+                "image size mismatch: expected {} bytes, but frame source produces {} bytes",
+                frame_info.image_size(),
+                image.len()
+            );
+            image.resize(frame_info.image_size(), 0);
+        }
+
+        let (payload, chunk_layout_id) = if settings.chunk_mode_active {
+            let (payload, chunk_layout_id) = settings.chunk_payload(image, &frame_info);
+            (payload, Some(chunk_layout_id))
+        } else {
+            (image, None)
+        };
+
+        let leader = if chunk_layout_id.is_some() {
+            stream_packet::image_extended_chunk_leader(&frame_info)
+        } else {
+            stream_packet::image_leader(&frame_info)
+        };
+        let mut leader_buf = vec![];
+        if let Err(e) = leader.serialize(&mut leader_buf) {
+            log::error!("failed to serialize leader: {}", e);
+            return;
+        }
+        if leader_buf.len() > params.maximum_leader_size {
+            log::error!("leader size is larger than SIRM maximum leader size");
+            return;
+        }
+        self.pending.push_back(leader_buf);
+        self.sent_payload_size = None;
+
+        let mut rest = payload.as_slice();
+        let mut valid_payload_size = 0;
+        for size in params.transfer_sizes() {
+            if rest.is_empty() {
+                break;
+            }
+            let len = size.min(rest.len());
+            let (transfer, remainder) = rest.split_at(len);
+            let transfer = match truncate_len {
+                Some(truncate_len) => &transfer[..truncate_len.min(len)],
+                None => transfer,
+            };
+            self.pending.push_back(transfer.to_vec());
+            valid_payload_size += len;
+            rest = remainder;
+        }
+
+        let status = if rest.is_empty() {
+            stream_packet::PayloadStatus::Success
+        } else {
+            log::warn!("payload size is larger than the sum of SIRM payload transfer sizes");
+            stream_packet::PayloadStatus::DataOverrun
+        };
+        let trailer = match chunk_layout_id {
+            Some(chunk_layout_id) => stream_packet::image_extended_chunk_trailer(
+                block_id,
+                status,
+                valid_payload_size as u64,
+                frame_info.height,
+                chunk_layout_id,
+            ),
+            None => stream_packet::image_trailer(
+                block_id,
+                status,
+                valid_payload_size as u64,
+                frame_info.height,
+            ),
+        };
+        self.trailer = Some(trailer);
+    }
+}
+
+/// Image format and acquisition settings read from `GenApiReg`.
+struct FrameSettings {
+    width: u32,
+    height: u32,
+    offset_x: u32,
+    offset_y: u32,
+    pixel_format: u32,
+    trigger_mode: u32,
+    acquisition_mode: u32,
+    exposure_time: f64,
+    gain: f64,
+    chunk_mode_active: bool,
+    chunk_enable_timestamp: bool,
+    chunk_enable_frame_id: bool,
+    chunk_enable_exposure_time: bool,
+    chunk_enable_gain: bool,
+}
+
+impl FrameSettings {
+    fn from_memory(memory: &Memory) -> cameleon_impl::memory::MemoryResult<Self> {
+        Ok(Self {
+            width: memory.read::<GenApiReg::Width>()?,
+            height: memory.read::<GenApiReg::Height>()?,
+            offset_x: memory.read::<GenApiReg::OffsetX>()?,
+            offset_y: memory.read::<GenApiReg::OffsetY>()?,
+            pixel_format: memory.read::<GenApiReg::PixelFormat>()?,
+            trigger_mode: memory.read::<GenApiReg::TriggerMode>()?,
+            acquisition_mode: memory.read::<GenApiReg::AcquisitionMode>()?,
+            exposure_time: memory.read::<GenApiReg::ExposureTime>()?,
+            gain: memory.read::<GenApiReg::Gain>()?,
+            chunk_mode_active: memory.read::<GenApiReg::ChunkModeActive>()? == 1,
+            chunk_enable_timestamp: memory.read::<GenApiReg::ChunkEnableTimestamp>()? == 1,
+            chunk_enable_frame_id: memory.read::<GenApiReg::ChunkEnableFrameID>()? == 1,
+            chunk_enable_exposure_time: memory.read::<GenApiReg::ChunkEnableExposureTime>()? == 1,
+            chunk_enable_gain: memory.read::<GenApiReg::ChunkEnableGain>()? == 1,
+        })
+    }
+
+    /// Build chunk data from the image and enabled chunks.
+    ///
+    /// Returns the chunk data and the chunk layout id, which is a bit set of `ChunkSelector`
+    /// values of the contained chunks.
+    fn chunk_payload(&self, image: Vec<u8>, frame_info: &FrameInfo) -> (Vec<u8>, u32) {
+        let image_len = image.len();
+        let mut payload = image;
+        stream_packet::write_chunk_trailer(&mut payload, genapi::CHUNK_ID_IMAGE, image_len);
+        let mut chunk_layout_id = 1 << genapi::CHUNK_SELECTOR_IMAGE;
+
+        let chunks = [
+            (
+                self.chunk_enable_timestamp,
+                genapi::CHUNK_SELECTOR_TIMESTAMP,
+                genapi::CHUNK_ID_TIMESTAMP,
+                frame_info.timestamp.to_le_bytes(),
+            ),
+            (
+                self.chunk_enable_frame_id,
+                genapi::CHUNK_SELECTOR_FRAME_ID,
+                genapi::CHUNK_ID_FRAME_ID,
+                frame_info.block_id.to_le_bytes(),
+            ),
+            (
+                self.chunk_enable_exposure_time,
+                genapi::CHUNK_SELECTOR_EXPOSURE_TIME,
+                genapi::CHUNK_ID_EXPOSURE_TIME,
+                self.exposure_time.to_le_bytes(),
+            ),
+            (
+                self.chunk_enable_gain,
+                genapi::CHUNK_SELECTOR_GAIN,
+                genapi::CHUNK_ID_GAIN,
+                self.gain.to_le_bytes(),
+            ),
+        ];
+        for (enabled, selector, chunk_id, data) in &chunks {
+            if *enabled {
+                payload.extend_from_slice(data);
+                stream_packet::write_chunk_trailer(&mut payload, *chunk_id, data.len());
+                chunk_layout_id |= 1 << selector;
+            }
+        }
+
+        (payload, chunk_layout_id)
+    }
+}
+
+/// Transfer sizes read from `SIRM`.
+struct TransferParams {
+    maximum_leader_size: usize,
+    payload_transfer_size: usize,
+    payload_transfer_count: usize,
+    payload_final_transfer1_size: usize,
+    payload_final_transfer2_size: usize,
+}
+
+impl TransferParams {
+    fn from_memory(memory: &Memory) -> cameleon_impl::memory::MemoryResult<Self> {
+        Ok(Self {
+            maximum_leader_size: memory.read::<SIRM::MaximumLeaderSize>()? as usize,
+            payload_transfer_size: memory.read::<SIRM::PayloadTransferSize>()? as usize,
+            payload_transfer_count: memory.read::<SIRM::PayloadTransferCount>()? as usize,
+            payload_final_transfer1_size: memory.read::<SIRM::PayloadFinalTransferSize1>()?
+                as usize,
+            payload_final_transfer2_size: memory.read::<SIRM::PayloadFinalTransferSize2>()?
+                as usize,
+        })
+    }
+
+    /// Return sizes of payload transfers in the order they are sent.
+    fn transfer_sizes(&self) -> impl Iterator<Item = usize> {
+        let transfer_size = self.payload_transfer_size;
+        (0..self.payload_transfer_count)
+            .map(move |_| transfer_size)
+            .chain(std::iter::once(self.payload_final_transfer1_size))
+            .chain(std::iter::once(self.payload_final_transfer2_size))
+            .filter(|size| *size != 0)
+    }
+}
+
+mod stream_packet {
+    use std::io::Write;
+
+    use crate::u3v::protocol::util::WriteBytes;
+
+    use super::FrameInfo;
+
+    #[derive(Clone, Copy)]
+    pub(super) enum PayloadStatus {
+        Success,
+        DataDiscarded,
+        DataOverrun,
+    }
+
+    const PAYLOAD_TYPE_IMAGE: u16 = 0x0001;
+    const PAYLOAD_TYPE_IMAGE_EXTENDED_CHUNK: u16 = 0x4001;
+
+    pub(super) struct Leader<'a> {
+        frame_info: &'a FrameInfo,
+        payload_type: u16,
+    }
+
+    pub(super) fn image_leader(frame_info: &FrameInfo) -> Leader<'_> {
+        Leader {
+            frame_info,
+            payload_type: PAYLOAD_TYPE_IMAGE,
+        }
+    }
+
+    /// Image extended chunk leader has the same layout as image leader.
+    pub(super) fn image_extended_chunk_leader(frame_info: &FrameInfo) -> Leader<'_> {
+        Leader {
+            frame_info,
+            payload_type: PAYLOAD_TYPE_IMAGE_EXTENDED_CHUNK,
+        }
+    }
+
+    impl<'a> Leader<'a> {
+        const LEADER_MAGIC: u32 = 0x4C56_3355;
+        /// Generic leader(20bytes) + image leader(32bytes).
+        const LEADER_SIZE: u16 = 52;
+
+        pub(super) fn serialize(&self, mut buf: impl Write) -> std::io::Result<()> {
+            buf.write_bytes(Self::LEADER_MAGIC)?;
+            buf.write_bytes(0_u16)?;
+            buf.write_bytes(Self::LEADER_SIZE)?;
+            let info = self.frame_info;
+            buf.write_bytes(info.block_id)?;
+            buf.write_bytes(0_u16)?;
+            buf.write_bytes(self.payload_type)?;
+
+            buf.write_bytes(info.timestamp)?;
+            buf.write_bytes::<u32>(info.pixel_format.into())?;
+            buf.write_bytes(info.width)?;
+            buf.write_bytes(info.height)?;
+            buf.write_bytes(info.x_offset)?;
+            buf.write_bytes(info.y_offset)?;
+            // X padding.
+            buf.write_bytes(0_u16)?;
+            buf.write_bytes(0_u16)?;
+            Ok(())
+        }
+    }
+
+    pub(super) struct Trailer {
+        block_id: u64,
+        status: PayloadStatus,
+        valid_payload_size: u64,
+        actual_height: u32,
+        /// `Some` if the payload type is image extended chunk.
+        chunk_layout_id: Option<u32>,
+    }
+
+    pub(super) fn image_trailer(
+        block_id: u64,
+        status: PayloadStatus,
+        valid_payload_size: u64,
+        actual_height: u32,
+    ) -> Trailer {
+        Trailer {
+            block_id,
+            status,
+            valid_payload_size,
+            actual_height,
+            chunk_layout_id: None,
+        }
+    }
+
+    pub(super) fn image_extended_chunk_trailer(
+        block_id: u64,
+        status: PayloadStatus,
+        valid_payload_size: u64,
+        actual_height: u32,
+        chunk_layout_id: u32,
+    ) -> Trailer {
+        Trailer {
+            block_id,
+            status,
+            valid_payload_size,
+            actual_height,
+            chunk_layout_id: Some(chunk_layout_id),
+        }
+    }
+
+    impl Trailer {
+        /// Report that the rest of the payload is discarded after `valid_payload_size` bytes are
+        /// sent.
+        pub(super) fn discard(&mut self, valid_payload_size: u64) {
+            self.status = PayloadStatus::DataDiscarded;
+            self.valid_payload_size = valid_payload_size;
+        }
+
+        const TRAILER_MAGIC: u32 = 0x5456_3355;
+        /// Generic trailer(28bytes) + image trailer(4bytes).
+        const IMAGE_TRAILER_SIZE: u16 = 32;
+        /// Generic trailer(28bytes) + image extended chunk trailer(8bytes).
+        const IMAGE_EXTENDED_CHUNK_TRAILER_SIZE: u16 = 36;
+
+        pub(super) fn serialize(&self, mut buf: impl Write) -> std::io::Result<()> {
+            let status: u16 = match self.status {
+                PayloadStatus::Success => 0x0000,
+                PayloadStatus::DataDiscarded => 0xA100,
+                PayloadStatus::DataOverrun => 0xA101,
+            };
+
+            buf.write_bytes(Self::TRAILER_MAGIC)?;
+            buf.write_bytes(0_u16)?;
+            let trailer_size = if self.chunk_layout_id.is_some() {
+                Self::IMAGE_EXTENDED_CHUNK_TRAILER_SIZE
+            } else {
+                Self::IMAGE_TRAILER_SIZE
+            };
+            buf.write_bytes(trailer_size)?;
+            buf.write_bytes(self.block_id)?;
+            buf.write_bytes(status)?;
+            buf.write_bytes(0_u16)?;
+            buf.write_bytes(self.valid_payload_size)?;
+            buf.write_bytes(self.actual_height)?;
+            if let Some(chunk_layout_id) = self.chunk_layout_id {
+                buf.write_bytes(chunk_layout_id)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Append chunk id and chunk length to the chunk data in `buf`.
+    ///
+    /// Chunk id and chunk length are written in big endian so that the host can parse chunks from
+    /// the end of the payload.
+    pub(super) fn write_chunk_trailer(buf: &mut Vec<u8>, chunk_id: u32, chunk_len: usize) {
+        buf.extend_from_slice(&chunk_id.to_be_bytes());
+        buf.extend_from_slice(&(chunk_len as u32).to_be_bytes());
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::{u3v::protocol::stream, PixelFormat};
+
+        #[test]
+        fn test_image_leader() {
+            let info = FrameInfo {
+                block_id: 10,
+                timestamp: 123_456_789,
+                pixel_format: PixelFormat::Mono8,
+                width: 640,
+                height: 480,
+                x_offset: 16,
+                y_offset: 8,
+            };
+            let mut buf = vec![];
+            image_leader(&info).serialize(&mut buf).unwrap();
+
+            let leader = stream::Leader::parse(&buf).unwrap();
+            assert_eq!(leader.leader_size() as usize, buf.len());
+            assert_eq!(leader.block_id(), 10);
+            assert_eq!(leader.payload_type(), stream::PayloadType::Image);
+
+            let image_leader: stream::ImageLeader = leader.specific_leader_as().unwrap();
+            assert_eq!(image_leader.timestamp().as_nanos(), 123_456_789);
+            assert_eq!(image_leader.pixel_format(), PixelFormat::Mono8);
+            assert_eq!(image_leader.width(), 640);
+            assert_eq!(image_leader.height(), 480);
+            assert_eq!(image_leader.x_offset(), 16);
+            assert_eq!(image_leader.y_offset(), 8);
+        }
+
+        #[test]
+        fn test_image_trailer() {
+            let mut buf = vec![];
+            image_trailer(10, PayloadStatus::DataOverrun, 1024, 480)
+                .serialize(&mut buf)
+                .unwrap();
+
+            let trailer = stream::Trailer::parse(&buf).unwrap();
+            assert_eq!(trailer.trailer_size() as usize, buf.len());
+            assert_eq!(trailer.block_id(), 10);
+            assert_eq!(trailer.payload_status(), stream::PayloadStatus::DataOverrun);
+            assert_eq!(trailer.valid_payload_size(), 1024);
+
+            let image_trailer: stream::ImageTrailer = trailer.specific_trailer_as().unwrap();
+            assert_eq!(image_trailer.actual_height(), 480);
+        }
+
+        #[test]
+        fn test_discard_trailer() {
+            let mut trailer = image_trailer(10, PayloadStatus::Success, 1024, 480);
+            trailer.discard(512);
+            let mut buf = vec![];
+            trailer.serialize(&mut buf).unwrap();
+
+            let trailer = stream::Trailer::parse(&buf).unwrap();
+            assert_eq!(
+                trailer.payload_status(),
+                stream::PayloadStatus::DataDiscarded
+            );
+            assert_eq!(trailer.valid_payload_size(), 512);
+        }
+
+        #[test]
+        fn test_image_extended_chunk() {
+            let info = FrameInfo {
+                block_id: 10,
+                timestamp: 123_456_789,
+                pixel_format: PixelFormat::Mono8,
+                width: 640,
+                height: 480,
+                x_offset: 0,
+                y_offset: 0,
+            };
+            let mut buf = vec![];
+            image_extended_chunk_leader(&info)
+                .serialize(&mut buf)
+                .unwrap();
+            let leader = stream::Leader::parse(&buf).unwrap();
+            assert_eq!(
+                leader.payload_type(),
+                stream::PayloadType::ImageExtendedChunk
+            );
+            let chunk_leader: stream::ImageExtendedChunkLeader =
+                leader.specific_leader_as().unwrap();
+            assert_eq!(chunk_leader.width(), 640);
+
+            let mut buf = vec![];
+            image_extended_chunk_trailer(10, PayloadStatus::Success, 1024, 480, 0b11)
+                .serialize(&mut buf)
+                .unwrap();
+            let trailer = stream::Trailer::parse(&buf).unwrap();
+            assert_eq!(trailer.trailer_size() as usize, buf.len());
+            let chunk_trailer: stream::ImageExtendedChunkTrailer =
+                trailer.specific_trailer_as().unwrap();
+            assert_eq!(chunk_trailer.actual_height(), 480);
+            assert_eq!(chunk_trailer.chunk_layout_id(), 0b11);
+        }
+
+        #[test]
+        fn test_write_chunk_trailer() {
+            let mut buf = vec![1, 2, 3];
+            write_chunk_trailer(&mut buf, 0x4000_0001, 3);
+            assert_eq!(
+                buf,
+                &[1, 2, 3, 0x40, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03]
+            );
+        }
+    }
 }
@@ -2,13 +2,19 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+mod async_read;
 mod channel;
 mod device;
 mod emulator_impl;
 
+pub use async_read::AsyncPool;
+pub use cameleon_impl::memory::MemorySnapshot;
 pub use channel::{ControlChannel, ReceiveChannel};
 pub use device::Device;
-pub use emulator_impl::{BuilderError, BuilderResult, EmulatorBuilder};
+pub use emulator_impl::{
+    bandwidth, clock, event, fault, frame_source, BuilderError, BuilderResult, EmulatorBuilder,
+    RegisterImage,
+};
 
 use crate::u3v::Result;
 
@@ -27,3 +33,101 @@ pub fn enumerate_devices() -> Result<Vec<Device>> {
 
     Ok(devices)
 }
+
+/// Disconnect an emulated device which has the serial number, as if the cable is unplugged.
+///
+/// All channels opened on the device start returning [`LibUsbError::NoDevice`](crate::u3v::LibUsbError::NoDevice), and the device
+/// disappears from [`enumerate_devices`](crate::u3v::enumerate_devices) until [`reconnect_device`] is called.
+///
+/// # Errors
+/// If no connected device has the serial number, then [`LibUsbError::NotFound`](crate::u3v::LibUsbError::NotFound) is
+/// returned.
+///
+/// # Example
+/// ```rust
+/// use cameleon_device::u3v::{self, EmulatorBuilder};
+///
+/// EmulatorBuilder::new().serial_number("CAM0042").unwrap().build();
+///
+/// u3v::disconnect_device("CAM0042").unwrap();
+/// assert!(u3v::enumerate_devices()
+///     .unwrap()
+///     .iter()
+///     .all(|dev| dev.device_info.serial_number != "CAM0042"));
+///
+/// u3v::reconnect_device("CAM0042").unwrap();
+/// assert!(u3v::enumerate_devices()
+///     .unwrap()
+///     .iter()
+///     .any(|dev| dev.device_info.serial_number == "CAM0042"));
+/// ```
+pub fn disconnect_device(serial_number: &str) -> Result<()> {
+    emulator_impl::DevicePool::with(|pool| pool.disconnect(serial_number))
+}
+
+/// Trigger events on an emulated device which has the serial number.
+///
+/// Events are sent to the host only while the event interface of the device is enabled. See
+/// [`event`] for details.
+///
+/// # Errors
+/// If no connected device has the serial number, then
+/// [`LibUsbError::NotFound`](crate::u3v::LibUsbError::NotFound) is returned.
+/// If too many events are waiting to be processed by the device, then
+/// [`LibUsbError::Busy`](crate::u3v::LibUsbError::Busy) is returned.
+pub fn trigger_events(
+    serial_number: &str,
+    events: impl IntoIterator<Item = event::Event>,
+) -> Result<()> {
+    let events = events.into_iter().collect();
+    emulator_impl::DevicePool::with(|pool| pool.trigger_events(serial_number, events))
+}
+
+/// Take a snapshot of the memory of an emulated device which has the serial number.
+///
+/// The snapshot contains the current values and access rights of all registers of the device,
+/// and can be restored to another emulated device by [`EmulatorBuilder::memory_snapshot`].
+///
+/// NOTE: Registers provided by [`RegisterImage`] aren't contained in the snapshot.
+///
+/// # Errors
+/// If no connected device has the serial number, then
+/// [`LibUsbError::NotFound`](crate::u3v::LibUsbError::NotFound) is returned.
+///
+/// # Examples
+///
+/// ```rust
+/// use cameleon_device::u3v::{self, EmulatorBuilder};
+///
+/// EmulatorBuilder::new().serial_number("CAM0051").unwrap().build();
+///
+/// let snapshot = u3v::snapshot_device("CAM0051").unwrap();
+/// let path = std::env::temp_dir().join("CAM0051.snapshot");
+/// snapshot.save(&path).unwrap();
+///
+/// // The new device starts with the state of `CAM0051` except for the serial number.
+/// EmulatorBuilder::new()
+///     .memory_snapshot(u3v::MemorySnapshot::load(&path).unwrap())
+///     .unwrap()
+///     .serial_number("CAM0052")
+///     .unwrap()
+///     .build();
+/// # std::fs::remove_file(path).ok();
+/// ```
+pub fn snapshot_device(serial_number: &str) -> Result<MemorySnapshot> {
+    emulator_impl::DevicePool::with(|pool| pool.snapshot(serial_number))
+}
+
+/// Reconnect an emulated device which was disconnected by [`disconnect_device`], as if the cable
+/// is plugged in again.
+///
+/// The device is powered on again, so its registers are reset to the values configured by
+/// [`EmulatorBuilder`]. The reconnected device must be found again by [`enumerate_devices`](crate::u3v::enumerate_devices);
+/// channels opened before disconnection stay unusable.
+///
+/// # Errors
+/// If no disconnected device has the serial number, then [`LibUsbError::NotFound`](crate::u3v::LibUsbError::NotFound) is
+/// returned.
+pub fn reconnect_device(serial_number: &str) -> Result<()> {
+    emulator_impl::DevicePool::with(|pool| pool.reconnect(serial_number))
+}
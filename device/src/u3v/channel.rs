@@ -6,167 +6,112 @@ use std::time;
 
 use crate::u3v::Result;
 
-use super::device::LibUsbDeviceHandle;
+#[cfg(feature = "emulator")]
+use crate::emulator;
 
-pub struct ControlChannel {
-    pub(super) device_handle: LibUsbDeviceHandle,
-    pub iface_info: ControlIfaceInfo,
-    pub is_opened: bool,
+#[cfg(feature = "libusb")]
+use super::libusb;
+
+pub struct ControlChannel(pub(super) ControlChannelKind);
+
+pub(super) enum ControlChannelKind {
+    #[cfg(feature = "libusb")]
+    LibUsb(libusb::ControlChannel),
+    #[cfg(feature = "emulator")]
+    Emulator(emulator::ControlChannel),
 }
 
 impl ControlChannel {
     pub fn open(&mut self) -> Result<()> {
-        if !self.is_opened() {
-            self.device_handle
-                .claim_interface(self.iface_info.iface_number)?;
-            self.is_opened = true;
-        }
-
-        Ok(())
+        dispatch!(ControlChannelKind, &mut self.0, inner => inner.open())
     }
 
     pub fn close(&mut self) -> Result<()> {
-        if self.is_opened() {
-            self.device_handle
-                .release_interface(self.iface_info.iface_number)?;
-            self.is_opened = false;
-        }
-
-        Ok(())
+        dispatch!(ControlChannelKind, &mut self.0, inner => inner.close())
     }
 
     #[must_use]
     pub fn is_opened(&self) -> bool {
-        self.is_opened
+        dispatch!(ControlChannelKind, &self.0, inner => inner.is_opened())
     }
 
     pub fn send(&self, buf: &[u8], timeout: time::Duration) -> Result<usize> {
-        Ok(self
-            .device_handle
-            .write_bulk(self.iface_info.bulk_out_ep, buf, timeout)?)
+        dispatch!(ControlChannelKind, &self.0, inner => inner.send(buf, timeout))
     }
 
     pub fn recv(&self, buf: &mut [u8], timeout: time::Duration) -> Result<usize> {
-        Ok(self
-            .device_handle
-            .read_bulk(self.iface_info.bulk_in_ep, buf, timeout)?)
+        dispatch!(ControlChannelKind, &self.0, inner => inner.recv(buf, timeout))
     }
 
     pub fn set_halt(&self, timeout: time::Duration) -> Result<()> {
-        set_halt(&self.device_handle, self.iface_info.bulk_in_ep, timeout)?;
-        set_halt(&self.device_handle, self.iface_info.bulk_out_ep, timeout)?;
-
-        Ok(())
+        dispatch!(ControlChannelKind, &self.0, inner => inner.set_halt(timeout))
     }
 
     pub fn clear_halt(&mut self) -> Result<()> {
-        self.device_handle.clear_halt(self.iface_info.bulk_in_ep)?;
-        self.device_handle.clear_halt(self.iface_info.bulk_out_ep)?;
-        Ok(())
-    }
-
-    pub(super) fn new(device_handle: LibUsbDeviceHandle, iface_info: ControlIfaceInfo) -> Self {
-        Self {
-            device_handle,
-            iface_info,
-            is_opened: false,
-        }
+        dispatch!(ControlChannelKind, &mut self.0, inner => inner.clear_halt())
     }
 }
 
-pub struct ReceiveChannel {
-    pub(super) device_handle: LibUsbDeviceHandle,
-    pub iface_info: ReceiveIfaceInfo,
-    pub is_opened: bool,
+pub struct ReceiveChannel(pub(super) ReceiveChannelKind);
+
+pub(super) enum ReceiveChannelKind {
+    #[cfg(feature = "libusb")]
+    LibUsb(libusb::ReceiveChannel),
+    #[cfg(feature = "emulator")]
+    Emulator(emulator::ReceiveChannel),
 }
 
 impl ReceiveChannel {
     pub fn open(&mut self) -> Result<()> {
-        if !self.is_opened() {
-            self.device_handle
-                .claim_interface(self.iface_info.iface_number)?;
-            self.is_opened = true;
-        }
-
-        Ok(())
+        dispatch!(ReceiveChannelKind, &mut self.0, inner => inner.open())
     }
 
     pub fn close(&mut self) -> Result<()> {
-        if self.is_opened() {
-            self.device_handle
-                .release_interface(self.iface_info.iface_number)?;
-        }
-
-        self.is_opened = false;
-        Ok(())
+        dispatch!(ReceiveChannelKind, &mut self.0, inner => inner.close())
     }
 
     #[must_use]
     pub fn is_opened(&self) -> bool {
-        self.is_opened
+        dispatch!(ReceiveChannelKind, &self.0, inner => inner.is_opened())
     }
 
     pub fn recv(&self, buf: &mut [u8], timeout: time::Duration) -> Result<usize> {
-        Ok(self
-            .device_handle
-            .read_bulk(self.iface_info.bulk_in_ep, buf, timeout)?)
+        dispatch!(ReceiveChannelKind, &self.0, inner => inner.recv(buf, timeout))
     }
 
     pub fn set_halt(&self, timeout: time::Duration) -> Result<()> {
-        set_halt(&self.device_handle, self.iface_info.bulk_in_ep, timeout)?;
-
-        Ok(())
+        dispatch!(ReceiveChannelKind, &self.0, inner => inner.set_halt(timeout))
     }
 
     pub fn clear_halt(&mut self) -> Result<()> {
-        self.device_handle.clear_halt(self.iface_info.bulk_in_ep)?;
-        Ok(())
+        dispatch!(ReceiveChannelKind, &mut self.0, inner => inner.clear_halt())
     }
+}
 
-    pub(super) fn new(device_handle: LibUsbDeviceHandle, iface_info: ReceiveIfaceInfo) -> Self {
-        Self {
-            device_handle,
-            iface_info,
-            is_opened: false,
-        }
+#[cfg(feature = "libusb")]
+impl From<libusb::ControlChannel> for ControlChannel {
+    fn from(channel: libusb::ControlChannel) -> Self {
+        Self(ControlChannelKind::LibUsb(channel))
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct ControlIfaceInfo {
-    pub iface_number: u8,
-    pub bulk_in_ep: u8,
-    pub bulk_out_ep: u8,
+#[cfg(feature = "emulator")]
+impl From<emulator::ControlChannel> for ControlChannel {
+    fn from(channel: emulator::ControlChannel) -> Self {
+        Self(ControlChannelKind::Emulator(channel))
+    }
 }
 
-#[derive(Clone, Debug)]
-pub struct ReceiveIfaceInfo {
-    pub iface_number: u8,
-    pub bulk_in_ep: u8,
+#[cfg(feature = "libusb")]
+impl From<libusb::ReceiveChannel> for ReceiveChannel {
+    fn from(channel: libusb::ReceiveChannel) -> Self {
+        Self(ReceiveChannelKind::LibUsb(channel))
+    }
 }
 
-fn set_halt(
-    handle: &LibUsbDeviceHandle,
-    endpoint_number: u8,
-    timeout: time::Duration,
-) -> Result<()> {
-    let request_type = rusb::request_type(
-        rusb::Direction::Out,
-        rusb::RequestType::Standard,
-        rusb::Recipient::Endpoint,
-    );
-    let request = 0x03; // SET_FEATURE.
-    let value = 0x00; // ENDPOINT_HALT.
-    let buf = vec![]; // NO DATA.
-
-    handle.write_control(
-        request_type,
-        request,
-        value,
-        u16::from(endpoint_number),
-        &buf,
-        timeout,
-    )?;
-
-    Ok(())
+#[cfg(feature = "emulator")]
+impl From<emulator::ReceiveChannel> for ReceiveChannel {
+    fn from(channel: emulator::ReceiveChannel) -> Self {
+        Self(ReceiveChannelKind::Emulator(channel))
+    }
 }
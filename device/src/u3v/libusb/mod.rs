@@ -0,0 +1,44 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! `libusb` backend of U3V devices.
+
+pub(super) mod async_read;
+
+mod channel;
+mod device;
+mod device_builder;
+
+pub(super) use channel::{ControlChannel, ReceiveChannel};
+pub(super) use device::Device;
+pub(super) use device_builder::enumerate_devices;
+
+use super::{Error, LibUsbError};
+
+impl From<rusb::Error> for Error {
+    fn from(err: rusb::Error) -> Error {
+        use LibUsbError::{
+            Access, BadDescriptor, Busy, Interrupted, InvalidParam, Io, NoDevice, NoMem, NotFound,
+            NotSupported, Other, Overflow, Pipe, Timeout,
+        };
+        let kind = match err {
+            rusb::Error::Io => Io,
+            rusb::Error::InvalidParam => InvalidParam,
+            rusb::Error::Access => Access,
+            rusb::Error::NoDevice => NoDevice,
+            rusb::Error::NotFound => NotFound,
+            rusb::Error::Busy => Busy,
+            rusb::Error::Timeout => Timeout,
+            rusb::Error::Overflow => Overflow,
+            rusb::Error::Pipe => Pipe,
+            rusb::Error::Interrupted => Interrupted,
+            rusb::Error::NoMem => NoMem,
+            rusb::Error::NotSupported => NotSupported,
+            rusb::Error::BadDescriptor => BadDescriptor,
+            rusb::Error::Other => Other,
+        };
+
+        Error::LibUsb(kind)
+    }
+}
@@ -2,6 +2,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+/// Dispatches a method call to the backend which a device or a channel belongs to.
+macro_rules! dispatch {
+    ($kind:ident, $self:expr, $inner:ident => $expr:expr) => {{
+        match $self {
+            #[cfg(feature = "libusb")]
+            $kind::LibUsb($inner) => $expr,
+            #[cfg(feature = "emulator")]
+            $kind::Emulator($inner) => $expr,
+        }
+    }};
+}
+
 pub mod async_read;
 pub mod protocol;
 pub mod register_map;
@@ -14,14 +26,21 @@ pub mod prelude {
 
 mod channel;
 mod device;
-mod device_builder;
 mod device_info;
+#[cfg(feature = "libusb")]
+mod libusb;
 
 pub use channel::{ControlChannel, ReceiveChannel};
-pub use device::Device;
-pub use device_builder::enumerate_devices;
+pub use device::{enumerate_devices, Device};
 pub use device_info::{BusSpeed, DeviceInfo};
 
+#[cfg(feature = "emulator")]
+pub use crate::emulator::{
+    bandwidth, clock, disconnect_device, event, fault, frame_source, reconnect_device,
+    snapshot_device, trigger_events, BuilderError, BuilderResult, EmulatorBuilder, MemorySnapshot,
+    RegisterImage,
+};
+
 use std::borrow::Cow;
 
 use thiserror::Error;
@@ -75,30 +94,3 @@ pub enum LibUsbError {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
-
-impl From<rusb::Error> for Error {
-    fn from(err: rusb::Error) -> Error {
-        use LibUsbError::{
-            Access, BadDescriptor, Busy, Interrupted, InvalidParam, Io, NoDevice, NoMem, NotFound,
-            NotSupported, Other, Overflow, Pipe, Timeout,
-        };
-        let kind = match err {
-            rusb::Error::Io => Io,
-            rusb::Error::InvalidParam => InvalidParam,
-            rusb::Error::Access => Access,
-            rusb::Error::NoDevice => NoDevice,
-            rusb::Error::NotFound => NotFound,
-            rusb::Error::Busy => Busy,
-            rusb::Error::Timeout => Timeout,
-            rusb::Error::Overflow => Overflow,
-            rusb::Error::Pipe => Pipe,
-            rusb::Error::Interrupted => Interrupted,
-            rusb::Error::NoMem => NoMem,
-            rusb::Error::NotSupported => NotSupported,
-            rusb::Error::BadDescriptor => BadDescriptor,
-            rusb::Error::Other => Other,
-        };
-
-        Error::LibUsb(kind)
-    }
-}
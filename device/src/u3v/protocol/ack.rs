@@ -206,7 +206,7 @@ impl Status {
     fn parse(cursor: &mut Cursor<&[u8]>) -> Result<Self> {
         let code: u16 = cursor.read_bytes()?;
 
-        let namespace = (code >> 13) & 0x11;
+        let namespace = (code >> 13) & 0b11;
         match namespace {
             0b00 => Self::parse_gencp_status(code),
             0b01 => Self::parse_usb_status(code),
@@ -531,4 +531,21 @@ mod tests {
             _ => panic!("must be USB specific error status"),
         }
     }
+
+    #[test]
+    fn test_device_specific_error_status() {
+        let mut code_buf = vec![0; 2];
+
+        code_buf.as_mut_slice().write_bytes(0xC001_u16).unwrap();
+        let mut code = Cursor::new(code_buf.as_slice());
+        let status = Status::parse(&mut code).unwrap();
+        assert!(!status.is_success());
+        assert!(status.is_fatal());
+        assert_eq!(status.code(), 0xC001);
+        assert_eq!(status.kind, StatusKind::DeviceSpecific);
+
+        code_buf.as_mut_slice().write_bytes(0xE001_u16).unwrap();
+        let mut code = Cursor::new(code_buf.as_slice());
+        assert!(Status::parse(&mut code).is_err());
+    }
 }
@@ -6,7 +6,7 @@ use super::{
     parser,
     store::{
         CacheSink, DefaultCacheStore, DefaultNodeStore, DefaultValueStore, NodeData, NodeId,
-        ValueData, ValueId,
+        NodeStore, ValueData, ValueId,
     },
     RegisterDescription, ValueCtxt,
 };
@@ -33,6 +33,7 @@ impl<T, U, S> GenApiBuilder<T, U, S> {
     pub fn build(mut self, xml: &impl AsRef<str>) -> BuildResult<T::Store, U::Store, S::Store>
     where
         T: NodeStoreBuilder,
+        T::Store: NodeStore,
         U: ValueStoreBuilder,
         S: CacheStoreBuilder,
     {
@@ -43,11 +44,11 @@ impl<T, U, S> GenApiBuilder<T, U, S> {
             &mut self.cache_store,
         )?;
 
-        Ok((
-            reg_desc,
-            self.node_store.build(),
-            ValueCtxt::new(self.value_store.build(), self.cache_store.build()),
-        ))
+        let node_store = self.node_store.build();
+        let mut value_ctxt = ValueCtxt::new(self.value_store.build(), self.cache_store.build());
+        value_ctxt.bind_event_ports(&node_store);
+
+        Ok((reg_desc, node_store, value_ctxt))
     }
 
     pub fn no_cache(self) -> GenApiBuilder<T, U, CacheSink> {
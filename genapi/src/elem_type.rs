@@ -310,14 +310,15 @@ impl RegPIndex {
         store: &impl NodeStore,
         cx: &mut ValueCtxt<T, U>,
     ) -> GenApiResult<i64> {
-        let base = self
+        // Offset is a stride of the index, and defaults to 1.
+        let index = self
             .p_index
             .expect_iinteger_kind(store)?
             .value(device, store, cx)?;
         if let Some(offset) = &self.offset {
-            Ok(base + offset.value(device, store, cx)?)
+            Ok(index * offset.value(device, store, cx)?)
         } else {
-            Ok(base)
+            Ok(index)
         }
     }
 }
@@ -339,3 +340,69 @@ pub enum BitMask {
     SingleBit(u64),
     Range { lsb: u64, msb: u64 },
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::GenApiBuilder,
+        interface::IInteger,
+        store::NodeStore,
+        utils::tests::{register_description, TestDevice},
+    };
+
+    const P_INDEX_NODES: &str = r#"
+    <Integer Name="Index">
+        <Value>3</Value>
+    </Integer>
+
+    <IntReg Name="WithOffset">
+        <Address>0x100</Address>
+        <pIndex Offset="4">Index</pIndex>
+        <Length>4</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>Device</pPort>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <IntReg Name="WithoutOffset">
+        <Address>0x180</Address>
+        <pIndex>Index</pIndex>
+        <Length>4</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>Device</pPort>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <Port Name="Device" NameSpace="Standard">
+    </Port>
+"#;
+
+    #[test]
+    fn test_p_index_offset() {
+        let (_, store, mut cx) = GenApiBuilder::default()
+            .build(&register_description(P_INDEX_NODES))
+            .unwrap();
+        let mut device = TestDevice::new(0x200);
+        // Offset is multiplied by the index: 0x100 + 3 * 4.
+        device.memory[0x10C..0x110].copy_from_slice(&42_u32.to_le_bytes());
+        // Offset defaults to 1: 0x180 + 3.
+        device.memory[0x183..0x187].copy_from_slice(&7_u32.to_le_bytes());
+
+        let with_offset = store
+            .id_by_name("WithOffset")
+            .unwrap()
+            .expect_iinteger_kind(&store)
+            .unwrap();
+        assert_eq!(with_offset.value(&mut device, &store, &mut cx).unwrap(), 42);
+
+        let without_offset = store
+            .id_by_name("WithoutOffset")
+            .unwrap()
+            .expect_iinteger_kind(&store)
+            .unwrap();
+        assert_eq!(
+            without_offset.value(&mut device, &store, &mut cx).unwrap(),
+            7
+        );
+    }
+}
@@ -66,7 +66,7 @@ pub use string::StringNode;
 pub use string_reg::StringRegNode;
 pub use swiss_knife::SwissKnifeNode;
 
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap};
 
 use auto_impl::auto_impl;
 use tracing::error;
@@ -87,6 +87,9 @@ pub trait Device {
 
 #[derive(Debug, thiserror::Error)]
 pub enum GenApiError {
+    /// An error returned by [`Device`].
+    ///
+    /// The error is kept as is, so it can be downcast to the concrete error type of the device.
     #[error("device I/O error: {0}")]
     Device(Box<dyn std::error::Error>),
 
@@ -108,6 +111,10 @@ pub enum GenApiError {
     #[error("chunk data missing")]
     ChunkDataMissing,
 
+    /// Operation on the node failed because no event has been attached to the event port yet.
+    #[error("event data missing")]
+    EventDataMissing,
+
     /// Invalid buffer.
     #[error("invalid buffer: {0}")]
     InvalidBuffer(Cow<'static, str>),
@@ -144,6 +151,12 @@ impl GenApiError {
         err
     }
 
+    fn event_data_missing() -> Self {
+        let err = GenApiError::EventDataMissing;
+        error!("{}", err);
+        err
+    }
+
     fn invalid_buffer(inner: Cow<'static, str>) -> Self {
         let err = GenApiError::InvalidBuffer(inner);
         error!("{}", err);
@@ -153,17 +166,49 @@ impl GenApiError {
 
 pub type GenApiResult<T> = std::result::Result<T, GenApiError>;
 
+/// Context which holds values and caches of nodes.
+///
+/// `ValueCtxt` has private fields, so construct it with [`ValueCtxt::new`] or
+/// [`builder::GenApiBuilder`].
 #[derive(Clone, Debug)]
 pub struct ValueCtxt<T, U> {
     pub value_store: T,
     pub cache_store: U,
+    /// Event ports, i.e. `Port` nodes which have `EventID`, keyed by the `EventID`.
+    event_ports: HashMap<u64, Vec<store::NodeId>>,
+    /// Registers mapped onto each event port.
+    event_registers: HashMap<store::NodeId, Vec<store::NodeId>>,
+    /// Event data attached to event ports.
+    event_data: HashMap<store::NodeId, Vec<u8>>,
 }
 
 impl<T, U> ValueCtxt<T, U> {
+    /// Constructs new `ValueCtxt`.
+    ///
+    /// Call [`ValueCtxt::bind_event_ports`] to make [`ValueCtxt::attach_event`] work, which is
+    /// done by [`builder::GenApiBuilder`]. Use [`ValueCtxt::map_cache_store`] to replace the cache
+    /// store of an existing context while keeping the bound event ports.
     pub fn new(value_store: T, cache_store: U) -> Self {
         Self {
             value_store,
             cache_store,
+            event_ports: HashMap::new(),
+            event_registers: HashMap::new(),
+            event_data: HashMap::new(),
+        }
+    }
+
+    /// Replaces the cache store with the one converted by `f`.
+    ///
+    /// Unlike [`ValueCtxt::new`], the event ports bound to the context and the event data
+    /// attached to them are kept.
+    pub fn map_cache_store<U2>(self, f: impl FnOnce(U) -> U2) -> ValueCtxt<T, U2> {
+        ValueCtxt {
+            value_store: self.value_store,
+            cache_store: f(self.cache_store),
+            event_ports: self.event_ports,
+            event_registers: self.event_registers,
+            event_data: self.event_data,
         }
     }
 
@@ -221,4 +266,80 @@ impl<T, U> ValueCtxt<T, U> {
     {
         self.cache_store.clear()
     }
+
+    /// Returns the event data attached to the event port.
+    pub fn event_data(&self, nid: store::NodeId) -> Option<&[u8]> {
+        self.event_data.get(&nid).map(AsRef::as_ref)
+    }
+
+    /// Collects event ports and registers mapped onto them from `store`.
+    ///
+    /// The result is used by [`ValueCtxt::attach_event`] so that attaching an event doesn't walk
+    /// the whole node store.
+    pub fn bind_event_ports(&mut self, store: &impl NodeStore) {
+        self.event_ports.clear();
+        self.event_registers.clear();
+
+        let mut registers = vec![];
+        store.visit_nodes(|node| {
+            let register_base = match node {
+                store::NodeData::Port(_) => {
+                    let node_base = node.node_base();
+                    if let Some(event_id) = node_base.event_id() {
+                        self.event_ports
+                            .entry(event_id)
+                            .or_default()
+                            .push(node_base.id());
+                    }
+                    return;
+                }
+                store::NodeData::IntReg(n) => n.register_base(),
+                store::NodeData::MaskedIntReg(n) => n.register_base(),
+                store::NodeData::FloatReg(n) => n.register_base(),
+                store::NodeData::StringReg(n) => n.register_base(),
+                store::NodeData::Register(n) => n.register_base(),
+                _ => return,
+            };
+            registers.push((register_base.p_port(), node.node_base().id()));
+        });
+
+        for ports in self.event_ports.values() {
+            for port in ports {
+                self.event_registers.insert(*port, vec![]);
+            }
+        }
+        for (port, nid) in registers {
+            if let Some(event_registers) = self.event_registers.get_mut(&port) {
+                event_registers.push(nid);
+            }
+        }
+    }
+
+    /// Attaches `data` to all event ports whose `EventID` is `event_id`, then invalidates caches
+    /// of the registers which are mapped onto the ports and the nodes invalidated by the ports.
+    ///
+    /// Returns `false` if no event port has the `event_id`.
+    pub fn attach_event(&mut self, event_id: u64, data: &[u8]) -> bool
+    where
+        U: store::CacheStore,
+    {
+        let ports = match self.event_ports.get(&event_id) {
+            Some(ports) => ports,
+            None => return false,
+        };
+
+        for nid in ports {
+            self.event_data.insert(*nid, data.to_vec());
+            self.cache_store.invalidate_by(*nid);
+            for register in self.event_registers.get(nid).into_iter().flatten() {
+                self.cache_store.invalidate_of(*register);
+            }
+        }
+        true
+    }
+
+    /// Detaches all event data from event ports.
+    pub fn clear_event_data(&mut self) {
+        self.event_data.clear()
+    }
 }
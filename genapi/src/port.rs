@@ -2,6 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use std::convert::TryFrom;
+
 use super::{
     elem_type::ImmOrPNode,
     interface::{INode, IPort},
@@ -21,6 +23,12 @@ pub struct PortNode {
 }
 
 impl PortNode {
+    /// Returns `true` if the port is backed by event data, i.e. the port has `EventID`.
+    #[must_use]
+    pub fn is_event_port(&self) -> bool {
+        self.elem_base.event_id.is_some()
+    }
+
     #[must_use]
     pub fn chunk_id(&self) -> Option<&ImmOrPNode<u64>> {
         self.chunk_id.as_ref()
@@ -48,7 +56,7 @@ impl INode for PortNode {
 }
 
 impl IPort for PortNode {
-    #[tracing::instrument(skip(self, device, store),
+    #[tracing::instrument(skip(self, device, store, cx),
                           level = "trace",
                           fields(node = store.name_by_id(self.node_base().id()).unwrap()))]
     fn read<T: ValueStore, U: CacheStore>(
@@ -57,9 +65,11 @@ impl IPort for PortNode {
         buf: &mut [u8],
         device: &mut impl Device,
         store: &impl NodeStore,
-        _: &mut ValueCtxt<T, U>,
+        cx: &mut ValueCtxt<T, U>,
     ) -> GenApiResult<()> {
-        if self.chunk_id.is_some() {
+        if self.is_event_port() {
+            self.read_event_data(address, buf, cx)
+        } else if self.chunk_id.is_some() {
             Err(GenApiError::chunk_data_missing())
         } else {
             device
@@ -79,6 +89,10 @@ impl IPort for PortNode {
         store: &impl NodeStore,
         cx: &mut ValueCtxt<T, U>,
     ) -> GenApiResult<()> {
+        if self.is_event_port() {
+            return Err(GenApiError::not_writable());
+        }
+
         cx.invalidate_cache_by(self.node_base().id());
 
         if self.chunk_id.is_some() {
@@ -91,3 +105,116 @@ impl IPort for PortNode {
         }
     }
 }
+
+impl PortNode {
+    /// Event data is addressed from the beginning of the attached data.
+    fn read_event_data<T, U>(
+        &self,
+        address: i64,
+        buf: &mut [u8],
+        cx: &ValueCtxt<T, U>,
+    ) -> GenApiResult<()> {
+        let data = cx
+            .event_data(self.node_base().id())
+            .ok_or_else(GenApiError::event_data_missing)?;
+        let start = usize::try_from(address)
+            .map_err(|_| GenApiError::invalid_data("negative address for event data".into()))?;
+        let src = start
+            .checked_add(buf.len())
+            .and_then(|end| data.get(start..end))
+            .ok_or_else(|| {
+                GenApiError::invalid_buffer("the range exceeds the length of event data".into())
+            })?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::GenApiBuilder,
+        interface::IInteger,
+        store::{CacheSink, NodeStore},
+        utils::tests::register_description,
+        Device,
+    };
+
+    struct NoDevice;
+
+    impl Device for NoDevice {
+        fn read_mem(&mut self, _: i64, _: &mut [u8]) -> Result<(), Box<dyn std::error::Error>> {
+            Err("no device".into())
+        }
+
+        fn write_mem(&mut self, _: i64, _: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+            Err("no device".into())
+        }
+    }
+
+    const EVENT_NODES: &str = r#"
+    <IntReg Name="EventValue">
+        <Address>0x4</Address>
+        <Length>4</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>EventPort</pPort>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <IntReg Name="OutOfRange">
+        <Address>0x7FFFFFFFFFFFFFFF</Address>
+        <Length>4</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>EventPort</pPort>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <Port Name="EventPort">
+        <EventID>9003</EventID>
+    </Port>
+
+    <Port Name="Device" NameSpace="Standard">
+    </Port>
+"#;
+
+    #[test]
+    fn test_event_port() {
+        let (_, store, mut cx) = GenApiBuilder::default()
+            .build(&register_description(EVENT_NODES))
+            .unwrap();
+        let node = |name| {
+            store
+                .id_by_name(name)
+                .unwrap()
+                .expect_iinteger_kind(&store)
+                .unwrap()
+        };
+        let event_value = node("EventValue");
+        let out_of_range = node("OutOfRange");
+
+        assert!(event_value.value(&mut NoDevice, &store, &mut cx).is_err());
+        assert!(!cx.attach_event(0x9000, &[0; 8]));
+
+        assert!(cx.attach_event(0x9003, &[0, 0, 0, 0, 1, 0, 0, 0]));
+        assert_eq!(
+            event_value.value(&mut NoDevice, &store, &mut cx).unwrap(),
+            1
+        );
+        // The cache of the register is invalidated when a new event is attached.
+        assert!(cx.attach_event(0x9003, &[0, 0, 0, 0, 2, 0, 0, 0]));
+        assert_eq!(
+            event_value.value(&mut NoDevice, &store, &mut cx).unwrap(),
+            2
+        );
+
+        assert!(out_of_range.value(&mut NoDevice, &store, &mut cx).is_err());
+
+        // Event ports are kept when the cache store is replaced.
+        let mut cx = cx.map_cache_store(|_| CacheSink::default());
+        assert!(cx.attach_event(0x9003, &[0, 0, 0, 0, 3, 0, 0, 0]));
+        assert_eq!(
+            event_value.value(&mut NoDevice, &store, &mut cx).unwrap(),
+            3
+        );
+    }
+}
@@ -76,7 +76,7 @@ impl RegisterBase {
     ) -> GenApiResult<R> {
         let length = self.length(device, store, cx)?;
         let address = self.address(device, store, cx)?;
-        if let Some(cache) = cx.get_cache(nid, length, address) {
+        if let Some(cache) = cx.get_cache(nid, address, length) {
             f(cache)
         } else {
             let mut buf = vec![0; length as usize];
@@ -180,3 +180,70 @@ impl RegisterBase {
             && !matches!(self.access_mode(), AccessMode::RO))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::GenApiBuilder,
+        interface::IInteger,
+        store::{CacheStore, NodeStore},
+        utils::tests::{register_description, TestDevice},
+    };
+
+    const CACHE_NODES: &str = r#"
+    <IntReg Name="Cached">
+        <Address>0x10</Address>
+        <Length>4</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>Device</pPort>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <IntReg Name="NotCached">
+        <Address>0x20</Address>
+        <Length>4</Length>
+        <AccessMode>RO</AccessMode>
+        <pPort>Device</pPort>
+        <Cachable>NoCache</Cachable>
+        <Endianess>LittleEndian</Endianess>
+    </IntReg>
+
+    <Port Name="Device" NameSpace="Standard">
+    </Port>
+"#;
+
+    #[test]
+    fn test_read_with_cache() {
+        let (_, store, mut cx) = GenApiBuilder::default()
+            .build(&register_description(CACHE_NODES))
+            .unwrap();
+        let mut device = TestDevice::new(0x40);
+        device.memory[0x10..0x14].copy_from_slice(&1_u32.to_le_bytes());
+        device.memory[0x20..0x24].copy_from_slice(&2_u32.to_le_bytes());
+
+        let cached_nid = store.id_by_name("Cached").unwrap();
+        let cached = cached_nid.expect_iinteger_kind(&store).unwrap();
+        assert_eq!(cached.value(&mut device, &store, &mut cx).unwrap(), 1);
+        assert_eq!(device.reads, 1);
+
+        // The second read hits the cache even though the device memory is changed.
+        device.memory[0x10..0x14].copy_from_slice(&3_u32.to_le_bytes());
+        assert_eq!(cached.value(&mut device, &store, &mut cx).unwrap(), 1);
+        assert_eq!(device.reads, 1);
+
+        // The read misses the cache once it's invalidated.
+        cx.cache_store_mut().invalidate_of(cached_nid);
+        assert_eq!(cached.value(&mut device, &store, &mut cx).unwrap(), 3);
+        assert_eq!(device.reads, 2);
+
+        // `NoCache` register always reads the device.
+        let not_cached = store
+            .id_by_name("NotCached")
+            .unwrap()
+            .expect_iinteger_kind(&store)
+            .unwrap();
+        assert_eq!(not_cached.value(&mut device, &store, &mut cx).unwrap(), 2);
+        assert_eq!(not_cached.value(&mut device, &store, &mut cx).unwrap(), 2);
+        assert_eq!(device.reads, 4);
+    }
+}
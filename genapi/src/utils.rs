@@ -374,3 +374,71 @@ fn expr_from_nid<T: ValueStore, U: CacheStore>(
         ));
     })
 }
+
+#[cfg(test)]
+pub(super) mod tests {
+    use crate::Device;
+
+    /// A device backed by `memory`, which counts reads from the memory.
+    pub(crate) struct TestDevice {
+        pub(crate) memory: Vec<u8>,
+        pub(crate) reads: usize,
+    }
+
+    impl TestDevice {
+        pub(crate) fn new(len: usize) -> Self {
+            Self {
+                memory: vec![0; len],
+                reads: 0,
+            }
+        }
+    }
+
+    impl Device for TestDevice {
+        fn read_mem(
+            &mut self,
+            address: i64,
+            buf: &mut [u8],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let address = address as usize;
+            buf.copy_from_slice(&self.memory[address..address + buf.len()]);
+            self.reads += 1;
+            Ok(())
+        }
+
+        fn write_mem(
+            &mut self,
+            address: i64,
+            data: &[u8],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let address = address as usize;
+            self.memory[address..address + data.len()].copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    /// Wraps `nodes` in `RegisterDescription`.
+    pub(crate) fn register_description(nodes: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<RegisterDescription
+  ModelName="TestModel"
+  VendorName="VendorName"
+  StandardNameSpace="None"
+  SchemaMajorVersion="1"
+  SchemaMinorVersion="1"
+  SchemaSubMinorVersion="0"
+  MajorVersion="1"
+  MinorVersion="0"
+  SubMinorVersion="0"
+  ProductGuid="01234567-0123-0123-0123-0123456789ab"
+  VersionGuid="76543210-3210-3210-3210-ba9876543210"
+  xmlns="http://www.genicam.org/GenApi/Version_1_1"
+  xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+  xsi:schemaLocation="http://www.genicam.org/GenApi/Version_1_1 GenApiSchema_Version_1_1.xsd">
+{}</RegisterDescription>
+"#,
+            nodes
+        )
+    }
+}
@@ -19,7 +19,9 @@ impl From<MemoryError> for GenTlError {
         match err {
             MemoryError::AddressNotReadable | MemoryError::AddressNotWritable => Self::AccessDenied,
             MemoryError::InvalidAddress => Self::InvalidAddress,
-            MemoryError::InvalidRegisterData(cause) => Self::InvalidValue(cause),
+            MemoryError::InvalidRegisterData(cause) | MemoryError::InvalidSnapshot(cause) => {
+                Self::InvalidValue(cause)
+            }
         }
     }
 }
@@ -27,18 +29,23 @@ impl From<MemoryError> for GenTlError {
 impl From<ControlError> for GenTlError {
     fn from(err: ControlError) -> Self {
         use GenTlError::{
-            BufferTooSmall, InvalidValue, Io, NotInitialized, ResourceInUse, Timeout,
+            AccessDenied, BufferTooSmall, InvalidAddress, InvalidValue, Io, NotImplemented,
+            NotInitialized, ResourceInUse, Timeout,
         };
 
         match err {
             ControlError::Busy => ResourceInUse,
-            ControlError::Disconnected | ControlError::Io(..) | ControlError::InvalidDevice(..) => {
-                Io(err.into())
-            }
+            ControlError::Disconnected
+            | ControlError::Io(..)
+            | ControlError::InvalidDevice(..)
+            | ControlError::DeviceSpecific(..) => Io(err.into()),
             ControlError::NotOpened => NotInitialized,
             ControlError::InvalidData(..) => InvalidValue(format!("{}", err).into()),
             ControlError::Timeout => Timeout,
             ControlError::BufferTooSmall => BufferTooSmall,
+            ControlError::AccessDenied | ControlError::WriteProtect => AccessDenied,
+            ControlError::InvalidAddress | ControlError::BadAlignment => InvalidAddress,
+            ControlError::NotImplemented => NotImplemented,
         }
     }
 }
@@ -77,6 +77,7 @@ impl MemoryStruct {
 
     fn impl_methods(&self) -> TokenStream {
         let ident = &self.ident;
+        let vis = &self.vis;
         let new = self.impl_new();
         let fragments_len = self.fragments.len();
 
@@ -84,6 +85,51 @@ impl MemoryStruct {
             impl #ident {
                 #new
 
+                /// Returns a copy of the memory which has the same contents and access rights.
+                /// Registered observers are not copied.
+                #[allow(dead_code)]
+                #vis fn clone_without_observers(&self) -> Self {
+                    Self {
+                        raw: self.raw.clone(),
+                        protection: self.protection.clone(),
+                        observers: std::vec::Vec::new(),
+                    }
+                }
+
+                /// Returns a snapshot of the contents and access rights of the memory.
+                #[allow(dead_code)]
+                #vis fn snapshot(&self) -> cameleon_impl::memory::MemorySnapshot {
+                    cameleon_impl::memory::MemorySnapshot::new(self.raw.clone(), self.protection.clone())
+                }
+
+                /// Restore the contents and access rights of the memory from `snapshot`.
+                /// Registered observers are kept, and all of them are notified.
+                ///
+                /// # Errors
+                /// If the memory size of `snapshot` differs from the memory, then
+                /// [`MemoryError::InvalidSnapshot`](cameleon_impl::memory::MemoryError::InvalidSnapshot)
+                /// is returned.
+                #[allow(dead_code)]
+                #vis fn restore(&mut self, snapshot: cameleon_impl::memory::MemorySnapshot) -> cameleon_impl::memory::MemoryResult<()> {
+                    if snapshot.memory_size() != self.raw.len() {
+                        return Err(cameleon_impl::memory::MemoryError::InvalidSnapshot(
+                            format!(
+                                "memory size of the snapshot is {} bytes, but the memory size is {} bytes",
+                                snapshot.memory_size(),
+                                self.raw.len()
+                            )
+                            .into(),
+                        ));
+                    }
+
+                    let (raw, protection) = snapshot.into_parts();
+                    self.raw = raw;
+                    self.protection = protection;
+                    self.notify_all(0..self.raw.len());
+
+                    Ok(())
+                }
+
                 #[doc(hidden)]
                 fn notify_all(&self, written_range: std::ops::Range<usize>) {
 
@@ -23,6 +23,9 @@ pub enum MemoryError {
 
     #[error("invalid register data: {0}")]
     InvalidRegisterData(std::borrow::Cow<'static, str>),
+
+    #[error("invalid memory snapshot: {0}")]
+    InvalidSnapshot(std::borrow::Cow<'static, str>),
 }
 
 pub mod prelude {
@@ -148,6 +151,7 @@ impl AccessRight {
 }
 
 #[doc(hidden)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MemoryProtection {
     inner: Vec<u8>,
     memory_size: usize,
@@ -214,6 +218,149 @@ impl MemoryProtection {
     }
 }
 
+const SNAPSHOT_MAGIC: &[u8; 8] = b"CMLNMEM1";
+
+/// Contents and access rights of the whole memory defined by [`memory`].
+///
+/// A snapshot is taken by `snapshot` method of the memory, and restored by `restore` method.
+/// It can be saved to a file to start from a known memory state later.
+///
+/// # Examples
+///
+/// ```rust
+/// use cameleon_impl::memory::{memory, prelude::*, register_map, MemorySnapshot};
+///
+/// #[memory]
+/// pub struct Memory {
+///     abrm: ABRM,
+/// }
+///
+/// #[register_map(base = 0, endianness = LE)]
+/// pub enum ABRM {
+///     #[register(len = 2, access = RW, ty = u16)]
+///     Width = 640,
+/// }
+///
+/// let mut memory = Memory::new();
+/// memory.write::<ABRM::Width>(320).unwrap();
+///
+/// let mut file = vec![];
+/// memory.snapshot().write_to(&mut file).unwrap();
+///
+/// let mut restored = Memory::new();
+/// restored
+///     .restore(MemorySnapshot::read_from(file.as_slice()).unwrap())
+///     .unwrap();
+/// assert_eq!(restored.read::<ABRM::Width>().unwrap(), 320);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    raw: Vec<u8>,
+    protection: MemoryProtection,
+}
+
+impl MemorySnapshot {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn new(raw: Vec<u8>, protection: MemoryProtection) -> Self {
+        debug_assert_eq!(raw.len(), protection.memory_size);
+        Self { raw, protection }
+    }
+
+    #[doc(hidden)]
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<u8>, MemoryProtection) {
+        (self.raw, self.protection)
+    }
+
+    /// Size of the memory in bytes.
+    #[must_use]
+    pub fn memory_size(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Serialize the snapshot into `writer`.
+    ///
+    /// # Errors
+    /// If `writer` fails, then the error is returned.
+    pub fn write_to(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        use byteorder::{WriteBytesExt, LE};
+
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_u64::<LE>(self.raw.len() as u64)?;
+        writer.write_all(&self.raw)?;
+        writer.write_all(&self.protection.inner)
+    }
+
+    /// Deserialize a snapshot written by [`MemorySnapshot::write_to`] from `reader`.
+    ///
+    /// # Errors
+    /// If `reader` fails, then the error is returned.
+    /// If the data is not a snapshot, then the error of [`std::io::ErrorKind::InvalidData`] is
+    /// returned.
+    pub fn read_from(mut reader: impl std::io::Read) -> std::io::Result<Self> {
+        use byteorder::{ReadBytesExt, LE};
+        use std::{
+            convert::TryFrom,
+            io::{Error, ErrorKind},
+        };
+
+        let invalid_data = |msg| Error::new(ErrorKind::InvalidData, msg);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(invalid_data("not a memory snapshot"));
+        }
+
+        let memory_size = usize::try_from(reader.read_u64::<LE>()?)
+            .map_err(|_| invalid_data("memory size is too large"))?;
+        // Each byte of the protection holds access rights of 4 bytes of the memory.
+        let protection_len = memory_size / 4 + usize::from(memory_size % 4 != 0);
+        let snapshot_len = memory_size
+            .checked_add(protection_len)
+            .ok_or_else(|| invalid_data("memory size is too large"))?;
+
+        // Read the rest instead of allocating buffers of `memory_size` up front, so that a broken
+        // size doesn't cause a huge allocation.
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        if data.len() != snapshot_len {
+            return Err(invalid_data(
+                "length of the snapshot mismatches the memory size",
+            ));
+        }
+        let inner = data.split_off(memory_size);
+
+        Ok(Self {
+            raw: data,
+            protection: MemoryProtection { inner, memory_size },
+        })
+    }
+
+    /// Save the snapshot to the file at `path`. The file is created if it doesn't exist, and
+    /// truncated if it does.
+    ///
+    /// # Errors
+    /// If the file can't be created or written, then the error is returned.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut writer = std::io::BufWriter::new(file);
+        self.write_to(&mut writer)?;
+        std::io::Write::flush(&mut writer)
+    }
+
+    /// Load a snapshot saved by [`MemorySnapshot::save`] from the file at `path`.
+    ///
+    /// # Errors
+    /// Same as [`MemorySnapshot::read_from`], and if the file can't be opened, then the error
+    /// is returned.
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Self::read_from(std::io::BufReader::new(file))
+    }
+}
+
 pub trait Register {
     type Ty;
 
@@ -270,6 +417,35 @@ mod tests {
         assert_eq!(protection.access_right_with_range(3..5), NA);
     }
 
+    #[test]
+    fn test_snapshot() {
+        let mut protection = MemoryProtection::new(5);
+        protection.set_access_right_with_range(0..3, RO);
+        protection.set_access_right(4, WO);
+        let snapshot = MemorySnapshot::new(vec![1, 2, 3, 4, 5], protection);
+
+        let mut buf = vec![];
+        snapshot.write_to(&mut buf).unwrap();
+        assert_eq!(buf.len(), 8 + 8 + 5 + 2);
+        assert_eq!(MemorySnapshot::read_from(buf.as_slice()).unwrap(), snapshot);
+
+        // Truncated snapshot.
+        assert!(MemorySnapshot::read_from(&buf[..buf.len() - 1]).is_err());
+        // Broken magic.
+        buf[0] = 0;
+        assert!(MemorySnapshot::read_from(buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_with_huge_size() {
+        for memory_size in [u64::MAX, u64::MAX / 4 * 3, 1 << 40] {
+            let mut buf = SNAPSHOT_MAGIC.to_vec();
+            buf.extend_from_slice(&memory_size.to_le_bytes());
+            buf.extend_from_slice(&[0; 7]);
+            assert!(MemorySnapshot::read_from(buf.as_slice()).is_err());
+        }
+    }
+
     #[test]
     fn test_verify_address() {
         let protection = MemoryProtection::new(5);
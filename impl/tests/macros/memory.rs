@@ -118,4 +118,12 @@ fn main() {
     assert_eq!(memory.access_right::<SBRM::EIRMLength>(), AccessRight::NA);
 
     assert!(memory.read_raw(1000..1004).is_err());
+
+    // Test snapshot.
+    let snapshot = memory.snapshot();
+    let mut restored = Memory::new();
+    restored.restore(snapshot).unwrap();
+    assert_eq!(restored.read::<SBRM::TestI32>().unwrap(), 101);
+    assert_eq!(&restored.read::<ABRM::ManufacturerName>().unwrap(), "New name");
+    assert_eq!(restored.access_right::<SBRM::EIRMLength>(), AccessRight::NA);
 }